@@ -0,0 +1,240 @@
+// Compares the networks with the straightforward implementations they replaced,
+// run with `cargo bench`, criterion writes the reports in target/criterion
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use hopfield_nets::hop_net::batch_recall::{self, RecallOptions};
+use hopfield_nets::hop_net::classic_network::ClassicNetworkDiscrete;
+use hopfield_nets::hop_net::experiments;
+use hopfield_nets::hop_net::matrix;
+use hopfield_nets::hop_net::scalar::Scalar;
+use hopfield_nets::hop_net::storkey_learning::StorkeyLearningNetwork;
+use hopfield_nets::hop_net::{Net, UpdateMode};
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+const PATTERNS: usize = 5;
+
+fn random_pattern(size: usize) -> Vec<f64> {
+    let mut rng = rand::thread_rng();
+    (0..size)
+        .map(|_| if rng.gen_bool(0.5) { 1.0 } else { -1.0 })
+        .collect()
+}
+
+// Flips a tenth of the nodes, so that the network has something to do
+fn noisy(pattern: &[f64]) -> Vec<f64> {
+    let mut rng = rand::thread_rng();
+    pattern
+        .iter()
+        .map(|x| if rng.gen_bool(0.1) { -x } else { *x })
+        .collect()
+}
+
+// The learning rule as it was written before, with h recomputed 4 times for every pair of nodes,
+// and the unused pseudo inverse term computed for every pattern
+fn naive_storkey(weights: &mut [Vec<f64>], state: &[f64]) {
+    let old_weights = weights.to_vec();
+    let len = state.len();
+    let h = |i: usize, j: usize| -> f64 {
+        let mut sum = 0.0;
+        for k in 0..len {
+            if k != j || k != i {
+                sum += old_weights[i][k] * state[k];
+            }
+        }
+        sum
+    };
+    let mut c = 0.0;
+    for i in 0..len {
+        for j in 0..len {
+            c += state[i] * old_weights[i][j] * state[j];
+        }
+    }
+    black_box(1.0 - c / len as f64);
+
+    for i in 0..len {
+        for j in 0..len {
+            let state_product = state[i] * state[j];
+            let noise_reduction = state[i] * h(j, i) + state[j] * h(i, j);
+            black_box(h(i, j) * h(j, i));
+            weights[i][j] = old_weights[i][j] + (state_product - noise_reduction) / len as f64;
+        }
+    }
+}
+
+// A whole asynchronous sweep as it was done before, every step sums a full row and copies the state
+fn naive_sweep(weights: &[Vec<f64>], state: &mut [f64]) -> Vec<f64> {
+    let mut last = Vec::new();
+    for i in 0..state.len() {
+        let sum: f64 = weights[i]
+            .iter()
+            .zip(state.iter())
+            .map(|(w, s)| w * s)
+            .sum();
+        state[i] = if sum > 0.0 { 1.0 } else { -1.0 };
+        last = state.to_vec();
+    }
+    last
+}
+
+fn to_i8(pattern: &[f64]) -> Vec<i8> {
+    pattern.iter().map(|x| *x as i8).collect()
+}
+
+// Setting the state recomputes all the fields, so it's left out of the measured time
+fn timed_from<T: Scalar>(net: &mut dyn Net<T>, probe: &[T], iters: u64, steps: usize) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        net.set_state(probe).unwrap();
+        let start = Instant::now();
+        for _ in 0..steps {
+            black_box(net.step());
+        }
+        total += start.elapsed();
+    }
+    total
+}
+
+fn storkey_learning(c: &mut Criterion) {
+    let mut group = c.benchmark_group("storkey_learning");
+    group.sample_size(10);
+    for size in [64, 256] {
+        let pattern = random_pattern(size);
+        group.bench_with_input(BenchmarkId::new("naive", size), &size, |b, &size| {
+            let mut weights = vec![vec![0.0; size]; size];
+            b.iter(|| naive_storkey(&mut weights, black_box(&pattern)));
+        });
+        group.bench_with_input(BenchmarkId::new("network", size), &size, |b, &size| {
+            let mut net = StorkeyLearningNetwork::new(size, None).unwrap();
+            b.iter(|| net.learn(black_box(&pattern)));
+        });
+    }
+    // Too slow for the naive version
+    let size = 2048;
+    let pattern = random_pattern(size);
+    group.bench_with_input(BenchmarkId::new("network", size), &size, |b, &size| {
+        let mut net = StorkeyLearningNetwork::new(size, None).unwrap();
+        b.iter(|| net.learn(black_box(&pattern)));
+    });
+    group.finish();
+}
+
+fn asynchronous_sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("asynchronous_sweep");
+    for size in [1024, 4096] {
+        let patterns: Vec<Vec<f64>> = (0..PATTERNS).map(|_| random_pattern(size)).collect();
+        let probe = noisy(&patterns[0]);
+
+        let mut weights = vec![vec![0.0; size]; size];
+        for pattern in patterns.iter() {
+            for i in 0..size {
+                for j in 0..size {
+                    if i != j {
+                        weights[i][j] += pattern[i] * pattern[j];
+                    }
+                }
+            }
+        }
+        group.bench_with_input(BenchmarkId::new("naive", size), &size, |b, _| {
+            b.iter_batched(
+                || probe.clone(),
+                |mut state| naive_sweep(&weights, &mut state),
+                BatchSize::SmallInput,
+            );
+        });
+
+        let mut net = ClassicNetworkDiscrete::new(size, None).unwrap();
+        for pattern in patterns.iter() {
+            net.learn(pattern);
+        }
+        group.bench_with_input(BenchmarkId::new("network", size), &size, |b, &size| {
+            b.iter_custom(|iters| timed_from(&mut net, &probe, iters, size));
+        });
+
+        // The same network with 8 times smaller weights
+        let mut net = ClassicNetworkDiscrete::<i8>::new(size, None).unwrap();
+        for pattern in patterns.iter() {
+            net.learn(&to_i8(pattern));
+        }
+        let probe = to_i8(&probe);
+        group.bench_with_input(BenchmarkId::new("network_i8", size), &size, |b, &size| {
+            b.iter_custom(|iters| timed_from(&mut net, &probe, iters, size));
+        });
+    }
+    group.finish();
+}
+
+fn synchronous_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synchronous_step");
+    for size in [1024, 4096] {
+        let mut net = ClassicNetworkDiscrete::new(size, None).unwrap();
+        for _ in 0..PATTERNS {
+            net.learn(&random_pattern(size));
+        }
+        net.set_update_mode(UpdateMode::Synchronous);
+        let probe = random_pattern(size);
+        group.bench_with_input(BenchmarkId::new("network", size), &size, |b, _| {
+            b.iter_custom(|iters| timed_from(&mut net, &probe, iters, 1));
+        });
+    }
+    group.finish();
+}
+
+fn batch_recall(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch_recall");
+    group.sample_size(10);
+    let size = 1024;
+    let patterns: Vec<Vec<f64>> = (0..PATTERNS).map(|_| random_pattern(size)).collect();
+    let probes: Vec<Vec<f64>> = patterns.iter().cycle().take(64).map(|p| noisy(p)).collect();
+    let mut net = ClassicNetworkDiscrete::new(size, None).unwrap();
+    for pattern in patterns.iter() {
+        net.learn(pattern);
+    }
+
+    group.bench_function("one_at_a_time", |b| {
+        b.iter(|| {
+            for probe in probes.iter() {
+                black_box(
+                    experiments::recall(&mut net, probe, experiments::MAX_RECALL_SWEEPS).unwrap(),
+                );
+            }
+        })
+    });
+    let weights = net.get_weights().into_owned();
+    group.bench_function("batch", |b| {
+        b.iter(|| {
+            batch_recall::batch_recall(&weights, &probes, &patterns, RecallOptions::default())
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn kernels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dot");
+    let size = 4096;
+    let a = random_pattern(size);
+    let b_vec = random_pattern(size);
+    group.bench_function("iterator", |b| {
+        b.iter(|| {
+            black_box(&a)
+                .iter()
+                .zip(black_box(&b_vec).iter())
+                .map(|(x, y)| x * y)
+                .sum::<f64>()
+        })
+    });
+    group.bench_function("kernel", |b| {
+        b.iter(|| matrix::dot(black_box(&a), black_box(&b_vec)))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    storkey_learning,
+    asynchronous_sweep,
+    synchronous_step,
+    batch_recall,
+    kernels
+);
+criterion_main!(benches);
@@ -1,72 +1,171 @@
 // pub mod of all the modules to make the compiler happy
 pub mod central_panel;
+pub mod comparison_panel;
+pub mod damage_panel;
+pub mod diagnostics_panel;
+pub mod enumeration_panel;
+pub mod error_banner;
+pub mod history;
 pub mod hop_net;
+pub mod optimization_panel;
+pub mod pattern_list;
+pub mod shortcuts;
 pub mod side_panel;
 pub mod thread_utils;
 pub mod utilities;
 
 // Actually used stuff
+use hop_net::network_error::NetworkError;
 use hop_net::NetworkCommand;
 use hop_net::NetworkResponse;
+use shortcuts::Action;
 use std::sync::mpsc;
 
-/// We derive Deserialize/Serialize so we can persist app state on shutdown.
+// A network that sends more than this between two frames gets the rest of its messages handled in the next ones
+const MAX_MESSAGES_PER_FRAME: usize = 10_000;
+
+/// What the app remembers between runs, the network itself starts from scratch every time.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
+pub struct Settings {
+    net_type: hop_net::NetworkType,
+    // The sides of the square grids of the two layers, the second is used only by the networks that have it
+    state_side: usize,
+    output_side: usize,
+    // Only the shortcuts of the actions listed here differ from the default ones
+    shortcuts: Vec<(Action, Option<shortcuts::Shortcut>)>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            net_type: hop_net::NetworkType::SquareDiscrete,
+            state_side: 9,
+            output_side: 5,
+            shortcuts: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    // The settings could come from an older version, or a file edited by hand. The sizes the side panel
+    // wouldn't accept go back to the default ones, before the network is built with them
+    fn validated(self) -> Self {
+        let default = Settings::default();
+        let valid_side =
+            |side: usize| (side_panel::MIN_SIDE..=side_panel::MAX_SIDE).contains(&side);
+        Self {
+            state_side: if valid_side(self.state_side) {
+                self.state_side
+            } else {
+                default.state_side
+            },
+            output_side: if valid_side(self.output_side) {
+                self.output_side
+            } else {
+                default.output_side
+            },
+            ..self
+        }
+    }
+}
+
 pub struct HopfiledNetsApp {
-    #[serde(skip)]
     central_panel: central_panel::CentralPanel,
-    #[serde(skip)]
     side_panel: side_panel::SidePanel,
+    pattern_list: pattern_list::PatternList,
+    optimization_panel: optimization_panel::OptimizationPanel,
+    damage_panel: damage_panel::DamagePanel,
+    diagnostics_panel: diagnostics_panel::DiagnosticsPanel,
+    enumeration_panel: enumeration_panel::EnumerationPanel,
+    comparison_panel: comparison_panel::ComparisonPanel,
+    error_banner: error_banner::ErrorBanner,
+    history: history::History,
+    shortcuts: shortcuts::Shortcuts,
+
+    net: thread_utils::NetworkHandle,
 
-    #[serde(skip)]
-    send_to_net: mpsc::Sender<NetworkCommand>,
-    #[serde(skip)]
-    recieve_from_net: mpsc::Receiver<NetworkResponse>,
-
-    #[serde(skip)]
     // This attirbute is not really necessary, but it makes life a little simpler.
     net_stepping: bool,
 
-    #[serde(skip)]
     saved_state: Vec<f64>,
 
-    #[serde(skip)]
+    net_type: hop_net::NetworkType,
+
+    // True while the network is an optimization network, instead of one of the selectable types
+    solving_problem: bool,
+
     n: u64,
 }
 
 impl Default for HopfiledNetsApp {
     fn default() -> Self {
-        let (main_send, net_recieve) = mpsc::channel::<NetworkCommand>();
-        let (net_send, main_recieve) = mpsc::channel::<NetworkResponse>();
-
-        let state_size = 9;
-        let start_state = vec![-1.0; state_size * state_size];
-        let std_net_type = hop_net::NetworkType::SquareDiscrete;
-
-        let side_panel = side_panel::SidePanel::new(std_net_type, state_size);
+        Self::with_settings(Settings::default())
+    }
+}
 
-        thread_utils::start_net_thread(
-            hop_net::NetworkType::SquareDiscrete,
+impl HopfiledNetsApp {
+    fn with_settings(settings: Settings) -> Self {
+        let settings = settings.validated();
+        let net_type = settings.net_type;
+        let mut side_panel = side_panel::SidePanel::new(net_type, settings.state_side);
+        side_panel.set_output_layer_size(settings.output_side);
+        let layer_sizes = side_panel.get_layer_sizes(net_type);
+        let start_state = vec![-1.0; layer_sizes.iter().sum()];
+
+        let net = thread_utils::NetworkHandle::spawn(
+            net_type,
             start_state.clone(),
-            state_size,
-            net_send,
-            net_recieve,
+            layer_sizes.clone(),
+            side_panel.get_stepping_speed(),
         );
 
-        Self {
-            central_panel: central_panel::CentralPanel::new(std_net_type, &start_state),
+        let mut central_panel = central_panel::CentralPanel::new(net_type, &start_state);
+        central_panel.set_layer_sizes(layer_sizes);
+        let mut comparison_panel = comparison_panel::ComparisonPanel::new();
+        comparison_panel.set_net_type(net_type);
+
+        let mut app = Self {
+            central_panel,
             side_panel,
-            send_to_net: main_send,
-            recieve_from_net: main_recieve,
+            pattern_list: pattern_list::PatternList::new(),
+            optimization_panel: optimization_panel::OptimizationPanel::new(),
+            damage_panel: damage_panel::DamagePanel::new(),
+            diagnostics_panel: diagnostics_panel::DiagnosticsPanel::new(),
+            enumeration_panel: enumeration_panel::EnumerationPanel::new(),
+            comparison_panel,
+            error_banner: error_banner::ErrorBanner::new(),
+            history: history::History::new(),
+            shortcuts: shortcuts::Shortcuts::new(&settings.shortcuts),
+            net,
             net_stepping: false,
             saved_state: start_state,
+            net_type,
+            solving_problem: false,
             n: 0,
+        };
+        app.set_shortcut_hints();
+        app
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            net_type: self.net_type,
+            state_side: self.side_panel.get_state_side(),
+            output_side: self.side_panel.get_output_layer_side(),
+            shortcuts: self.shortcuts.get_bindings(),
         }
     }
-}
 
-impl HopfiledNetsApp {
+    // The panels show the shortcuts in the tooltips of their buttons
+    fn set_shortcut_hints(&mut self) {
+        let hints = self.shortcuts.hints();
+        self.side_panel.set_shortcut_hints(hints.clone());
+        self.pattern_list.set_shortcut_hints(hints.clone());
+        self.history.set_shortcut_hints(hints.clone());
+        self.central_panel.set_shortcut_hints(hints);
+    }
+
     /// Called once before the first frame.
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // This is also where you can customize the look and feel of egui using
@@ -75,68 +174,292 @@ impl HopfiledNetsApp {
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
         if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            let settings = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            return Self::with_settings(settings);
         }
 
         Default::default()
     }
 
-    fn process_net_mss(&self) -> NetworkResponse {
-        let mess = self.recieve_from_net.try_recv();
-        // We check to see if the channel is still open and if there are new states to render.
-        if let Err(e) = mess {
-            if e == mpsc::TryRecvError::Disconnected {
-                panic!("Net thread closed unexpectedly");
-            } else {
-                return NetworkResponse::None;
-            }
+    // Sends the new layout to the network, and resets everything that depended on the old one
+    fn change_layout(&mut self) {
+        self.net_stepping = false;
+        let layer_sizes = self.side_panel.get_layer_sizes(self.net_type);
+        let new_state = vec![-1.0; layer_sizes.iter().sum()];
+        self.saved_state = new_state.clone();
+        self.send_command(NetworkCommand::Stop);
+        let command = NetworkCommand::SetLayerSizes(layer_sizes.clone());
+        self.send_command(command);
+        self.send_command(NetworkCommand::SetState(new_state.clone()));
+        self.central_panel.set_layer_sizes(layer_sizes);
+        self.central_panel.set_net_state(new_state);
+        // The network forgets everything when the size changes
+        self.pattern_list.clear();
+        self.diagnostics_panel.clear_report();
+        self.enumeration_panel.clear_result();
+        self.solving_problem = false;
+        self.side_panel.set_is_solving(false);
+        self.optimization_panel.stop_evaluating();
+    }
+
+    // Replaces the network with the one built from the problem selected in the optimization panel
+    fn start_solving(&mut self) {
+        let config = match self.optimization_panel.get_solver_config() {
+            Some(config) => config,
+            None => return,
+        };
+
+        self.net_stepping = false;
+        self.side_panel.set_is_stepping(false);
+        let size = config.problem.neuron_count();
+        self.side_panel.set_state_size(config.problem.side());
+        self.send_command(NetworkCommand::Stop);
+        self.send_command(NetworkCommand::Solve(config));
+
+        // The network starts from a random state, we show an empty one until it arrives
+        let placeholder = vec![-1.0; size];
+        self.saved_state = placeholder.clone();
+        self.central_panel.set_layer_sizes(vec![size]);
+        self.central_panel.set_net_state(placeholder);
+        self.pattern_list.clear();
+        self.diagnostics_panel.clear_report();
+        self.enumeration_panel.clear_result();
+        self.solving_problem = true;
+        self.side_panel.set_is_solving(true);
+        self.history.clear();
+    }
+
+    fn snapshot(&self) -> history::Snapshot {
+        history::Snapshot {
+            net_type: self.net_type,
+            layer_sizes: self.central_panel.get_layer_sizes().to_vec(),
+            patterns: self.pattern_list.get_patterns().to_vec(),
+            state: self.central_panel.get_net_state(),
         }
-        mess.unwrap()
     }
 
-    // TODO Test if this version of the function looks better
-    /*
-    fn process_net_mss(&self) -> NetworkResponse {
-        let mut last_value = self.recieve_from_net.try_recv();
-        // We check to see if the channel is still open and if there are new states to render.
-        if let Err(e) = last_value {
-            if e == mpsc::TryRecvError::Disconnected {
-                panic!("Net thread closed unexpectedly");
-            } else {
-                return NetworkResponse::None;
+    // Brings the app back to a snapshot of the history. The snapshots don't have the weights, so when the type
+    // or the patterns change the network builds them again from the patterns: any damage, dream, symmetrization
+    // or cleared diagonal is lost, even the ones done before the snapshot was taken
+    fn restore_snapshot(&mut self, snapshot: history::Snapshot) {
+        self.net_stepping = false;
+        self.side_panel.set_is_stepping(false);
+        self.send_command(NetworkCommand::Stop);
+
+        let type_changed = snapshot.net_type != self.net_type;
+        let patterns_changed = snapshot.patterns != self.pattern_list.get_patterns();
+        if type_changed {
+            // The new network would learn the old patterns, only to forget them right after
+            if patterns_changed {
+                self.send_command(NetworkCommand::ResetWeights);
+            }
+            self.net_type = snapshot.net_type;
+            self.side_panel.set_selected_network(snapshot.net_type);
+            self.side_panel.set_net_type(snapshot.net_type);
+            self.central_panel.set_net_type(snapshot.net_type);
+            self.comparison_panel.set_net_type(snapshot.net_type);
+            self.send_command(NetworkCommand::ChangeNetType(snapshot.net_type));
+            if snapshot.net_type.is_sequence() {
+                self.side_panel
+                    .set_update_mode(hop_net::UpdateMode::Synchronous);
             }
+            self.diagnostics_panel.clear_report();
+            self.enumeration_panel.clear_result();
         }
 
-        let mut new_value = self.recieve_from_net.try_recv();
+        // The sizes in the side panel are set back too, so that the next change starts from them
+        let side = |size: usize| (size as f32).sqrt() as usize;
+        self.side_panel
+            .set_state_size(side(snapshot.layer_sizes[0]));
+        if let Some(output_size) = snapshot.layer_sizes.get(1) {
+            self.side_panel.set_output_layer_size(side(*output_size));
+        }
+        if snapshot.layer_sizes != self.central_panel.get_layer_sizes() {
+            let command = NetworkCommand::SetLayerSizes(snapshot.layer_sizes.clone());
+            self.send_command(command);
+            self.central_panel
+                .set_layer_sizes(snapshot.layer_sizes.clone());
+            self.saved_state = vec![-1.0; snapshot.layer_sizes.iter().sum()];
+            self.pattern_list.clear();
+            self.diagnostics_panel.clear_report();
+            self.enumeration_panel.clear_result();
+            self.solving_problem = false;
+            self.side_panel.set_is_solving(false);
+            self.optimization_panel.stop_evaluating();
+        }
 
-        // If the network thread is procusing new states faster than we can render them
-        // we stat skipping some, we don't want to skip indefinitely, because doing so
-        // we risk having the main thread stuck stuck skipping stuff, and killing the responsiveness
-        for _i in 0..1_000 {
-            if new_value.is_err()  {
-                return last_value.unwrap();
+        if patterns_changed {
+            self.pattern_list.clear();
+            for pattern in snapshot.patterns.iter() {
+                self.pattern_list
+                    .push(pattern.clone(), &snapshot.layer_sizes);
             }
-            last_value = self.recieve_from_net.try_recv();
-            if last_value.is_err() {
-                return new_value.unwrap();
+            self.send_command(NetworkCommand::LearnAll(snapshot.patterns.clone()));
+        }
+        if (type_changed || patterns_changed) && !snapshot.patterns.is_empty() {
+            self.side_panel.start_training(self.net_type);
+        }
+
+        self.send_command(NetworkCommand::SetState(snapshot.state.clone()));
+        self.central_panel.set_net_state(snapshot.state);
+    }
+
+    // Sends a command to the network, if the thread is gone a new one takes its place
+    fn send_command(&mut self, command: NetworkCommand) {
+        // The highlighted node is the last one the network updated by itself, a state that comes from anywhere else replaces it
+        if matches!(
+            command,
+            NetworkCommand::SetState(_)
+                | NetworkCommand::Go
+                | NetworkCommand::StepSweep
+                | NetworkCommand::Converge
+                | NetworkCommand::SetLayerSizes(_)
+                | NetworkCommand::ChangeNetType(_)
+                | NetworkCommand::Solve(_)
+        ) {
+            self.central_panel.clear_node_update();
+        }
+        if thread_utils::changes_weights(&command) {
+            self.central_panel.weights_changed();
+        }
+        if !self.net.send(command) {
+            self.restart_net_thread();
+        }
+    }
+
+    // The network thread recovers from its own crashes, so this happens only if even that failed.
+    // The weights are lost with the thread, the new network learns the stored patterns again
+    fn restart_net_thread(&mut self) {
+        let state = self.central_panel.get_net_state();
+        self.net = thread_utils::NetworkHandle::spawn(
+            self.net_type,
+            state.clone(),
+            self.central_panel.get_layer_sizes().to_vec(),
+            self.side_panel.get_stepping_speed(),
+        );
+        self.net_stepping = false;
+        self.side_panel.set_is_stepping(false);
+        self.cancel_jobs();
+        self.error_banner.push(NetworkError::ThreadRestarted);
+
+        // The new thread knows only its starting state, everything else has to be sent again.
+        // If it fails too there is nothing left to try, the next command will start yet another thread
+        let mut commands = vec![
+            NetworkCommand::SetPrecision(self.side_panel.get_precision()),
+            NetworkCommand::SetLearningRule(self.side_panel.get_learning_rule()),
+            NetworkCommand::SetUpdateMode(self.side_panel.get_update_mode()),
+            NetworkCommand::SetSpeed(self.side_panel.get_stepping_speed()),
+        ];
+        commands.push(NetworkCommand::LearnAll(
+            self.pattern_list.get_patterns().to_vec(),
+        ));
+        commands.push(NetworkCommand::SetState(state));
+        for command in commands {
+            if !self.net.send(command) {
+                println!("The new network thread closed too");
+                return;
             }
-            new_value = self.recieve_from_net.try_recv();
-            println!("Skip");
         }
+        self.central_panel.weights_changed();
+
+        // The optimization network is built from the problem, not from the patterns
+        if self.solving_problem {
+            self.start_solving();
+        }
+    }
 
-        if let Ok(v) = new_value {
-            v
-        } else {
-            last_value.unwrap()
+    // The jobs the network was busy with won't send their results anymore
+    fn cancel_jobs(&mut self) {
+        self.side_panel.cancel_jobs();
+        self.damage_panel.cancel();
+        self.enumeration_panel.cancel();
+    }
+
+    fn process_net_mss(&mut self) -> NetworkResponse {
+        let mess = self.net.try_recv();
+        // We check to see if the channel is still open and if there are new states to render.
+        if let Err(e) = mess {
+            if e == mpsc::TryRecvError::Disconnected {
+                println!("Net thread closed unexpectedly");
+                self.restart_net_thread();
+            }
+            return NetworkResponse::None;
+        }
+        mess.unwrap()
+    }
+
+    // Everything but the states, those are put together before being drawn
+    fn handle_response(&mut self, response: NetworkResponse) {
+        match response {
+            NetworkResponse::CycleDetected(cycle) => {
+                println!("Main thread: cycle of period {} detected", cycle.period);
+                self.central_panel.set_cycle(cycle);
+            }
+            NetworkResponse::ExperimentProgress(done, total) => {
+                self.damage_panel.set_progress(done, total);
+            }
+            NetworkResponse::ExperimentResult(points) => {
+                self.damage_panel.set_results(points);
+            }
+            NetworkResponse::Diagnostics(report) => {
+                self.diagnostics_panel.set_report(report);
+            }
+            NetworkResponse::EnumerationProgress(done, total) => {
+                self.enumeration_panel.set_progress(done, total);
+            }
+            NetworkResponse::EnumerationResult(result) => {
+                self.enumeration_panel.set_result(result);
+            }
+            NetworkResponse::EnumerationAvailability(reason) => {
+                self.enumeration_panel.set_unavailable_reason(reason);
+            }
+            NetworkResponse::Error(error) => {
+                // The training or the dreams that were refused won't report their progress
+                if matches!(
+                    error,
+                    NetworkError::Crashed { .. }
+                        | NetworkError::RuleNeedsFloats { .. }
+                        | NetworkError::NotUnlearnable(_)
+                ) {
+                    self.cancel_jobs();
+                }
+                self.error_banner.push(error);
+            }
+            NetworkResponse::Landscape(landscape) => {
+                self.central_panel.set_landscape(landscape);
+            }
+            NetworkResponse::NodeUpdated(update) => {
+                self.central_panel.set_node_update(update);
+            }
+            NetworkResponse::LocalFields(state, fields) => {
+                self.central_panel.set_local_fields(state, fields);
+            }
+            NetworkResponse::PatternStability(unstable_bits) => {
+                self.pattern_list.set_unstable_bits(&unstable_bits);
+            }
+            NetworkResponse::TrainingProgress(progress) => {
+                self.side_panel.set_training_progress(progress);
+            }
+            NetworkResponse::UnlearningProgress(done, total) => {
+                self.side_panel.set_unlearning_progress(done, total);
+            }
+            NetworkResponse::UnlearningResult(report) => {
+                self.side_panel.set_unlearning_report(report);
+            }
+            NetworkResponse::Stopped => {
+                println!("Main thread: net stopped");
+                self.net_stepping = false;
+                self.side_panel.set_is_stepping(false);
+            }
+            _ => {}
         }
     }
-    */
 }
 
 impl eframe::App for HopfiledNetsApp {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        eframe::set_value(storage, eframe::APP_KEY, &self.settings());
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
@@ -144,53 +467,162 @@ impl eframe::App for HopfiledNetsApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         //------------------------------Updating the UI components------------------------------
 
-        // We check to see if the net has generated a new states only if we know that the net is genrating.
-        match self.process_net_mss() {
-            NetworkResponse::NewState(s) => {
-                self.central_panel.set_net_state(s);
-            }
-            NetworkResponse::Stopped => {
-                println!("Main thread: net stopped");
-                self.side_panel.set_is_stepping(false);
+        // Everything the network sent since the last frame is handled, but only the last of the states is drawn
+        let mut latest_state: Option<Vec<f64>> = None;
+        for _ in 0..MAX_MESSAGES_PER_FRAME {
+            match self.process_net_mss() {
+                NetworkResponse::None => break,
+                NetworkResponse::NewState(state) => latest_state = Some(state),
+                NetworkResponse::StateDelta(changes) => {
+                    let state =
+                        latest_state.get_or_insert_with(|| self.central_panel.get_net_state());
+                    for (i, value) in changes {
+                        if let Some(node) = state.get_mut(i) {
+                            *node = value;
+                        }
+                    }
+                }
+                response => self.handle_response(response),
             }
-            _ => {}
+        }
+        if let Some(state) = latest_state {
+            self.central_panel.set_net_state(state);
         }
 
-        if self.side_panel.has_state_size_changed() {
-            self.net_stepping = false;
-            let new_state = vec![-1.0; self.side_panel.get_state_size()];
-            self.saved_state = new_state.clone();
-            if self.send_to_net.send(NetworkCommand::Stop).is_err() {
-                println!("Error sending stop command to net");
+        // The actions asked from the keyboard or from the palette do the same as their buttons
+        let actions = self.shortcuts.triggered_actions().to_vec();
+        let triggered = |action| actions.contains(&action);
+        if self.shortcuts.have_bindings_changed() {
+            self.set_shortcut_hints();
+        }
+
+        if self.history.undo_pressed() || triggered(Action::Undo) {
+            if let Some(snapshot) = self.history.undo(self.snapshot()) {
+                self.restore_snapshot(snapshot);
             }
-            if self
-                .send_to_net
-                .send(NetworkCommand::SetState(new_state.clone()))
-                .is_err()
-            {
-                println!("Error sending set state command to net");
+        }
+
+        if self.history.redo_pressed() || triggered(Action::Redo) {
+            if let Some(snapshot) = self.history.redo(self.snapshot()) {
+                self.restore_snapshot(snapshot);
             }
-            self.central_panel.set_net_state(new_state);
+        }
+
+        if self.side_panel.has_state_size_changed()
+            || (self.net_type.has_two_layers() && self.side_panel.has_output_layer_size_changed())
+        {
+            self.history.record("Change the size", self.snapshot());
+            self.change_layout();
         }
 
         // We save what the user is seeing (it may be different from what the network actually is)
-        if self.side_panel.save_current_state() {
+        if self.side_panel.save_current_state() || triggered(Action::SaveState) {
             self.saved_state = self.central_panel.get_net_state();
         }
 
-        if self.side_panel.load_saved_state() {
+        if self.side_panel.load_saved_state() || triggered(Action::ResetState) {
             self.net_stepping = false;
-            if self.send_to_net.send(NetworkCommand::Stop).is_err() {
-                panic!("The network is not running");
-            }
+            self.send_command(NetworkCommand::Stop);
 
             let command = NetworkCommand::SetState(self.saved_state.clone());
-            if self.send_to_net.send(command).is_err() {
-                panic!("The network is not running");
-            }
+            self.send_command(command);
             self.central_panel.set_net_state(self.saved_state.clone());
         }
 
+        let pattern_key = actions.iter().find_map(|action| match action {
+            Action::LoadPattern(i) => self.pattern_list.get_patterns().get(*i).cloned(),
+            _ => None,
+        });
+        if let Some(pattern) = self.pattern_list.pattern_to_load().cloned().or(pattern_key) {
+            self.net_stepping = false;
+            self.side_panel.set_is_stepping(false);
+            self.send_command(NetworkCommand::Stop);
+
+            let command = NetworkCommand::SetState(pattern.clone());
+            self.send_command(command);
+            self.central_panel.set_net_state(pattern);
+        }
+
+        if self.optimization_panel.solve_pressed() {
+            self.start_solving();
+        }
+
+        if self.damage_panel.apply_pressed() {
+            let command = NetworkCommand::Damage(self.damage_panel.get_damage());
+            self.send_command(command);
+        }
+
+        if self.damage_panel.restore_pressed() {
+            self.send_command(NetworkCommand::RestoreWeights);
+        }
+
+        // The experiment uses the network to recall the probes, so it can't be stepping at the same time
+        if self.damage_panel.run_pressed() {
+            self.net_stepping = false;
+            self.side_panel.set_is_stepping(false);
+            self.send_command(NetworkCommand::Stop);
+            let command = NetworkCommand::RunDamageExperiment(self.damage_panel.get_experiment());
+            self.send_command(command);
+            self.damage_panel
+                .set_progress(0, self.damage_panel.get_experiment().levels);
+        }
+
+        if self.diagnostics_panel.check_pressed() {
+            self.send_command(NetworkCommand::RunDiagnostics);
+        }
+
+        // The network answers with a new report, so that the effect of the fix can be seen
+        if self.diagnostics_panel.symmetrize_pressed() {
+            self.send_command(NetworkCommand::Symmetrize);
+        }
+
+        if self.diagnostics_panel.clear_diagonal_pressed() {
+            self.send_command(NetworkCommand::ClearDiagonal);
+        }
+
+        // The network doesn't step while the states are enumerated, the fields are computed on the side
+        if self.enumeration_panel.enumerate_pressed() {
+            self.net_stepping = false;
+            self.side_panel.set_is_stepping(false);
+            self.send_command(NetworkCommand::Stop);
+            self.send_command(NetworkCommand::EnumerateStates);
+            self.enumeration_panel.set_progress(0, 1);
+        }
+
+        if let Some(state) = self.enumeration_panel.state_to_load() {
+            self.net_stepping = false;
+            self.side_panel.set_is_stepping(false);
+            self.send_command(NetworkCommand::Stop);
+            let command = NetworkCommand::SetState(state.clone());
+            self.send_command(command);
+            self.central_panel.set_net_state(state);
+        }
+
+        // The networks of the comparison learn what the network of the app knows, and start from what the user sees
+        if self.comparison_panel.start_pressed() {
+            self.comparison_panel.start(
+                self.pattern_list.get_patterns(),
+                self.central_panel.get_net_state(),
+                self.central_panel.get_layer_sizes().to_vec(),
+                self.side_panel.get_update_mode(),
+            );
+        }
+
+        self.central_panel
+            .set_stored_patterns(self.pattern_list.get_patterns());
+        if let Some(plane) = self.central_panel.landscape_request() {
+            let command = NetworkCommand::ComputeLandscape(plane);
+            self.send_command(command);
+        }
+
+        if let Some(state) = self.central_panel.local_fields_request() {
+            self.send_command(NetworkCommand::ComputeLocalFields(state));
+        }
+
+        if triggered(Action::AddNoise) {
+            self.central_panel.add_noise();
+        }
+
         //If the user cahnged the nodes dimention through the slider, we update the gui.
         if self.side_panel.has_node_dim_changed() {
             self.central_panel
@@ -200,60 +632,165 @@ impl eframe::App for HopfiledNetsApp {
         // If the user editd the network state through the gui, we update the network.
         // The right way to do this is to save just the indices of the nodes that have changed, and then update only them
         // I'll rework this part for sure
+        if let Some(state) = self.central_panel.finished_edit() {
+            let before = history::Snapshot {
+                state,
+                ..self.snapshot()
+            };
+            self.history.record("Edit the state", before);
+        }
         if self.central_panel.has_net_state_changed() {
             let command = NetworkCommand::SetState(self.central_panel.get_net_state());
-            if self.send_to_net.send(command).is_err() {
-                panic!("The network is not running");
-            }
+            self.send_command(command);
         }
 
-        if self.side_panel.stop_stepping_pressed() {
+        // The side panel keeps track of its own buttons, but not of the shortcut
+        let start_stop = triggered(Action::StartStop);
+        if self.side_panel.stop_stepping_pressed() || (start_stop && self.net_stepping) {
             self.net_stepping = false;
-            if self.send_to_net.send(NetworkCommand::Stop).is_err() {
-                panic!("The network is not running");
-            }
-        }
-
-        if self.side_panel.start_stepping_pressed() {
+            self.side_panel.set_is_stepping(false);
+            self.send_command(NetworkCommand::Stop);
+        } else if self.side_panel.start_stepping_pressed() || start_stop {
             self.net_stepping = true;
-            if self.send_to_net.send(NetworkCommand::Go).is_err() {
-                panic!("The network is not running");
-            }
+            self.side_panel.set_is_stepping(true);
+            self.send_command(NetworkCommand::Go);
         }
 
         if self.side_panel.has_stepping_speed_changed() {
             let command = NetworkCommand::SetSpeed(self.side_panel.get_stepping_speed());
-            if self.send_to_net.send(command).is_err() {
-                panic!("The network is not running");
+            self.send_command(command);
+        }
+
+        // Like their buttons, the steps can't be asked while the network is running
+        if self.side_panel.step_node_pressed()
+            || (triggered(Action::StepNode) && !self.net_stepping)
+        {
+            self.send_command(NetworkCommand::StepNode);
+        }
+
+        if self.side_panel.step_sweep_pressed()
+            || (triggered(Action::StepSweep) && !self.net_stepping)
+        {
+            self.send_command(NetworkCommand::StepSweep);
+        }
+
+        // The network stops by itself once it settles, the gui waits for it like it does while it steps
+        if self.side_panel.converge_pressed() || (triggered(Action::Converge) && !self.net_stepping)
+        {
+            self.net_stepping = true;
+            self.side_panel.set_is_stepping(true);
+            self.send_command(NetworkCommand::Converge);
+        }
+
+        if self.side_panel.has_update_mode_changed() {
+            let command = NetworkCommand::SetUpdateMode(self.side_panel.get_update_mode());
+            self.send_command(command);
+        }
+
+        if self.side_panel.has_precision_changed() {
+            let command = NetworkCommand::SetPrecision(self.side_panel.get_precision());
+            self.send_command(command);
+        }
+
+        if self.side_panel.has_learning_rule_changed() {
+            let command = NetworkCommand::SetLearningRule(self.side_panel.get_learning_rule());
+            self.send_command(command);
+            // Only the hebbian network learns again with the new rule
+            if self.net_type == hop_net::NetworkType::SquareDiscrete
+                && !self.solving_problem
+                && !self.pattern_list.get_patterns().is_empty()
+            {
+                self.side_panel.start_training(self.net_type);
             }
         }
 
+        // The network relaxes from random states while dreaming, so it can't be stepping at the same time
+        if self.side_panel.unlearn_pressed() {
+            self.net_stepping = false;
+            self.side_panel.set_is_stepping(false);
+            self.send_command(NetworkCommand::Stop);
+            let unlearning = self.side_panel.get_unlearning();
+            let command = NetworkCommand::Unlearn(unlearning);
+            self.send_command(command);
+            self.side_panel
+                .set_unlearning_progress(0, unlearning.dreams);
+        }
+
         // The current state is always the one being shown to the user, not the one of the net.
-        if self.side_panel.learn_current_state() {
-            let command = NetworkCommand::Learn(self.central_panel.get_net_state());
-            if self.send_to_net.send(command).is_err() {
-                panic!("The network is not running");
-            }
+        // The optimization network can't learn, so the state would be listed as a pattern it doesn't have
+        if (self.side_panel.learn_current_state() || triggered(Action::LearnCurrentState))
+            && !self.solving_problem
+        {
+            self.history
+                .record("Learn the current state", self.snapshot());
+            let state = self.central_panel.get_net_state();
+            self.pattern_list
+                .push(state.clone(), self.central_panel.get_layer_sizes());
+            let command = NetworkCommand::Learn(state);
+            self.send_command(command);
+            self.side_panel.start_training(self.net_type);
         }
 
-        if self.side_panel.forget_all() {
-            if self.send_to_net.send(NetworkCommand::ResetWeights).is_err() {
-                panic!("The network is not running");
-            }
+        if self.side_panel.forget_all() || triggered(Action::ForgetAll) {
+            self.history.record("Forget all", self.snapshot());
+            self.pattern_list.clear();
+            self.send_command(NetworkCommand::ResetWeights);
+        }
+
+        // The order of the patterns matters only for the sequence networks, but the others will
+        // still forget the patterns that were removed from the list
+        if self.pattern_list.learn_as_sequence() {
+            self.send_command(NetworkCommand::LearnAll(
+                self.pattern_list.get_patterns().to_vec(),
+            ));
+        }
+
+        // A sequence network would still go through the removed pattern, so it learns the sequence again without it
+        if self.pattern_list.pattern_removed()
+            && self.net_type.is_sequence()
+            && !self.solving_problem
+        {
+            self.pattern_list.relearned();
+            self.send_command(NetworkCommand::LearnAll(
+                self.pattern_list.get_patterns().to_vec(),
+            ));
         }
 
         if self.side_panel.has_selected_network_changed() {
+            self.history
+                .record("Change the network type", self.snapshot());
             let new_type = self.side_panel.get_selected_network();
+            let same_layers = self.side_panel.get_layer_sizes(new_type)
+                == self.side_panel.get_layer_sizes(self.net_type);
+            self.net_type = new_type;
+            self.side_panel.set_net_type(new_type);
             self.central_panel.set_net_type(new_type);
-            if self
-                .send_to_net
-                .send(NetworkCommand::ChangeNetType(new_type))
-                .is_err()
-            {
-                panic!("The network is not running");
+            self.comparison_panel.set_net_type(new_type);
+            self.send_command(NetworkCommand::ChangeNetType(new_type));
+            if new_type.is_sequence() {
+                self.side_panel
+                    .set_update_mode(hop_net::UpdateMode::Synchronous);
+            }
+            self.diagnostics_panel.clear_report();
+            self.enumeration_panel.clear_result();
+
+            // The network keeps its memories and its state if it keeps its layers, and learns them again.
+            // The optimization network has no memories to keep, its problem is simply dropped
+            if same_layers && !self.solving_problem {
+                if !self.pattern_list.get_patterns().is_empty() {
+                    self.side_panel.start_training(new_type);
+                }
+            } else {
+                self.pattern_list.clear();
+                self.change_layout();
             }
         }
 
+        if self.solving_problem {
+            self.optimization_panel
+                .evaluate(&self.central_panel.get_net_state());
+        }
+
         //----------------------------------Rendering the UI----------------------------------
         /* I have no use for this at the moment
         #[cfg(not(target_arch = "wasm32"))] // no File->Quit on web pages!
@@ -268,17 +805,48 @@ impl eframe::App for HopfiledNetsApp {
             });
         });
         */
+        if self.error_banner.has_errors() {
+            egui::TopBottomPanel::top("error_banner").show(ctx, |ui| {
+                self.error_banner.generate_ui(ui);
+            });
+        }
+
         egui::SidePanel::left("side_panel").show(ctx, |ui| {
-            self.side_panel.generate_ui(ui);
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                self.history.generate_ui(ui);
+                self.shortcuts.generate_ui(ui);
+                ui.separator();
+                self.side_panel.generate_ui(ui);
+                ui.separator();
+                self.pattern_list.generate_ui(ui);
+                ui.separator();
+                self.optimization_panel.generate_ui(ui);
+                self.damage_panel.generate_ui(ui);
+                self.diagnostics_panel.generate_ui(ui);
+                self.enumeration_panel.generate_ui(ui);
+                self.comparison_panel.generate_ui(ui);
+            });
         });
 
+        self.comparison_panel.generate_window(ctx);
+        self.shortcuts.generate_windows(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.label("Hopfield Nets");
             self.central_panel.generate_ui(ui);
         });
 
         // If the net is stepping, we update the gui as soon as possible.
-        if self.net_stepping {
+        // The same goes for experiments, trainings, dreams, enumerations and comparisons, otherwise the progress would be shown only when the mouse moves.
+        // The keys are read while the windows are drawn, after the actions were handled, so the next frame has to come right away to carry them out
+        if self.net_stepping
+            || !self.shortcuts.triggered_actions().is_empty()
+            || self.damage_panel.is_running()
+            || self.enumeration_panel.is_running()
+            || self.comparison_panel.is_running()
+            || self.side_panel.is_training()
+            || self.side_panel.is_unlearning()
+        {
             ctx.request_repaint();
         }
     }
@@ -1,6 +1,32 @@
-mod state_renderer;
+pub mod bitmap_font;
+pub mod drawing_tools;
+pub mod landscape_view;
+pub mod state_renderer;
 
 use crate::app::hop_net;
+use crate::app::hop_net::cycle_detection::Cycle;
+use crate::app::hop_net::landscape::Landscape;
+use crate::app::hop_net::landscape::Plane;
+use crate::app::hop_net::NodeUpdate;
+use crate::app::shortcuts::ShortcutHints;
+use drawing_tools::Tool;
+
+// The fields are asked again if the network didn't answer within this time
+const FIELDS_RETRY_SECONDS: f64 = 1.0;
+
+// A node of one of the layers, the index is relative to the layer
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct GridNode {
+    start: usize,
+    size: usize,
+    index: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum View {
+    State,
+    EnergyLandscape,
+}
 
 pub struct CentralPanel {
     button_size: egui::Vec2,
@@ -10,8 +36,35 @@ pub struct CentralPanel {
     net_state_changed: bool,
     state_sqrt: usize,
     mouse_down: bool,
+    mouse_pressed: bool,
+    mouse_released: bool,
     network_type: hop_net::NetworkType,
+    // The state holds all the layers one after the other, each one is rendered as a separate grid
+    layer_sizes: Vec<usize>,
     nodes_being_edited: bool,
+    // The last cycle reported by the network, it's empty if the network didn't get stuck in one
+    cycle: Vec<Vec<f64>>,
+    playing_cycle: bool,
+    cycle_fps: f32,
+    cycle_start_time: f64,
+    view: View,
+    landscape_view: landscape_view::LandscapeView,
+    // The last node the network updated on its own, it's highlighted until the state changes in some other way
+    node_update: Option<NodeUpdate>,
+    // The fields of every node, and the state they were computed for
+    local_fields: Option<(Vec<f64>, Vec<f64>)>,
+    fields_to_request: Option<Vec<f64>>,
+    fields_requested_at: Option<f64>,
+    drawing_tools: drawing_tools::DrawingTools,
+    // Where the line or the rectangle being drawn started, and where it ends for now
+    drag: Option<(GridNode, GridNode)>,
+    // The state with the line or the rectangle on it, it's shown until the mouse is released
+    preview: Option<Vec<f64>>,
+    // The state before the edit the user is making, a drag is a single edit however many nodes it changes
+    edit_start_state: Option<Vec<f64>>,
+    finished_edit: Option<Vec<f64>>,
+    // Asked from the keyboard, it's applied in the next frame like the ones from the toolbar
+    pending_transform: Option<drawing_tools::Transform>,
 }
 
 impl CentralPanel {
@@ -25,45 +78,309 @@ impl CentralPanel {
             state_sqrt: sqrt,
             saved_state: net_state.clone(),
             mouse_down: false,
+            mouse_pressed: false,
+            mouse_released: false,
             network_type,
+            layer_sizes: vec![net_state.len()],
             nodes_being_edited: false,
+            cycle: Vec::new(),
+            playing_cycle: false,
+            cycle_fps: 2.0,
+            cycle_start_time: 0.0,
+            view: View::State,
+            landscape_view: landscape_view::LandscapeView::new(),
+            node_update: None,
+            local_fields: None,
+            fields_to_request: None,
+            fields_requested_at: None,
+            drawing_tools: drawing_tools::DrawingTools::new(),
+            drag: None,
+            preview: None,
+            edit_start_state: None,
+            finished_edit: None,
+            pending_transform: None,
         }
     }
 
     pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
         // The central panel the region left after adding TopPanel's and SidePanel's
-        ui.heading("Network state");
+        ui.horizontal(|ui| {
+            ui.selectable_value(
+                &mut self.view,
+                View::State,
+                egui::RichText::new("Network state").heading(),
+            );
+            ui.selectable_value(
+                &mut self.view,
+                View::EnergyLandscape,
+                egui::RichText::new("Energy landscape").heading(),
+            );
+        });
+        self.landscape_view
+            .set_open(self.view == View::EnergyLandscape, &self.net_state);
 
         // Here we extrat the mouse position,and if the mouse primary button is pressed form the context
         // when the mouse is released we also reset the just_cahnged mask to all false
         let mouse_pos = self.handle_mouse(ui);
 
+        if !self.cycle.is_empty() {
+            self.cycle_ui(ui);
+        }
+
+        self.net_state_changed = false;
+        self.fields_to_request = None;
+        self.finished_edit = None;
+
+        let state_before = self.net_state.clone();
+        if let Some(transform) = self.pending_transform.take() {
+            self.apply_transform(transform);
+        }
+
+        if self.view == View::EnergyLandscape {
+            self.landscape_view.generate_ui(ui);
+            self.finish_edit(state_before);
+            return;
+        }
+
+        if let Some(transform) = self.drawing_tools.generate_ui(ui) {
+            self.apply_transform(transform);
+        }
+
+        // The fields are shown only if they belong to the state on screen
+        let fields = match &self.local_fields {
+            Some((state, fields)) if *state == self.net_state => Some(fields.clone()),
+            _ => None,
+        };
+        let mut pointed = None;
+        // While a line or a rectangle is being drawn, it's shown on top of the state
+        let shown_state = self.preview.as_ref().unwrap_or(&self.net_state);
+
         // More than a single net uses the same renderer, so we store the call in a closure to improve redability
         let mut square_descrete_render = || {
-            state_renderer::render_square_discrete(
-                ui,
-                &mut self.net_state,
-                &mut self.just_changed,
-                &mut self.net_state_changed,
-                self.button_size,
-                mouse_pos,
-                self.mouse_down,
-                &mut self.nodes_being_edited,
-            )
+            ui.horizontal_top(|ui| {
+                let mut start = 0;
+                for (layer, size) in self.layer_sizes.iter().enumerate() {
+                    let end = start + size;
+                    if *size > 0 {
+                        let highlight = self
+                            .node_update
+                            .filter(|update| (start..end).contains(&update.index))
+                            .map(|update| NodeUpdate {
+                                index: update.index - start,
+                                ..update
+                            });
+                        let node = state_renderer::render_square_discrete(
+                            ui,
+                            layer,
+                            &shown_state[start..end],
+                            self.button_size,
+                            mouse_pos,
+                            highlight,
+                            fields.as_ref().map(|fields| &fields[start..end]),
+                        );
+                        if let Some(index) = node {
+                            pointed = Some(GridNode {
+                                start,
+                                size: *size,
+                                index,
+                            });
+                        }
+                        ui.add_space(self.button_size.x);
+                    }
+                    start = end;
+                }
+            });
         };
 
         match self.network_type {
             hop_net::NetworkType::StorkeySquareDiscrete => square_descrete_render(),
             hop_net::NetworkType::SquareDiscrete => square_descrete_render(),
+            hop_net::NetworkType::SequenceSquareDiscrete => square_descrete_render(),
+            hop_net::NetworkType::DelayedSequenceSquareDiscrete => square_descrete_render(),
+            hop_net::NetworkType::BamSquareDiscrete => square_descrete_render(),
+            hop_net::NetworkType::BoltzmannSquareDiscrete => square_descrete_render(),
+            hop_net::NetworkType::RbmSquareDiscrete => square_descrete_render(),
             _ => panic!("Renderer not available"),
         }
 
+        self.use_tool(pointed);
+
+        if let Some(update) = self.node_update {
+            ui.label(format!(
+                "Node {} was updated with a field of {:.3}, {}",
+                update.index + 1,
+                update.field,
+                if update.flipped {
+                    "it flipped"
+                } else {
+                    "it stayed the same"
+                }
+            ));
+        }
+
+        // The fields are asked only when the user is looking at them
+        let time = ui.input(|i| i.time);
+        let retry = self.fields_requested_at.map_or(true, |requested_at| {
+            time - requested_at >= FIELDS_RETRY_SECONDS
+        });
+        if pointed.is_some() && fields.is_none() && retry {
+            self.fields_to_request = Some(self.net_state.clone());
+            self.fields_requested_at = Some(time);
+        }
+
+        self.finish_edit(state_before);
+
         // egui::warn_if_debug_build(ui);
     }
 
+    fn finish_edit(&mut self, state_before: Vec<f64>) {
+        // The edit is over when the mouse is released, the buttons are clicked on the release too
+        if self.net_state_changed && self.edit_start_state.is_none() {
+            self.edit_start_state = Some(state_before);
+        }
+        if !self.mouse_down {
+            self.finished_edit = self.edit_start_state.take();
+        }
+
+        // If the user starts editing the state, the animation would overwrite the edits
+        if self.net_state_changed && self.playing_cycle {
+            self.cycle.clear();
+            self.playing_cycle = false;
+        }
+
+        // The edited state wasn't reached by the network, so the path starts again from it
+        if self.net_state_changed {
+            self.node_update = None;
+            self.landscape_view.clear_trajectory();
+            self.landscape_view.push_state(&self.net_state);
+        }
+    }
+
+    fn cycle_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Limit cycle of period {}", self.cycle.len()));
+
+            let label = if self.playing_cycle { "Stop" } else { "Play" };
+            if ui.button(label).clicked() {
+                self.playing_cycle = !self.playing_cycle;
+                self.cycle_start_time = ui.input(|i| i.time);
+                if !self.playing_cycle {
+                    // The network stopped on the first state of the cycle, so that's what we go back to
+                    self.net_state = self.cycle[0].clone();
+                }
+            }
+            ui.add(egui::Slider::new(&mut self.cycle_fps, 0.5..=30.0).text("states/sec"));
+        });
+
+        if self.playing_cycle {
+            let elapsed = ui.input(|i| i.time) - self.cycle_start_time;
+            let frame = (elapsed * self.cycle_fps as f64) as usize % self.cycle.len();
+            self.net_state = self.cycle[frame].clone();
+            ui.label(format!("State {} of {}", frame + 1, self.cycle.len()));
+            ui.ctx().request_repaint();
+        }
+    }
+
+    // Applies the selected tool to the node under the mouse, the mouse state comes from handle_mouse
+    fn use_tool(&mut self, pointed: Option<GridNode>) {
+        let tool = self.drawing_tools.tool();
+        let value = self.drawing_tools.paint_value();
+
+        if let Tool::Line | Tool::Rectangle = tool {
+            self.drag_shape(tool, pointed, value);
+            return;
+        }
+
+        let node = match pointed {
+            Some(node) => node,
+            None => return,
+        };
+        let layer = &mut self.net_state[node.start..node.start + node.size];
+        let side = (node.size as f32).sqrt() as usize;
+        let changed = match tool {
+            Tool::Toggle => {
+                let i = node.start + node.index;
+                if !self.mouse_down || self.just_changed[i] {
+                    return;
+                }
+                self.just_changed[i] = true;
+                self.nodes_being_edited = true;
+                layer[node.index] = -layer[node.index];
+                true
+            }
+            Tool::Brush if self.mouse_down => drawing_tools::paint_disk(
+                layer,
+                side,
+                node.index,
+                self.drawing_tools.brush_radius(),
+                value,
+            ),
+            Tool::Fill if self.mouse_pressed => {
+                drawing_tools::flood_fill(layer, side, node.index, value)
+            }
+            Tool::Text if self.mouse_pressed => {
+                drawing_tools::stamp_text(layer, side, node.index, self.drawing_tools.text(), value)
+            }
+            _ => false,
+        };
+        self.net_state_changed |= changed;
+    }
+
+    // The shape follows the mouse while it's down, and it's painted on the state when it's released
+    fn drag_shape(&mut self, tool: Tool, pointed: Option<GridNode>, value: f64) {
+        if self.mouse_pressed {
+            self.drag = pointed.map(|node| (node, node));
+        }
+        let (from, mut to) = match self.drag {
+            Some(drag) => drag,
+            None => return,
+        };
+        // The shape can't go past the layer it started in
+        if let Some(node) = pointed.filter(|node| node.start == from.start) {
+            to = node;
+            self.drag = Some((from, to));
+        }
+
+        let mut preview = self.net_state.clone();
+        let layer = &mut preview[from.start..from.start + from.size];
+        let side = (from.size as f32).sqrt() as usize;
+        let changed = if tool == Tool::Line {
+            drawing_tools::draw_line(layer, side, from.index, to.index, value)
+        } else {
+            let filled = self.drawing_tools.is_filled();
+            drawing_tools::draw_rectangle(layer, side, from.index, to.index, value, filled)
+        };
+
+        if self.mouse_released || !self.mouse_down {
+            self.drag = None;
+            self.preview = None;
+            if changed {
+                self.net_state = preview;
+                self.net_state_changed = true;
+            }
+        } else {
+            self.preview = Some(preview);
+        }
+    }
+
+    fn apply_transform(&mut self, transform: drawing_tools::Transform) {
+        let mut start = 0;
+        for size in self.layer_sizes.iter() {
+            let side = (*size as f32).sqrt() as usize;
+            if side * side == *size && *size > 0 {
+                let layer = &mut self.net_state[start..start + size];
+                drawing_tools::apply_transform(layer, side, transform);
+            }
+            start += size;
+        }
+        self.net_state_changed = true;
+    }
+
     fn handle_mouse(&mut self, ui: &mut egui::Ui) -> egui::Pos2 {
         let mut mouse_pos = egui::Pos2::new(0.0, 0.0);
         ui.ctx().input(|i| {
+            self.mouse_pressed = i.pointer.primary_pressed();
+            self.mouse_released = i.pointer.primary_released();
             if i.pointer.primary_pressed() {
                 self.mouse_down = true;
             }
@@ -100,6 +417,24 @@ impl CentralPanel {
         self.net_state_changed
     }
 
+    pub fn get_layer_sizes(&self) -> &[usize] {
+        &self.layer_sizes
+    }
+
+    pub fn landscape_request(&self) -> Option<Plane> {
+        self.landscape_view.landscape_request()
+    }
+
+    // The state before the edit the user just finished, if they finished one in this frame
+    pub fn finished_edit(&self) -> Option<Vec<f64>> {
+        self.finished_edit.clone()
+    }
+
+    // The state the network has to compute the fields of, if the user is hovering a node
+    pub fn local_fields_request(&self) -> Option<Vec<f64>> {
+        self.fields_to_request.clone()
+    }
+
     // Setters
 
     pub fn set_node_size(&mut self, size: f32) {
@@ -110,16 +445,81 @@ impl CentralPanel {
     }
 
     pub fn set_net_state(&mut self, net_state: Vec<f64>) {
+        // The layout is always changed before the state, so a state that doesn't fit in it
+        // was sent by the network before it knew about the new layout
+        if self.layer_sizes.iter().sum::<usize>() != net_state.len() {
+            println!("Discarded a state that doesn't match the layers");
+            return;
+        }
+
         if net_state.len() != self.net_state.len() {
             self.just_changed = vec![false; net_state.len()];
             self.state_sqrt = (net_state.len() as f32).sqrt() as usize;
             self.net_state_changed = false;
         }
 
+        self.landscape_view.push_state(&net_state);
         self.net_state = net_state;
+        // A new state means the network moved on, so the old cycle is not relevant anymore
+        self.cycle.clear();
+        self.playing_cycle = false;
+    }
+
+    pub fn add_noise(&mut self) {
+        let noise = self.drawing_tools.noise();
+        self.pending_transform = Some(drawing_tools::Transform::AddNoise(noise));
+    }
+
+    pub fn set_shortcut_hints(&mut self, hints: ShortcutHints) {
+        self.drawing_tools.set_shortcut_hints(hints);
+    }
+
+    pub fn set_node_update(&mut self, update: NodeUpdate) {
+        self.node_update = Some(update);
+    }
+
+    pub fn clear_node_update(&mut self) {
+        self.node_update = None;
+    }
+
+    pub fn set_local_fields(&mut self, state: Vec<f64>, fields: Vec<f64>) {
+        self.local_fields = Some((state, fields));
+        self.fields_requested_at = None;
+    }
+
+    // The weights changed, so the fields and the landscape have to be computed again
+    pub fn weights_changed(&mut self) {
+        self.local_fields = None;
+        self.fields_requested_at = None;
+        self.landscape_view.weights_changed();
+    }
+
+    pub fn set_cycle(&mut self, cycle: Cycle) {
+        self.cycle = cycle.states;
+        self.playing_cycle = false;
     }
 
     pub fn set_net_type(&mut self, network_type: hop_net::NetworkType) {
         self.network_type = network_type;
     }
+
+    // Must be called before the state with the new layout is set
+    pub fn set_layer_sizes(&mut self, layer_sizes: Vec<usize>) {
+        self.layer_sizes = layer_sizes;
+        self.landscape_view.clear();
+        self.node_update = None;
+        self.local_fields = None;
+        self.drag = None;
+        self.preview = None;
+        self.edit_start_state = None;
+    }
+
+    pub fn set_landscape(&mut self, landscape: Landscape) {
+        self.landscape_view.set_landscape(landscape);
+    }
+
+    // The patterns the landscape can be projected on
+    pub fn set_stored_patterns(&mut self, patterns: &[Vec<f64>]) {
+        self.landscape_view.set_patterns(patterns);
+    }
 }
@@ -0,0 +1,373 @@
+use std::collections::VecDeque;
+
+use rand::Rng;
+
+use super::bitmap_font;
+use crate::app::hop_net::experiments;
+use crate::app::shortcuts::Action;
+use crate::app::shortcuts::ShortcutHints;
+
+const MAX_BRUSH_RADIUS: usize = 8;
+
+// What happens when the user clicks or drags on the grid
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Tool {
+    // Inverts every node the mouse passes over, once per drag
+    Toggle,
+    Brush,
+    // Paints the region of nodes with the same value connected to the clicked one
+    Fill,
+    // The line and the rectangle go from where the mouse was pressed to where it's released
+    Line,
+    Rectangle,
+    // Writes the text with its top left corner on the clicked node
+    Text,
+}
+
+// Changes to the whole pattern, they are applied to every layer on its own
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Transform {
+    Clear,
+    Invert,
+    Randomize,
+    // Flips the given fraction of the nodes, picked at random
+    AddNoise(f64),
+    // Moves the pattern by the given columns and rows, what goes out of a side comes back from the other
+    Shift(isize, isize),
+    RotateClockwise,
+    RotateCounterclockwise,
+    MirrorHorizontal,
+    MirrorVertical,
+}
+
+pub struct DrawingTools {
+    tool: Tool,
+    // Otherwise the tools paint the nodes off
+    paint_on: bool,
+    brush_radius: usize,
+    filled: bool,
+    text: String,
+    noise: f64,
+    shortcut_hints: ShortcutHints,
+}
+
+impl DrawingTools {
+    pub fn new() -> Self {
+        Self {
+            tool: Tool::Toggle,
+            paint_on: true,
+            brush_radius: 1,
+            filled: false,
+            text: "A".to_string(),
+            noise: 0.1,
+            shortcut_hints: ShortcutHints::default(),
+        }
+    }
+
+    // Returns the transform to apply to the pattern, if one of the buttons was pressed
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) -> Option<Transform> {
+        ui.horizontal(|ui| {
+            ui.label("Tool:");
+            ui.selectable_value(&mut self.tool, Tool::Toggle, "Toggle");
+            ui.selectable_value(&mut self.tool, Tool::Brush, "Brush");
+            ui.selectable_value(&mut self.tool, Tool::Fill, "Fill");
+            ui.selectable_value(&mut self.tool, Tool::Line, "Line");
+            ui.selectable_value(&mut self.tool, Tool::Rectangle, "Rectangle");
+            ui.selectable_value(&mut self.tool, Tool::Text, "Text");
+
+            if self.tool == Tool::Toggle {
+                return;
+            }
+            ui.separator();
+            ui.radio_value(&mut self.paint_on, true, "Paint on");
+            ui.radio_value(&mut self.paint_on, false, "Paint off");
+            match self.tool {
+                Tool::Brush => {
+                    ui.add(
+                        egui::Slider::new(&mut self.brush_radius, 0..=MAX_BRUSH_RADIUS)
+                            .text("radius"),
+                    );
+                }
+                Tool::Rectangle => {
+                    ui.checkbox(&mut self.filled, "Filled");
+                }
+                Tool::Text => {
+                    ui.add(egui::TextEdit::singleline(&mut self.text).desired_width(100.0))
+                        .on_hover_text(
+                            "Click on the grid where the top left corner of the text goes",
+                        );
+                }
+                _ => {}
+            }
+        });
+
+        let mut transform = None;
+        ui.horizontal(|ui| {
+            let mut button = |ui: &mut egui::Ui, text: &str, hover: &str, pressed: Transform| {
+                if ui.button(text).on_hover_text(hover).clicked() {
+                    transform = Some(pressed);
+                }
+            };
+            button(ui, "Clear", "Turn every node off", Transform::Clear);
+            button(ui, "Invert", "Flip every node", Transform::Invert);
+            button(
+                ui,
+                "Random",
+                "Set every node at random",
+                Transform::Randomize,
+            );
+            ui.separator();
+            button(ui, "⬅", "Shift left", Transform::Shift(-1, 0));
+            button(ui, "➡", "Shift right", Transform::Shift(1, 0));
+            button(ui, "⬆", "Shift up", Transform::Shift(0, -1));
+            button(ui, "⬇", "Shift down", Transform::Shift(0, 1));
+            ui.separator();
+            button(
+                ui,
+                "⟲",
+                "Rotate counterclockwise",
+                Transform::RotateCounterclockwise,
+            );
+            button(ui, "⟳", "Rotate clockwise", Transform::RotateClockwise);
+            button(ui, "⇔", "Mirror left to right", Transform::MirrorHorizontal);
+            button(ui, "⇕", "Mirror top to bottom", Transform::MirrorVertical);
+            ui.separator();
+            let response = ui.button("Noise");
+            let response = self.shortcut_hints.tooltip(
+                response,
+                Action::AddNoise,
+                "Flip this fraction of the nodes, picked at random",
+            );
+            if response.clicked() {
+                transform = Some(Transform::AddNoise(self.noise));
+            }
+            ui.add(
+                egui::DragValue::new(&mut self.noise)
+                    .clamp_range(0.0..=1.0)
+                    .speed(0.01)
+                    .fixed_decimals(2),
+            );
+        });
+        transform
+    }
+
+    // Getters
+
+    pub fn tool(&self) -> Tool {
+        self.tool
+    }
+
+    pub fn paint_value(&self) -> f64 {
+        if self.paint_on {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    pub fn brush_radius(&self) -> usize {
+        self.brush_radius
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.filled
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn noise(&self) -> f64 {
+        self.noise
+    }
+
+    // Setters
+
+    pub fn set_shortcut_hints(&mut self, hints: ShortcutHints) {
+        self.shortcut_hints = hints;
+    }
+}
+
+impl Default for DrawingTools {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// All the functions below work on a single square layer, the nodes are stored row by row.
+// The ones that paint return true if at least a node changed
+
+fn set(grid: &mut [f64], index: usize, value: f64) -> bool {
+    let changed = grid[index] != value;
+    grid[index] = value;
+    changed
+}
+
+// r * (r + 1) instead of r * r gives rounder disks on small radiuses
+pub fn paint_disk(grid: &mut [f64], side: usize, center: usize, radius: usize, value: f64) -> bool {
+    let (row, column) = (center / side, center % side);
+    let mut changed = false;
+    for r in row.saturating_sub(radius)..(row + radius + 1).min(side) {
+        for c in column.saturating_sub(radius)..(column + radius + 1).min(side) {
+            let distance = r.abs_diff(row).pow(2) + c.abs_diff(column).pow(2);
+            if distance <= radius * (radius + 1) {
+                changed |= set(grid, r * side + c, value);
+            }
+        }
+    }
+    changed
+}
+
+pub fn flood_fill(grid: &mut [f64], side: usize, start: usize, value: f64) -> bool {
+    let target = grid[start];
+    if target == value {
+        return false;
+    }
+
+    let mut queue = VecDeque::from([start]);
+    grid[start] = value;
+    while let Some(index) = queue.pop_front() {
+        let (row, column) = (index / side, index % side);
+        let mut neighbours = Vec::with_capacity(4);
+        if row > 0 {
+            neighbours.push(index - side);
+        }
+        if row + 1 < side {
+            neighbours.push(index + side);
+        }
+        if column > 0 {
+            neighbours.push(index - 1);
+        }
+        if column + 1 < side {
+            neighbours.push(index + 1);
+        }
+        for neighbour in neighbours {
+            if grid[neighbour] == target {
+                grid[neighbour] = value;
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    true
+}
+
+// Bresenham's line, both ends included
+pub fn draw_line(grid: &mut [f64], side: usize, from: usize, to: usize, value: f64) -> bool {
+    let (mut row, mut column) = ((from / side) as isize, (from % side) as isize);
+    let (end_row, end_column) = ((to / side) as isize, (to % side) as isize);
+    let (d_row, d_column) = (-(end_row - row).abs(), (end_column - column).abs());
+    let step_row = if row < end_row { 1 } else { -1 };
+    let step_column = if column < end_column { 1 } else { -1 };
+    let mut error = d_column + d_row;
+    let mut changed = false;
+    loop {
+        changed |= set(grid, row as usize * side + column as usize, value);
+        if row == end_row && column == end_column {
+            return changed;
+        }
+        let double_error = 2 * error;
+        if double_error >= d_row {
+            error += d_row;
+            column += step_column;
+        }
+        if double_error <= d_column {
+            error += d_column;
+            row += step_row;
+        }
+    }
+}
+
+pub fn draw_rectangle(
+    grid: &mut [f64],
+    side: usize,
+    corner: usize,
+    opposite: usize,
+    value: f64,
+    filled: bool,
+) -> bool {
+    let (top, bottom) = (
+        (corner / side).min(opposite / side),
+        (corner / side).max(opposite / side),
+    );
+    let (left, right) = (
+        (corner % side).min(opposite % side),
+        (corner % side).max(opposite % side),
+    );
+    let mut changed = false;
+    for row in top..=bottom {
+        for column in left..=right {
+            let border = row == top || row == bottom || column == left || column == right;
+            if filled || border {
+                changed |= set(grid, row * side + column, value);
+            }
+        }
+    }
+    changed
+}
+
+// Only the nodes of the characters are painted, the ones around them are left as they are.
+// What doesn't fit in the grid is cut off
+pub fn stamp_text(grid: &mut [f64], side: usize, origin: usize, text: &str, value: f64) -> bool {
+    let (top, left) = (origin / side, origin % side);
+    let mut changed = false;
+    for (n, c) in text.chars().enumerate() {
+        let glyph = bitmap_font::glyph(c);
+        let glyph_left = left + n * bitmap_font::GLYPH_ADVANCE;
+        if glyph_left >= side {
+            break;
+        }
+        for row in 0..bitmap_font::GLYPH_HEIGHT {
+            for column in 0..bitmap_font::GLYPH_WIDTH {
+                let (r, c) = (top + row, glyph_left + column);
+                if r < side && c < side && bitmap_font::is_on(&glyph, column, row) {
+                    changed |= set(grid, r * side + c, value);
+                }
+            }
+        }
+    }
+    changed
+}
+
+pub fn apply_transform(grid: &mut [f64], side: usize, transform: Transform) {
+    let old = grid.to_vec();
+    // For each node of the new grid, the row and column it comes from in the old one
+    let source: Box<dyn Fn(usize, usize) -> (usize, usize)> = match transform {
+        Transform::Clear => {
+            grid.fill(-1.0);
+            return;
+        }
+        Transform::Invert => {
+            grid.iter_mut().for_each(|node| *node = -*node);
+            return;
+        }
+        Transform::Randomize => {
+            let mut rng = rand::thread_rng();
+            grid.iter_mut()
+                .for_each(|node| *node = if rng.gen_bool(0.5) { 1.0 } else { -1.0 });
+            return;
+        }
+        Transform::AddNoise(fraction) => {
+            grid.copy_from_slice(&experiments::add_noise(&old, fraction));
+            return;
+        }
+        Transform::Shift(columns, rows) => {
+            let side = side as isize;
+            Box::new(move |r, c| {
+                (
+                    (r as isize - rows).rem_euclid(side) as usize,
+                    (c as isize - columns).rem_euclid(side) as usize,
+                )
+            })
+        }
+        Transform::RotateClockwise => Box::new(|r, c| (side - 1 - c, r)),
+        Transform::RotateCounterclockwise => Box::new(|r, c| (c, side - 1 - r)),
+        Transform::MirrorHorizontal => Box::new(|r, c| (r, side - 1 - c)),
+        Transform::MirrorVertical => Box::new(|r, c| (side - 1 - r, c)),
+    };
+    for r in 0..side {
+        for c in 0..side {
+            let (from_r, from_c) = source(r, c);
+            grid[r * side + c] = old[from_r * side + from_c];
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::VecDeque;
+
+use crate::app::hop_net::landscape::Landscape;
+use crate::app::hop_net::landscape::Plane;
+
+// The principal components move with the visited states, they are computed again at most this often
+const REFRESH_SECONDS: f64 = 1.0;
+// The oldest states are forgotten after this many
+const MAX_TRAJECTORY: usize = 2000;
+// The principal components are computed on at most this many states, picked evenly along the trajectory
+const PCA_STATES: usize = 100;
+// The surface is drawn as a grid of this many cells per side
+const GRID_RESOLUTION: usize = 60;
+const MAX_SIDE: f32 = 500.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Projection {
+    Patterns,
+    Pca,
+}
+
+// Draws the energy on a plane of states as a colored surface, with the states visited by the network on top
+pub struct LandscapeView {
+    projection: Projection,
+    first_pattern: usize,
+    second_pattern: usize,
+    patterns: Vec<Vec<f64>>,
+    // The states received from the network while the view is open, in order
+    trajectory: VecDeque<Vec<f64>>,
+    landscape: Option<Landscape>,
+    // The trajectory projected on the plane of the landscape
+    points: VecDeque<[f64; 2]>,
+    plane_to_request: Option<Plane>,
+    last_request_time: f64,
+    // The plane or the weights changed, the landscape has to be asked again right away
+    plane_changed: bool,
+    // New states arrived since the last request, the principal components may have moved
+    trajectory_changed: bool,
+    open: bool,
+}
+
+impl LandscapeView {
+    pub fn new() -> Self {
+        Self {
+            projection: Projection::Patterns,
+            first_pattern: 0,
+            second_pattern: 1,
+            patterns: Vec::new(),
+            trajectory: VecDeque::new(),
+            landscape: None,
+            points: VecDeque::new(),
+            plane_to_request: None,
+            last_request_time: 0.0,
+            plane_changed: true,
+            trajectory_changed: false,
+            open: false,
+        }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        self.plane_to_request = None;
+
+        ui.horizontal(|ui| {
+            ui.label("Projection:");
+            let old_projection = self.projection;
+            ui.radio_value(&mut self.projection, Projection::Patterns, "Two patterns");
+            ui.radio_value(
+                &mut self.projection,
+                Projection::Pca,
+                "PCA of visited states",
+            );
+            if self.projection != old_projection {
+                self.plane_changed = true;
+            }
+            if ui.button("Clear path").clicked() {
+                self.clear_trajectory();
+            }
+        });
+
+        if self.projection == Projection::Patterns {
+            if self.patterns.len() < 2 {
+                ui.label("Learn at least two patterns to see the plane they span");
+                return;
+            }
+            ui.horizontal(|ui| {
+                let old = (self.first_pattern, self.second_pattern);
+                pattern_combo(
+                    ui,
+                    "landscape_first_pattern",
+                    &mut self.first_pattern,
+                    self.patterns.len(),
+                );
+                pattern_combo(
+                    ui,
+                    "landscape_second_pattern",
+                    &mut self.second_pattern,
+                    self.patterns.len(),
+                );
+                if (self.first_pattern, self.second_pattern) != old {
+                    self.plane_changed = true;
+                }
+            });
+        }
+
+        let time = ui.input(|i| i.time);
+        let pca_moved = self.projection == Projection::Pca && self.trajectory_changed;
+        if self.plane_changed || (pca_moved && time - self.last_request_time >= REFRESH_SECONDS) {
+            self.plane_to_request = self.build_plane();
+            self.last_request_time = time;
+            self.plane_changed = false;
+            self.trajectory_changed = false;
+        } else if pca_moved {
+            // The last states have to show up even if the network stopped in the meantime
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f64(REFRESH_SECONDS));
+        }
+
+        if self.landscape.is_none() {
+            match self.projection {
+                Projection::Patterns => ui.label("Waiting for the network..."),
+                Projection::Pca => ui.label("The network has to visit a few states first"),
+            };
+            return;
+        }
+        self.draw_landscape(ui);
+    }
+
+    fn build_plane(&self) -> Option<Plane> {
+        match self.projection {
+            Projection::Patterns => {
+                let first = self.patterns.get(self.first_pattern)?;
+                let second = self.patterns.get(self.second_pattern)?;
+                Some(Plane::from_patterns(first, second))
+            }
+            Projection::Pca => {
+                let step = (self.trajectory.len() / PCA_STATES).max(1);
+                let states: Vec<Vec<f64>> = self.trajectory.iter().step_by(step).cloned().collect();
+                Plane::from_pca(&states)
+            }
+        }
+    }
+
+    fn draw_landscape(&self, ui: &mut egui::Ui) {
+        let landscape = match &self.landscape {
+            Some(landscape) => landscape,
+            None => return,
+        };
+        let pattern_points: Vec<[f64; 2]> = self
+            .patterns
+            .iter()
+            .filter(|pattern| pattern.len() == landscape.plane.origin.len())
+            .map(|pattern| landscape.project(pattern))
+            .collect();
+
+        // The patterns are at distance 1 from the origin of their plane, the principal components are unit vectors
+        let default_extent: f64 = match self.projection {
+            Projection::Patterns => 1.25,
+            Projection::Pca => 1.0,
+        };
+        let (mut min, mut max) = ([-default_extent; 2], [default_extent; 2]);
+        for point in self.points.iter() {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+        }
+        for axis in 0..2 {
+            let padding = (max[axis] - min[axis]) * 0.05;
+            min[axis] -= padding;
+            max[axis] += padding;
+        }
+
+        let cell = |i: usize, axis: usize| {
+            min[axis] + (i as f64 + 0.5) / GRID_RESOLUTION as f64 * (max[axis] - min[axis])
+        };
+        let energies: Vec<f64> = (0..GRID_RESOLUTION * GRID_RESOLUTION)
+            .map(|k| landscape.energy(cell(k % GRID_RESOLUTION, 0), cell(k / GRID_RESOLUTION, 1)))
+            .collect();
+        let lowest = energies.iter().cloned().fold(f64::INFINITY, f64::min);
+        let highest = energies.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+        let side = ui
+            .available_width()
+            .min(ui.available_height() - 40.0)
+            .clamp(100.0, MAX_SIDE);
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(side, side), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+
+        // b grows upwards, like on a plot
+        let to_screen = |point: [f64; 2]| {
+            egui::pos2(
+                rect.left() + ((point[0] - min[0]) / (max[0] - min[0])) as f32 * side,
+                rect.bottom() - ((point[1] - min[1]) / (max[1] - min[1])) as f32 * side,
+            )
+        };
+        let cell_side = side / GRID_RESOLUTION as f32;
+        for (k, energy) in energies.iter().enumerate() {
+            let t = if highest > lowest {
+                (energy - lowest) / (highest - lowest)
+            } else {
+                0.0
+            };
+            let min_corner = egui::pos2(
+                rect.left() + (k % GRID_RESOLUTION) as f32 * cell_side,
+                rect.bottom() - (k / GRID_RESOLUTION + 1) as f32 * cell_side,
+            );
+            // The cells overlap a bit, otherwise the gaps between them would show up
+            let cell_rect =
+                egui::Rect::from_min_size(min_corner, egui::vec2(cell_side + 0.5, cell_side + 0.5));
+            painter.rect_filled(cell_rect, 0.0, energy_color(t as f32));
+        }
+
+        for (i, point) in pattern_points.iter().enumerate() {
+            let position = to_screen(*point);
+            painter.circle_filled(position, 4.0, egui::Color32::WHITE);
+            painter.text(
+                position + egui::vec2(6.0, -6.0),
+                egui::Align2::LEFT_BOTTOM,
+                (i + 1).to_string(),
+                egui::FontId::monospace(12.0),
+                egui::Color32::WHITE,
+            );
+        }
+
+        if !self.points.is_empty() {
+            let path: Vec<egui::Pos2> = self.points.iter().map(|p| to_screen(*p)).collect();
+            painter.add(egui::Shape::line(
+                path.clone(),
+                egui::Stroke::new(1.5, egui::Color32::LIGHT_RED),
+            ));
+            if let Some(current) = path.last() {
+                painter.circle_filled(*current, 5.0, egui::Color32::RED);
+            }
+        }
+
+        let (x_label, y_label) = match self.projection {
+            Projection::Patterns => (
+                format!("pattern {}", self.first_pattern + 1),
+                format!("pattern {}", self.second_pattern + 1),
+            ),
+            Projection::Pca => (
+                "first component".to_string(),
+                "second component".to_string(),
+            ),
+        };
+        ui.label(format!(
+            "Horizontal: {}, vertical: {}. The energy goes from {:.2} (dark) to {:.2} (bright)",
+            x_label, y_label, lowest, highest
+        ));
+
+        // The value under the mouse, or the one of the current state
+        let shown = match response.hover_pos() {
+            Some(position) => Some([
+                min[0] + ((position.x - rect.left()) / side) as f64 * (max[0] - min[0]),
+                min[1] + ((rect.bottom() - position.y) / side) as f64 * (max[1] - min[1]),
+            ]),
+            None => self.points.back().cloned(),
+        };
+        if let Some([a, b]) = shown {
+            ui.label(format!(
+                "({:.2}, {:.2}) energy {:.2}",
+                a,
+                b,
+                landscape.energy(a, b)
+            ));
+        }
+    }
+
+    // Getters
+
+    // The plane the network has to compute the landscape on, if it's time to ask for it
+    pub fn landscape_request(&self) -> Option<Plane> {
+        self.plane_to_request.clone()
+    }
+
+    // Setters
+
+    pub fn set_landscape(&mut self, landscape: Landscape) {
+        self.points = self
+            .trajectory
+            .iter()
+            .map(|state| landscape.project(state))
+            .collect();
+        self.landscape = Some(landscape);
+    }
+
+    // While the view is closed the states aren't recorded and no landscape is asked for,
+    // when it opens the path starts again from the current state
+    pub fn set_open(&mut self, open: bool, state: &[f64]) {
+        if open && !self.open {
+            self.open = true;
+            self.clear_trajectory();
+            self.push_state(state);
+        } else if !open {
+            self.open = false;
+            self.plane_to_request = None;
+        }
+    }
+
+    pub fn push_state(&mut self, state: &[f64]) {
+        if !self.open {
+            return;
+        }
+        if self.trajectory.len() == MAX_TRAJECTORY {
+            self.trajectory.pop_front();
+            self.points.pop_front();
+        }
+        self.trajectory.push_back(state.to_vec());
+        if let Some(landscape) = &self.landscape {
+            self.points.push_back(landscape.project(state));
+        }
+        self.trajectory_changed = true;
+    }
+
+    pub fn weights_changed(&mut self) {
+        self.plane_changed = true;
+    }
+
+    pub fn clear_trajectory(&mut self) {
+        self.trajectory.clear();
+        self.points.clear();
+        if self.projection == Projection::Pca {
+            self.landscape = None;
+        }
+        self.plane_changed = true;
+    }
+
+    pub fn set_patterns(&mut self, patterns: &[Vec<f64>]) {
+        if self.patterns == patterns {
+            return;
+        }
+        self.patterns = patterns.to_vec();
+        let last = self.patterns.len().saturating_sub(1);
+        self.first_pattern = self.first_pattern.min(last);
+        self.second_pattern = self.second_pattern.min(last);
+        if self.projection == Projection::Patterns {
+            self.landscape = None;
+            self.points.clear();
+        }
+        self.plane_changed = true;
+    }
+
+    // The old states and landscape don't fit in the new layout
+    pub fn clear(&mut self) {
+        self.trajectory.clear();
+        self.points.clear();
+        self.landscape = None;
+        self.plane_changed = true;
+    }
+}
+
+impl Default for LandscapeView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn pattern_combo(ui: &mut egui::Ui, id: &str, selected: &mut usize, count: usize) {
+    egui::ComboBox::new(id, "")
+        .selected_text(format!("pattern {}", *selected + 1))
+        .show_ui(ui, |ui| {
+            for i in 0..count {
+                ui.selectable_value(selected, i, format!("pattern {}", i + 1));
+            }
+        });
+}
+
+// Low energies are dark blue, high energies bright yellow, going through green
+fn energy_color(t: f32) -> egui::Color32 {
+    let stops = [
+        (20.0, 24.0, 110.0),
+        (30.0, 150.0, 140.0),
+        (250.0, 230.0, 80.0),
+    ];
+    let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let i = (scaled as usize).min(stops.len() - 2);
+    let f = scaled - i as f32;
+    let (from, to) = (stops[i], stops[i + 1]);
+    let lerp = |a: f32, b: f32| (a + (b - a) * f) as u8;
+    egui::Color32::from_rgb(lerp(from.0, to.0), lerp(from.1, to.1), lerp(from.2, to.2))
+}
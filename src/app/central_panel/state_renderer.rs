@@ -1,3 +1,9 @@
+use crate::app::hop_net::NodeUpdate;
+
+// The outline of the node the network just updated, depending on whether it flipped or not
+const FLIPPED_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 140, 0);
+const UNCHANGED_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 170, 255);
+
 // This struct is used to store the renderer configuration, wich is : nodes_spacing, node_on_color, node_off_color
 struct RenderConfig {
     nodes_spacing: egui::Vec2,
@@ -6,25 +12,21 @@ struct RenderConfig {
     node_off_color: egui::Color32,
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn render_square_discrete<T: PartialOrd + std::ops::Neg<Output = T> + From<u32> + Copy>(
+// The highlighted node and the fields are relative to this grid, not to the whole state.
+// The grid is only drawn, the edits are up to the caller, it gets the node under the mouse if there's one
+pub fn render_square_discrete<T: PartialOrd + From<u32> + Copy + Into<f64>>(
     ui: &mut egui::Ui,
-    state: &mut Vec<T>,
-    state_change_mask: &mut Vec<bool>,
-    state_changed_falg: &mut bool,
+    grid_id: usize,
+    state: &[T],
     node_size: egui::Vec2,
     mouse_pos: egui::Pos2,
-    mouse_down: bool,
-    nodes_being_edited: &mut bool,
-) {
+    highlight: Option<NodeUpdate>,
+    fields: Option<&[f64]>,
+) -> Option<usize> {
     if state.is_empty() {
         panic!("Cannot render empty state");
     }
 
-    if state.len() != state_change_mask.len() {
-        panic!("State and state_change_mask are not the same length");
-    }
-
     let state_sqrt = (state.len() as f32).sqrt() as usize;
     // This check, is done every frame, may be a good idea find a better way to check this
     if state_sqrt.pow(2) != state.len() {
@@ -35,10 +37,12 @@ pub fn render_square_discrete<T: PartialOrd + std::ops::Neg<Output = T> + From<u
     // To be abele to confront with T, we need to convert into it
     let zero = T::from(0);
 
-    *state_changed_falg = false;
+    // The rect is checked instead of the response, since egui doesn't report other widgets
+    // as hovered while the mouse is dragging
+    let mut pointed = None;
 
     // Main node where the rendering happens
-    egui::Grid::new("central_panel_grid_0")
+    egui::Grid::new(format!("central_panel_grid_{}", grid_id))
         .spacing(egui::vec2(3.0, 3.0))
         .min_col_width(0.0)
         .min_row_height(0.0)
@@ -55,12 +59,24 @@ pub fn render_square_discrete<T: PartialOrd + std::ops::Neg<Output = T> + From<u
                 }
 
                 let response = ui.add(button);
-                // If the mouse is over the button, and the mouse is pressed, invert it's state
-                if response.rect.contains(mouse_pos) && mouse_down && !state_change_mask[i] {
-                    *state_changed_falg = true;
-                    state_change_mask[i] = true;
-                    state[i] = -state[i];
-                    *nodes_being_edited = true;
+                if let Some(update) = highlight.filter(|update| update.index == i) {
+                    let color = if update.flipped {
+                        FLIPPED_COLOR
+                    } else {
+                        UNCHANGED_COLOR
+                    };
+                    ui.painter().rect_stroke(
+                        response.rect.expand(1.5),
+                        2.0,
+                        egui::Stroke::new(2.5, color),
+                    );
+                }
+                if response.rect.contains(mouse_pos) {
+                    pointed = Some(i);
+                }
+                if response.hovered() {
+                    let value: f64 = state[i].into();
+                    response.on_hover_text(field_text(value, fields.map(|f| f[i])));
                 }
 
                 if (i + 1) % state_sqrt == 0 {
@@ -68,4 +84,71 @@ pub fn render_square_discrete<T: PartialOrd + std::ops::Neg<Output = T> + From<u
                 }
             }
         });
+    pointed
+}
+
+// The sign rule sets a node to 1 if its field is positive, and to -1 otherwise,
+// so s * h is how much the field can change before the node flips
+fn field_text(value: f64, field: Option<f64>) -> String {
+    let field = match field {
+        Some(field) => field,
+        None => return "Computing the local field...".to_string(),
+    };
+    let margin = if value > 0.0 { field } else { -field };
+    let stability = if margin > 0.0 || (margin == 0.0 && value <= 0.0) {
+        format!(
+            "Stable, the field has to change by {:.3} to flip it",
+            margin.abs()
+        )
+    } else {
+        "Unstable, it flips the next time it's updated".to_string()
+    };
+    format!("Local field: {:.3}\n{}", field, stability)
+}
+
+// Draws a small, non interactive copy of a state, it's used wherever a list of states has to be shown.
+// Every layer is drawn as a separate square, layers that are not a perfect square are drawn
+// on the smallest square that can contain them
+pub fn render_thumbnail(
+    ui: &mut egui::Ui,
+    state: &[f64],
+    layer_sizes: &[usize],
+    cell_size: f32,
+) -> egui::Response {
+    let sides: Vec<usize> = layer_sizes
+        .iter()
+        .map(|size| (*size as f32).sqrt().ceil().max(1.0) as usize)
+        .collect();
+    let gap = cell_size * 2.0;
+    let width = sides.iter().sum::<usize>() as f32 * cell_size + gap * (sides.len() - 1) as f32;
+    let height = *sides.iter().max().unwrap_or(&1) as f32 * cell_size;
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::click());
+
+    let painter = ui.painter_at(rect);
+    let mut layer_min = rect.min;
+    let mut start = 0;
+    for (size, side) in layer_sizes.iter().zip(sides.iter()) {
+        let layer_rect = egui::Rect::from_min_size(
+            layer_min,
+            egui::vec2(*side as f32 * cell_size, *side as f32 * cell_size),
+        );
+        painter.rect_filled(layer_rect, 0.0, egui::Color32::from_rgb(0, 0, 0));
+
+        let end = (start + size).min(state.len());
+        for (i, node) in state[start..end].iter().enumerate() {
+            if *node > 0.0 {
+                let min = layer_min + egui::vec2((i % side) as f32, (i / side) as f32) * cell_size;
+                let cell = egui::Rect::from_min_size(min, egui::vec2(cell_size, cell_size));
+                painter.rect_filled(cell, 0.0, egui::Color32::from_rgb(255, 255, 255));
+            }
+        }
+
+        layer_min.x += *side as f32 * cell_size + gap;
+        start = end;
+    }
+
+    if response.hovered() {
+        painter.rect_stroke(rect, 0.0, ui.visuals().widgets.hovered.fg_stroke);
+    }
+    response
 }
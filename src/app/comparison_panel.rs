@@ -0,0 +1,474 @@
+use crate::app::central_panel::state_renderer;
+use crate::app::hop_net::experiments;
+use crate::app::hop_net::learning_rules::LearningRule;
+use crate::app::hop_net::learning_rules::LearningRuleKind;
+use crate::app::hop_net::NetworkCommand;
+use crate::app::hop_net::NetworkResponse;
+use crate::app::hop_net::NetworkStatus;
+use crate::app::hop_net::NetworkType;
+use crate::app::hop_net::StepSpeed;
+use crate::app::hop_net::UpdateMode;
+use crate::app::thread_utils::NetworkHandle;
+use std::sync::mpsc;
+use strum::IntoEnumIterator;
+
+const MIN_COMPARED: usize = 2;
+const MAX_COMPARED: usize = 4;
+// The networks take a sweep together this often, slow enough to follow them side by side
+const SWEEP_SECONDS: f64 = 0.2;
+// The rest of the messages are handled in the next frames
+const MAX_MESSAGES_PER_FRAME: usize = 1000;
+// Roughly how wide each grid is drawn
+const GRID_WIDTH: f32 = 180.0;
+
+// One of the networks the user wants to compare, the rule matters only for the hebbian network
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Contestant {
+    net_type: NetworkType,
+    rule_kind: LearningRuleKind,
+}
+
+impl Contestant {
+    fn name(&self) -> String {
+        if self.net_type == NetworkType::SquareDiscrete {
+            format!("{} ({})", self.net_type, self.rule_kind)
+        } else {
+            self.net_type.to_string()
+        }
+    }
+}
+
+// A network started by the comparison, it lives in its own thread, next to the one of the app
+struct ComparedRun {
+    name: String,
+    handle: NetworkHandle,
+    state: Vec<f64>,
+    // How many patterns the network has learned so far, it starts only after it learned all of them
+    learned: usize,
+    is_running: bool,
+    status: Option<NetworkStatus>,
+    // The network was told to take a sweep and hasn't answered with its status yet,
+    // the others wait for it before they take the next one
+    sweep_pending: bool,
+    cycle_period: Option<usize>,
+    error: Option<String>,
+}
+
+// Runs a few networks side by side, trained on the same patterns and started from the same probe.
+// The networks are independent of the one of the app, closing the comparison stops their threads
+pub struct ComparisonPanel {
+    start_pressed: bool,
+    contestants: Vec<Contestant>,
+    // Only networks with the same layers as the one of the app can start from its state
+    two_layers: bool,
+    runs: Vec<ComparedRun>,
+    patterns: Vec<Vec<f64>>,
+    probe: Vec<f64>,
+    layer_sizes: Vec<usize>,
+    // True once the networks are done learning and have been set to the probe
+    started: bool,
+    // The sweeps the networks took together
+    sweeps: usize,
+    last_sweep_time: f64,
+}
+
+impl ComparisonPanel {
+    pub fn new() -> Self {
+        Self {
+            start_pressed: false,
+            contestants: default_contestants(false),
+            two_layers: false,
+            runs: Vec::new(),
+            patterns: Vec::new(),
+            probe: Vec::new(),
+            layer_sizes: Vec::new(),
+            started: false,
+            sweeps: 0,
+            last_sweep_time: 0.0,
+        }
+    }
+
+    // The list of networks to compare, it goes in the side panel with the other tools
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        self.start_pressed = false;
+
+        egui::CollapsingHeader::new("Network comparison").show(ui, |ui| {
+            let compatible: Vec<NetworkType> = NetworkType::iter()
+                .filter(|net_type| net_type.has_two_layers() == self.two_layers)
+                .collect();
+            let can_remove = self.contestants.len() > MIN_COMPARED;
+            let mut removed = None;
+            for (i, contestant) in self.contestants.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::new(format!("comparison_type_{}", i), "")
+                        .selected_text(contestant.net_type.to_string())
+                        .show_ui(ui, |ui| {
+                            for net_type in compatible.iter() {
+                                ui.selectable_value(
+                                    &mut contestant.net_type,
+                                    *net_type,
+                                    net_type.to_string(),
+                                );
+                            }
+                        });
+                    if contestant.net_type == NetworkType::SquareDiscrete {
+                        egui::ComboBox::new(format!("comparison_rule_{}", i), "")
+                            .selected_text(contestant.rule_kind.to_string())
+                            .show_ui(ui, |ui| {
+                                for kind in LearningRuleKind::iter() {
+                                    ui.selectable_value(
+                                        &mut contestant.rule_kind,
+                                        kind,
+                                        kind.to_string(),
+                                    );
+                                }
+                            });
+                    }
+                    if ui.add_enabled(can_remove, egui::Button::new("✖")).clicked() {
+                        removed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = removed {
+                self.contestants.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        self.contestants.len() < MAX_COMPARED,
+                        egui::Button::new("+ Add network"),
+                    )
+                    .clicked()
+                {
+                    let last = self.contestants[self.contestants.len() - 1];
+                    self.contestants.push(last);
+                }
+                self.start_pressed = ui
+                    .button("Compare")
+                    .on_hover_text("Every network learns the stored patterns, then they all start from the current state at the same time")
+                    .clicked();
+            });
+
+            if !self.runs.is_empty() {
+                ui.horizontal(|ui| {
+                    if ui.button("Stop").clicked() {
+                        self.stop();
+                    }
+                    if ui.button("Close").clicked() {
+                        self.close();
+                    }
+                });
+            }
+        });
+    }
+
+    // The grids of the networks being compared, in a window of their own so they can be seen next to the main one
+    pub fn generate_window(&mut self, ctx: &egui::Context) {
+        if self.runs.is_empty() {
+            return;
+        }
+        let time = ctx.input(|i| i.time);
+        self.process_messages(time);
+
+        let mut open = true;
+        egui::Window::new("Network comparison")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let cell_size = self.cell_size();
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.strong("Probe");
+                        state_renderer::render_thumbnail(
+                            ui,
+                            &self.probe,
+                            &self.layer_sizes,
+                            cell_size,
+                        );
+                        ui.label(format!("{} stored patterns", self.patterns.len()));
+                        if self.started {
+                            ui.label(format!("Sweep {}", self.sweeps));
+                        }
+                    });
+                    for run in self.runs.iter() {
+                        ui.separator();
+                        ui.vertical(|ui| {
+                            ui.strong(&run.name);
+                            state_renderer::render_thumbnail(
+                                ui,
+                                &run.state,
+                                &self.layer_sizes,
+                                cell_size,
+                            );
+                            self.run_status_ui(ui, run);
+                        });
+                    }
+                });
+            });
+        if !open {
+            self.close();
+        }
+    }
+
+    fn run_status_ui(&self, ui: &mut egui::Ui, run: &ComparedRun) {
+        if let Some(error) = &run.error {
+            ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", error));
+        }
+        if !self.started {
+            ui.label(format!(
+                "Learning... {}/{}",
+                run.learned.min(self.patterns.len()),
+                self.patterns.len()
+            ));
+            return;
+        }
+
+        match run.status {
+            Some(status) => {
+                ui.label(format!("Steps: {}", status.steps));
+                ui.label(format!("Energy: {:.2}", status.energy));
+            }
+            None => {
+                ui.label("Steps: -");
+                ui.label("Energy: -");
+            }
+        }
+
+        let state = if run.is_running {
+            "Running".to_string()
+        } else {
+            match run.cycle_period {
+                Some(period) => format!("Stopped in a cycle of period {}", period),
+                None => "Stopped".to_string(),
+            }
+        };
+        ui.label(state);
+
+        let prefix = if run.is_running {
+            "Match"
+        } else {
+            "Final match"
+        };
+        let text = match best_match(&run.state, &self.patterns) {
+            Some((i, overlap)) if overlap >= 1.0 => format!("{}: pattern {} ✔", prefix, i + 1),
+            Some((i, overlap)) if overlap <= -1.0 => {
+                format!("{}: negation of pattern {}", prefix, i + 1)
+            }
+            Some((i, overlap)) => format!(
+                "{}: closest to pattern {}, overlap {:.2}",
+                prefix,
+                i + 1,
+                overlap
+            ),
+            None => format!("{}: no stored patterns", prefix),
+        };
+        ui.label(text);
+    }
+
+    fn process_messages(&mut self, time: f64) {
+        for run in self.runs.iter_mut() {
+            for _ in 0..MAX_MESSAGES_PER_FRAME {
+                match run.handle.try_recv() {
+                    Ok(NetworkResponse::NewState(state)) => run.state = state,
+                    Ok(NetworkResponse::StateDelta(changes)) => {
+                        for (i, value) in changes {
+                            if let Some(node) = run.state.get_mut(i) {
+                                *node = value;
+                            }
+                        }
+                    }
+                    Ok(NetworkResponse::PatternStability(unstable_bits)) => {
+                        run.learned = run.learned.max(unstable_bits.len());
+                    }
+                    Ok(NetworkResponse::CycleDetected(cycle)) => {
+                        run.cycle_period = Some(cycle.period);
+                    }
+                    Ok(NetworkResponse::Status(status)) => {
+                        run.status = Some(status);
+                        run.sweep_pending = false;
+                    }
+                    // The network reached a fixed point or a cycle, the status of its last sweep is still on its way
+                    Ok(NetworkResponse::Stopped) => run.is_running = false,
+                    Ok(NetworkResponse::Error(error)) => run.error = Some(error.to_string()),
+                    Ok(_) => {}
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        run.error = Some("The network thread closed".to_string());
+                        run.is_running = false;
+                        run.sweep_pending = false;
+                        break;
+                    }
+                }
+            }
+        }
+
+        // A network that failed to learn doesn't hold the others back
+        if !self.started
+            && self
+                .runs
+                .iter()
+                .all(|run| run.learned >= self.patterns.len() || run.error.is_some())
+        {
+            self.started = true;
+            for run in self.runs.iter_mut() {
+                run.is_running = run.error.is_none()
+                    && run
+                        .handle
+                        .send(NetworkCommand::SetState(self.probe.clone()));
+            }
+        }
+
+        // The networks step in lockstep, a sweep each, so that a faster thread doesn't get ahead of the others.
+        // The status is asked right after the sweep, its answer means the sweep is done
+        let sweep_done = self.runs.iter().all(|run| !run.sweep_pending);
+        if self.started && sweep_done && time - self.last_sweep_time >= SWEEP_SECONDS {
+            self.last_sweep_time = time;
+            let mut swept = false;
+            for run in self.runs.iter_mut().filter(|run| run.is_running) {
+                run.sweep_pending = run.handle.send(NetworkCommand::StepSweep)
+                    && run.handle.send(NetworkCommand::GetStatus);
+                run.is_running = run.sweep_pending;
+                swept |= run.sweep_pending;
+            }
+            if swept {
+                self.sweeps += 1;
+            }
+        }
+    }
+
+    // The grids get smaller as the networks get bigger, so that they all fit
+    fn cell_size(&self) -> f32 {
+        let side: usize = self
+            .layer_sizes
+            .iter()
+            .map(|size| (*size as f32).sqrt().ceil() as usize)
+            .sum();
+        (GRID_WIDTH / side.max(1) as f32).clamp(2.0, 12.0)
+    }
+
+    // The sweep under way is let finish, no other one is started
+    fn stop(&mut self) {
+        for run in self.runs.iter_mut() {
+            run.is_running = false;
+        }
+    }
+
+    // Dropping the handles closes the channels, and the threads close with them
+    fn close(&mut self) {
+        self.runs.clear();
+        self.started = false;
+    }
+
+    // Getters
+
+    pub fn start_pressed(&self) -> bool {
+        self.start_pressed
+    }
+
+    // True while the networks learn or step, the gui has to keep drawing them
+    pub fn is_running(&self) -> bool {
+        !self.runs.is_empty()
+            && (!self.started
+                || self
+                    .runs
+                    .iter()
+                    .any(|run| run.is_running || run.sweep_pending))
+    }
+
+    // Setters
+
+    // A new comparison replaces the old one, each network is set up like the one of the app.
+    // The panel times the sweeps itself, so the networks don't need the speed of the app
+    pub fn start(
+        &mut self,
+        patterns: &[Vec<f64>],
+        probe: Vec<f64>,
+        layer_sizes: Vec<usize>,
+        update_mode: UpdateMode,
+    ) {
+        self.close();
+        self.patterns = patterns.to_vec();
+        self.probe = probe;
+        self.layer_sizes = layer_sizes;
+        self.sweeps = 0;
+        self.last_sweep_time = 0.0;
+
+        for contestant in self.contestants.iter() {
+            let handle = NetworkHandle::spawn(
+                contestant.net_type,
+                self.probe.clone(),
+                self.layer_sizes.clone(),
+                StepSpeed::Max,
+            );
+            let rule = LearningRule {
+                kind: contestant.rule_kind,
+                ..LearningRule::default()
+            };
+            let mut commands = vec![
+                NetworkCommand::SetLearningRule(rule),
+                NetworkCommand::SetUpdateMode(update_mode),
+            ];
+            commands.extend(self.patterns.iter().cloned().map(NetworkCommand::Learn));
+            let sent = commands.into_iter().all(|command| handle.send(command));
+
+            self.runs.push(ComparedRun {
+                name: contestant.name(),
+                handle,
+                state: self.probe.clone(),
+                learned: 0,
+                is_running: false,
+                status: None,
+                sweep_pending: false,
+                cycle_period: None,
+                error: (!sent).then(|| "The network thread closed".to_string()),
+            });
+        }
+    }
+
+    // The networks to compare are reset when they can't start from the state of the new network anymore
+    pub fn set_net_type(&mut self, net_type: NetworkType) {
+        let two_layers = net_type.has_two_layers();
+        if two_layers != self.two_layers {
+            self.two_layers = two_layers;
+            self.contestants = default_contestants(two_layers);
+        }
+    }
+}
+
+impl Default for ComparisonPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn default_contestants(two_layers: bool) -> Vec<Contestant> {
+    let types = if two_layers {
+        [
+            NetworkType::BamSquareDiscrete,
+            NetworkType::RbmSquareDiscrete,
+        ]
+    } else {
+        [
+            NetworkType::SquareDiscrete,
+            NetworkType::StorkeySquareDiscrete,
+        ]
+    };
+    types
+        .into_iter()
+        .map(|net_type| Contestant {
+            net_type,
+            rule_kind: LearningRuleKind::Hebbian,
+        })
+        .collect()
+}
+
+// The stored pattern the state is closest to, a negation counts as close as the pattern itself.
+// The overlap goes from -1 to 1, the ends are only reached by the pattern and its negation
+fn best_match(state: &[f64], patterns: &[Vec<f64>]) -> Option<(usize, f64)> {
+    patterns
+        .iter()
+        .enumerate()
+        .filter(|(_, pattern)| pattern.len() == state.len())
+        .map(|(i, pattern)| (i, experiments::overlap(pattern, state)))
+        .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+}
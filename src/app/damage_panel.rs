@@ -0,0 +1,182 @@
+use crate::app::hop_net::damage::Damage;
+use crate::app::hop_net::damage::DamageKind;
+use crate::app::hop_net::experiments::DamageExperiment;
+use crate::app::hop_net::experiments::ExperimentPoint;
+use strum::IntoEnumIterator;
+
+pub struct DamagePanel {
+    kind: DamageKind,
+    amount: f64,
+    apply_pressed: bool,
+    restore_pressed: bool,
+    levels: usize,
+    probe_noise: f64,
+    trials: usize,
+    run_pressed: bool,
+    // Levels tested out of the total, it's Some only while an experiment is running
+    progress: Option<(usize, usize)>,
+    results: Vec<ExperimentPoint>,
+    results_kind: DamageKind,
+}
+
+impl DamagePanel {
+    pub fn new() -> Self {
+        Self {
+            kind: DamageKind::Dilution,
+            amount: 0.3,
+            apply_pressed: false,
+            restore_pressed: false,
+            levels: 11,
+            probe_noise: 0.1,
+            trials: 5,
+            run_pressed: false,
+            progress: None,
+            results: Vec::new(),
+            results_kind: DamageKind::Dilution,
+        }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        self.apply_pressed = false;
+        self.restore_pressed = false;
+        self.run_pressed = false;
+
+        egui::CollapsingHeader::new("Damage").show(ui, |ui| {
+            egui::ComboBox::new("damage_kind_combo", "damage")
+                .selected_text(self.kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in DamageKind::iter() {
+                        ui.selectable_value(&mut self.kind, kind, kind.to_string());
+                    }
+                });
+
+            // Clipping has no amount, the weights are either clipped or not
+            if self.kind != DamageKind::ClipWeights {
+                let text = match self.kind {
+                    DamageKind::GaussianNoise => "noise std",
+                    _ => "fraction",
+                };
+                self.amount = self.amount.min(self.kind.max_amount());
+                ui.add(
+                    egui::Slider::new(&mut self.amount, 0.0..=self.kind.max_amount()).text(text),
+                );
+            }
+
+            ui.horizontal(|ui| {
+                self.apply_pressed = ui.button("Apply damage").clicked();
+                self.restore_pressed = ui
+                    .button("Restore weights")
+                    .on_hover_text("Undoes the damage done since the last time the network learned")
+                    .clicked();
+            });
+
+            ui.add_space(5.0);
+            ui.label("Damage experiment:");
+            ui.add(egui::Slider::new(&mut self.levels, 2..=21).text("damage levels"));
+            ui.add(egui::Slider::new(&mut self.probe_noise, 0.0..=0.5).text("probe noise"));
+            ui.add(egui::Slider::new(&mut self.trials, 1..=50).text("probes per pattern"));
+
+            match self.progress {
+                Some((done, total)) => {
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / total as f32)
+                            .text(format!("{} of {} levels", done, total)),
+                    );
+                }
+                None => {
+                    self.run_pressed = ui
+                        .button("Run experiment")
+                        .on_hover_text("Recalls every stored pattern from noisy probes, at growing amounts of damage")
+                        .clicked();
+                }
+            }
+
+            if !self.results.is_empty() {
+                self.plot_results(ui);
+            }
+        });
+    }
+
+    fn plot_results(&self, ui: &mut egui::Ui) {
+        use egui::plot::{Legend, Line, Plot, PlotPoints};
+
+        let recall: PlotPoints = self
+            .results
+            .iter()
+            .map(|point| [point.amount, point.recall_rate])
+            .collect();
+        let overlap: PlotPoints = self
+            .results
+            .iter()
+            .map(|point| [point.amount, point.mean_overlap])
+            .collect();
+
+        ui.label(format!("Recall against {}", self.results_kind));
+        Plot::new("damage_experiment_plot")
+            .height(150.0)
+            .legend(Legend::default())
+            .allow_drag(false)
+            .allow_zoom(false)
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(recall).name("recall rate"));
+                plot_ui.line(Line::new(overlap).name("mean overlap"));
+            });
+    }
+
+    // Getters
+
+    pub fn apply_pressed(&self) -> bool {
+        self.apply_pressed
+    }
+
+    pub fn restore_pressed(&self) -> bool {
+        self.restore_pressed
+    }
+
+    pub fn run_pressed(&self) -> bool {
+        self.run_pressed
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.progress.is_some()
+    }
+
+    pub fn get_damage(&self) -> Damage {
+        Damage {
+            kind: self.kind,
+            amount: self.amount,
+        }
+    }
+
+    pub fn get_experiment(&self) -> DamageExperiment {
+        DamageExperiment {
+            kind: self.kind,
+            levels: self.levels,
+            probe_noise: self.probe_noise,
+            trials: self.trials,
+        }
+    }
+
+    // Setters
+
+    // The network crashed during the experiment, there are no results coming
+    pub fn cancel(&mut self) {
+        self.progress = None;
+    }
+
+    pub fn set_progress(&mut self, done: usize, total: usize) {
+        self.progress = Some((done, total));
+    }
+
+    pub fn set_results(&mut self, results: Vec<ExperimentPoint>) {
+        self.progress = None;
+        self.results = results;
+        self.results_kind = self.kind;
+    }
+}
+
+impl Default for DamagePanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
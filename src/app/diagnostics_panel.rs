@@ -0,0 +1,118 @@
+use crate::app::hop_net::diagnostics::DiagnosticsReport;
+
+pub struct DiagnosticsPanel {
+    check_pressed: bool,
+    symmetrize_pressed: bool,
+    clear_diagonal_pressed: bool,
+    report: Option<DiagnosticsReport>,
+}
+
+impl DiagnosticsPanel {
+    pub fn new() -> Self {
+        Self {
+            check_pressed: false,
+            symmetrize_pressed: false,
+            clear_diagonal_pressed: false,
+            report: None,
+        }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        self.check_pressed = false;
+        self.symmetrize_pressed = false;
+        self.clear_diagonal_pressed = false;
+
+        egui::CollapsingHeader::new("Diagnostics").show(ui, |ui| {
+            self.check_pressed = ui.button("Check weights").clicked();
+
+            if let Some(report) = &self.report {
+                egui::Grid::new("diagnostics_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("symmetry error");
+                        ui.label(format!("{:.3e}", report.symmetry_error))
+                            .on_hover_text(format!(
+                                "|W - Wᵀ| / |W|, the biggest |w_ij - w_ji| is {:.3e}",
+                                report.max_asymmetry
+                            ));
+                        ui.end_row();
+
+                        ui.label("biggest |w_ii|");
+                        ui.label(format!("{:.3e}", report.max_diagonal));
+                        ui.end_row();
+
+                        ui.label("eigenvalues");
+                        ui.label(format!(
+                            "{:.3} to {:.3}",
+                            report.min_eigenvalue, report.max_eigenvalue
+                        ))
+                        .on_hover_text("The range of the spectrum of (W + Wᵀ) / 2");
+                        ui.end_row();
+
+                        ui.label("weights norm");
+                        ui.label(format!("{:.3}", report.weight_norm));
+                        ui.end_row();
+
+                        ui.label("fixed points");
+                        match report.fixed_point_fraction() {
+                            Some(fraction) => ui.label(format!(
+                                "{} of {} patterns ({:.0}%)",
+                                report.stable_patterns,
+                                report.pattern_count,
+                                fraction * 100.0
+                            )),
+                            None => ui.label("no stored patterns"),
+                        };
+                        ui.end_row();
+                    });
+
+                if !report.guarantees_convergence() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "The weights aren't symmetric with an empty diagonal, the network may never settle",
+                    );
+                }
+            }
+
+            ui.horizontal(|ui| {
+                self.symmetrize_pressed = ui
+                    .button("Symmetrize")
+                    .on_hover_text("Sets both w_ij and w_ji to their mean")
+                    .clicked();
+                self.clear_diagonal_pressed = ui.button("Clear diagonal").clicked();
+            });
+        });
+    }
+
+    // Getters
+
+    pub fn check_pressed(&self) -> bool {
+        self.check_pressed
+    }
+
+    pub fn symmetrize_pressed(&self) -> bool {
+        self.symmetrize_pressed
+    }
+
+    pub fn clear_diagonal_pressed(&self) -> bool {
+        self.clear_diagonal_pressed
+    }
+
+    // Setters
+
+    pub fn set_report(&mut self, report: DiagnosticsReport) {
+        self.report = Some(report);
+    }
+
+    // The report is about weights that aren't there anymore
+    pub fn clear_report(&mut self) {
+        self.report = None;
+    }
+}
+
+impl Default for DiagnosticsPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
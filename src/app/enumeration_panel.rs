@@ -0,0 +1,290 @@
+use crate::app::central_panel::state_renderer;
+use crate::app::hop_net::enumeration::Enumeration;
+use crate::app::hop_net::enumeration::FixedPointKind;
+use crate::app::hop_net::enumeration::MAX_ENUMERATION_SIZE;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum SortKey {
+    Kind,
+    Energy,
+    Basin,
+}
+
+// Lists every fixed point of a small network, with its energy and how many states end up in it
+pub struct EnumerationPanel {
+    enumerate_pressed: bool,
+    // Why the network can't be enumerated, the button is disabled while it's Some
+    unavailable_reason: Option<&'static str>,
+    // Work done out of the total, it's Some only while the states are being enumerated
+    progress: Option<(usize, usize)>,
+    // The outer None means nothing was enumerated yet, the inner one that the network was too big
+    result: Option<Option<Enumeration>>,
+    // The order the fixed points are shown in, the result keeps its own order so the indices sent to the app stay stable
+    sorted: Vec<usize>,
+    sort_key: SortKey,
+    ascending: bool,
+    thumbnail_cell_size: f32,
+    state_to_load: Option<usize>,
+    // What happened the last time the table was saved to a file
+    save_message: Option<String>,
+}
+
+impl EnumerationPanel {
+    pub fn new() -> Self {
+        Self {
+            enumerate_pressed: false,
+            unavailable_reason: None,
+            progress: None,
+            result: None,
+            sorted: Vec::new(),
+            sort_key: SortKey::Energy,
+            ascending: true,
+            thumbnail_cell_size: 3.0,
+            state_to_load: None,
+            save_message: None,
+        }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        self.enumerate_pressed = false;
+        self.state_to_load = None;
+
+        egui::CollapsingHeader::new("State enumeration").show(ui, |ui| {
+            match self.progress {
+                Some((done, total)) => {
+                    let fraction = done as f32 / total.max(1) as f32;
+                    ui.add(
+                        egui::ProgressBar::new(fraction)
+                            .text(format!("{:.0}%", fraction * 100.0)),
+                    );
+                }
+                None => {
+                    self.enumerate_pressed = ui
+                        .add_enabled(
+                            self.unavailable_reason.is_none(),
+                            egui::Button::new("Enumerate states"),
+                        )
+                        .on_hover_text(format!(
+                            "Follows the dynamics from every possible state to find all the fixed points, only for networks with up to {} nodes",
+                            MAX_ENUMERATION_SIZE
+                        ))
+                        .on_disabled_hover_text(self.unavailable_reason.unwrap_or_default())
+                        .clicked();
+                }
+            }
+
+            match &self.result {
+                Some(Some(_)) => self.result_ui(ui),
+                Some(None) => {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "The network is too big, only networks with up to {} nodes can be enumerated",
+                            MAX_ENUMERATION_SIZE
+                        ),
+                    );
+                }
+                None => {}
+            }
+        });
+    }
+
+    fn result_ui(&mut self, ui: &mut egui::Ui) {
+        let enumeration = match &self.result {
+            Some(Some(enumeration)) => enumeration,
+            _ => return,
+        };
+
+        ui.label(format!(
+            "{} fixed points out of {} states",
+            enumeration.fixed_points.len(),
+            enumeration.state_count
+        ));
+        if enumeration.cycling_states > 0 {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "{} states never reach a fixed point, the weights aren't symmetric",
+                    enumeration.cycling_states
+                ),
+            );
+        }
+
+        let mut clicked_key = None;
+        egui::ScrollArea::vertical()
+            .id_source("enumeration_scroll")
+            .max_height(250.0)
+            .show(ui, |ui| {
+                egui::Grid::new("enumeration_grid")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("");
+                        for (key, name) in [
+                            (SortKey::Kind, "kind"),
+                            (SortKey::Energy, "energy"),
+                            (SortKey::Basin, "basin"),
+                        ] {
+                            let arrow = match (self.sort_key == key, self.ascending) {
+                                (true, true) => " ⏶",
+                                (true, false) => " ⏷",
+                                (false, _) => "",
+                            };
+                            if ui
+                                .selectable_label(
+                                    self.sort_key == key,
+                                    format!("{}{}", name, arrow),
+                                )
+                                .clicked()
+                            {
+                                clicked_key = Some(key);
+                            }
+                        }
+                        ui.end_row();
+
+                        for &i in self.sorted.iter() {
+                            let point = &enumeration.fixed_points[i];
+                            let response = state_renderer::render_thumbnail(
+                                ui,
+                                &point.state,
+                                &enumeration.layer_sizes,
+                                self.thumbnail_cell_size,
+                            );
+                            if response.on_hover_text("Click to load").clicked() {
+                                self.state_to_load = Some(i);
+                            }
+                            ui.label(point.kind.to_string());
+                            ui.label(format!("{:.3}", point.energy));
+                            ui.label(format!(
+                                "{} ({:.1}%)",
+                                point.basin_size,
+                                point.basin_size as f64 / enumeration.state_count as f64 * 100.0
+                            ));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        ui.horizontal(|ui| {
+            if ui.button("Copy CSV").clicked() {
+                let csv = enumeration.to_csv();
+                ui.output_mut(|output| output.copied_text = csv);
+            }
+            // There is no file system on the web
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui
+                .button("Save CSV")
+                .on_hover_text("Writes fixed_points.csv in the working directory")
+                .clicked()
+            {
+                self.save_message = Some(
+                    match std::fs::write("fixed_points.csv", enumeration.to_csv()) {
+                        Ok(()) => "Saved to fixed_points.csv".to_string(),
+                        Err(e) => format!("Couldn't save the table: {}", e),
+                    },
+                );
+            }
+        });
+        if let Some(message) = &self.save_message {
+            ui.label(message);
+        }
+
+        // Clicking the column that is already selected flips the order
+        if let Some(key) = clicked_key {
+            if self.sort_key == key {
+                self.ascending = !self.ascending;
+            } else {
+                self.sort_key = key;
+                self.ascending = true;
+            }
+            self.sort();
+        }
+    }
+
+    fn sort(&mut self) {
+        let enumeration = match &self.result {
+            Some(Some(enumeration)) => enumeration,
+            _ => return,
+        };
+
+        let points = &enumeration.fixed_points;
+        let key = self.sort_key;
+        self.sorted = (0..points.len()).collect();
+        self.sorted.sort_by(|&a, &b| {
+            let (a, b) = (&points[a], &points[b]);
+            match key {
+                SortKey::Kind => kind_rank(&a.kind)
+                    .cmp(&kind_rank(&b.kind))
+                    .then(a.energy.total_cmp(&b.energy)),
+                SortKey::Energy => a.energy.total_cmp(&b.energy),
+                SortKey::Basin => a.basin_size.cmp(&b.basin_size),
+            }
+        });
+        if !self.ascending {
+            self.sorted.reverse();
+        }
+    }
+
+    // Getters
+
+    pub fn enumerate_pressed(&self) -> bool {
+        self.enumerate_pressed
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.progress.is_some()
+    }
+
+    // The state of the fixed point the user clicked on, if any
+    pub fn state_to_load(&self) -> Option<Vec<f64>> {
+        match (&self.result, self.state_to_load) {
+            (Some(Some(enumeration)), Some(i)) => Some(enumeration.fixed_points[i].state.clone()),
+            _ => None,
+        }
+    }
+
+    // Setters
+
+    pub fn set_unavailable_reason(&mut self, reason: Option<&'static str>) {
+        self.unavailable_reason = reason;
+    }
+
+    // The network crashed during the enumeration, there is no result coming
+    pub fn cancel(&mut self) {
+        self.progress = None;
+    }
+
+    pub fn set_progress(&mut self, done: usize, total: usize) {
+        self.progress = Some((done, total));
+    }
+
+    pub fn set_result(&mut self, result: Option<Enumeration>) {
+        self.progress = None;
+        self.result = Some(result);
+        self.save_message = None;
+        self.sort();
+    }
+
+    // The fixed points belong to weights that aren't there anymore
+    pub fn clear_result(&mut self) {
+        self.result = None;
+        self.sorted.clear();
+        self.save_message = None;
+    }
+}
+
+impl Default for EnumerationPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The stored patterns come first, then their negations, the mixtures and the rest
+fn kind_rank(kind: &FixedPointKind) -> (usize, usize) {
+    match kind {
+        FixedPointKind::Pattern(i) => (0, *i),
+        FixedPointKind::Negation(i) => (1, *i),
+        FixedPointKind::Mixture(_) => (2, 0),
+        FixedPointKind::Spurious => (3, 0),
+    }
+}
@@ -0,0 +1,60 @@
+use crate::app::hop_net::network_error::NetworkError;
+
+// The errors go away by themselves after this long, unless they are closed before
+const ERROR_SECONDS: f64 = 10.0;
+
+// Shows the errors reported by the network at the top of the window, the most recent one last
+pub struct ErrorBanner {
+    // Each error with the time it was first shown, None until it's drawn for the first time
+    errors: Vec<(NetworkError, Option<f64>)>,
+}
+
+impl ErrorBanner {
+    pub fn new() -> Self {
+        Self { errors: Vec::new() }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        let time = ui.input(|i| i.time);
+        let mut dismissed = None;
+        for (i, (error, shown_at)) in self.errors.iter_mut().enumerate() {
+            let shown_at = *shown_at.get_or_insert(time);
+            if time - shown_at > ERROR_SECONDS {
+                dismissed = Some(i);
+            }
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", error));
+                if ui.small_button("✖").clicked() {
+                    dismissed = Some(i);
+                }
+            });
+        }
+
+        // Only one error goes away each frame, the others follow in the next ones
+        if let Some(i) = dismissed {
+            self.errors.remove(i);
+        }
+        // Otherwise the errors would expire only when the mouse moves
+        if !self.errors.is_empty() {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f64(1.0));
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    // Setters
+
+    pub fn push(&mut self, error: NetworkError) {
+        println!("Network error: {}", error);
+        self.errors.push((error, None));
+    }
+}
+
+impl Default for ErrorBanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
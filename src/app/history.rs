@@ -0,0 +1,111 @@
+use crate::app::hop_net;
+use crate::app::shortcuts::Action;
+use crate::app::shortcuts::ShortcutHints;
+
+// The oldest actions are forgotten after this many
+const MAX_HISTORY: usize = 50;
+
+// What the app knows about the network, it's all that's needed to build it again.
+// The weights are not part of it, the network learns the patterns again instead
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot {
+    pub net_type: hop_net::NetworkType,
+    pub layer_sizes: Vec<usize>,
+    pub patterns: Vec<Vec<f64>>,
+    pub state: Vec<f64>,
+}
+
+// Every action is stored with what the app looked like before it, undoing it swaps that with what the app looks like now
+pub struct History {
+    undo: Vec<(String, Snapshot)>,
+    redo: Vec<(String, Snapshot)>,
+    undo_pressed: bool,
+    redo_pressed: bool,
+    shortcut_hints: ShortcutHints,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            undo_pressed: false,
+            redo_pressed: false,
+            shortcut_hints: ShortcutHints::default(),
+        }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let hover = match self.undo.last() {
+                Some((label, _)) => format!("Undo: {}", label),
+                None => "Nothing to undo".to_string(),
+            };
+            let response = ui
+                .add_enabled(!self.undo.is_empty(), egui::Button::new("⟲ Undo"))
+                .on_disabled_hover_text("Nothing to undo");
+            let response = self.shortcut_hints.tooltip(response, Action::Undo, &hover);
+            self.undo_pressed = response.clicked();
+
+            let hover = match self.redo.last() {
+                Some((label, _)) => format!("Redo: {}", label),
+                None => "Nothing to redo".to_string(),
+            };
+            let response = ui
+                .add_enabled(!self.redo.is_empty(), egui::Button::new("⟳ Redo"))
+                .on_disabled_hover_text("Nothing to redo");
+            let response = self.shortcut_hints.tooltip(response, Action::Redo, &hover);
+            self.redo_pressed = response.clicked();
+        });
+    }
+
+    // Getters
+
+    pub fn undo_pressed(&self) -> bool {
+        self.undo_pressed
+    }
+
+    pub fn redo_pressed(&self) -> bool {
+        self.redo_pressed
+    }
+
+    // Setters
+
+    pub fn set_shortcut_hints(&mut self, hints: ShortcutHints) {
+        self.shortcut_hints = hints;
+    }
+
+    // Called right before an action, with what the app looks like before it. A new action makes the undone ones unreachable
+    pub fn record(&mut self, label: &str, before: Snapshot) {
+        self.undo.push((label.to_string(), before));
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    // Returns what the app has to go back to, the current snapshot is kept to redo the action
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let (label, before) = self.undo.pop()?;
+        self.redo.push((label, current));
+        Some(before)
+    }
+
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let (label, after) = self.redo.pop()?;
+        self.undo.push((label, current));
+        Some(after)
+    }
+
+    // The snapshots can't describe the optimization networks, so the actions before one can't be undone
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,37 +1,168 @@
 // This file is here because otherwise the rust copiler doesn't corrctly compute the module tree
 // there is probably a better way to do this, but at least for the moment this is good enough
 // the problems probably originates from me oranizing the fils in a java-like fashion
+pub mod bam_network;
+pub mod batch_recall;
+pub mod boltzmann_machine;
 pub mod classic_network;
+pub mod cycle_detection;
+pub mod damage;
+pub mod diagnostics;
+pub mod enumeration;
+pub mod experiments;
+pub mod landscape;
+pub mod learning_rules;
+pub mod local_fields;
+pub mod matrix;
+pub mod network_error;
+pub mod optimization;
+pub mod optimization_network;
+pub mod precision_adapter;
+pub mod scalar;
+pub mod sequence_network;
 pub mod storkey_learning;
+pub mod unlearning;
 
+use std::borrow::Cow;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
+use batch_recall::RecallOptions;
+use batch_recall::RecallResult;
+use learning_rules::LearningRule;
+use learning_rules::TrainingCallback;
+use matrix::Matrix;
+use network_error::NetworkError;
 use rand::prelude::SliceRandom;
+use scalar::Scalar;
 use strum_macros::EnumIter;
 
+// The networks can't work with fewer nodes than this
+pub const MIN_STATE_SIZE: usize = 4;
+
 // ---------------------------------Start of Net trait---------------------------------
-pub trait Net<T> {
-    fn get_state(&self) -> Vec<T>;
+pub trait Net<T: Scalar> {
+    fn get_state_ref(&self) -> &[T];
+
+    fn get_state(&self) -> Vec<T> {
+        self.get_state_ref().to_vec()
+    }
 
     fn learn(&mut self, state: &[T]);
 
-    fn step(&mut self) -> (bool, Vec<T>);
+    // Forgets everything and learns the patterns, in their order. The networks that train on all their patterns
+    // at every learn train only once, instead of once for every pattern
+    fn learn_all(&mut self, patterns: &[Vec<T>]) {
+        self.reset_weights();
+        for pattern in patterns {
+            self.learn(pattern);
+        }
+    }
+
+    // Returns true if the state changed, the new state can be read with get_state_ref,
+    // so it's copied only by who really needs it
+    fn step(&mut self) -> bool;
 
     fn get_steps(&self) -> usize;
 
-    fn set_state(&mut self, state: &[T]);
+    // The node updated by the last step and the field it was updated with.
+    // Only the asynchronous steps update a single node, after a synchronous one this is None
+    fn get_last_update(&self) -> Option<(usize, f64)> {
+        None
+    }
+
+    // A state the network can't work with is refused, and the network is left as it was
+    fn set_state(&mut self, state: &[T]) -> Result<(), NetworkError>;
 
     fn reset_weights(&mut self);
 
-    fn get_weights(&self) -> Vec<Vec<T>>;
+    // Borrowed by the networks that store their weights as they are, the others build them on request
+    fn get_weights(&self) -> Cow<'_, Matrix<T>>;
+
+    // Calls modify on every weight matrix of the network, networks with more than one matrix call it once for each.
+    // Anything that depends on the weights is updated afterwards
+    fn modify_weights(&mut self, modify: &mut dyn FnMut(&mut Matrix<T>));
+
+    // The weights the network uses to step, only for the networks that need nothing but them and the sign rule.
+    // The ones that depend on something else too (like thresholds or past states) keep returning None,
+    // otherwise their recalls could be run in parallel without it, giving wrong results
+    fn get_recall_weights(&self) -> Option<&Matrix<T>> {
+        None
+    }
+
+    // Runs every probe from the recall weights at once, None for the networks that don't have them.
+    // Networks that wrap another one forward this, so the recall runs on the weights in their own type
+    fn batch_recall(
+        &self,
+        probes: &[Vec<f64>],
+        patterns: &[Vec<f64>],
+        options: RecallOptions,
+    ) -> Option<Result<Vec<RecallResult>, NetworkError>> {
+        self.get_recall_weights()
+            .map(|weights| batch_recall::batch_recall(weights, probes, patterns, options))
+    }
+
+    // The local field of every node if the network were in the given state, the sign rule takes each node
+    // to the sign of its field, so a state is a fixed point if every field agrees with it
+    fn get_local_fields(&self, state: &[T]) -> Vec<f64> {
+        let weights = match self.get_recall_weights() {
+            Some(weights) => Cow::Borrowed(weights),
+            None => self.get_weights(),
+        };
+        let mut fields = vec![T::Acc::default(); weights.rows()];
+        weights.mul_vec(state, &mut fields);
+        fields.into_iter().map(T::acc_to_f64).collect()
+    }
+
+    fn set_update_mode(&mut self, mode: UpdateMode);
+
+    fn get_update_mode(&self) -> UpdateMode;
+
+    // Everything that determines the future of the network, for most networks it's just the state,
+    // but networks that remember their past have to include it, otherwise we would see cycles where there are none
+    fn get_dynamic_state(&self) -> Vec<T> {
+        self.get_state()
+    }
+
+    // Networks with more than one layer of nodes keep all of them in the same state, one after the other
+    fn get_layer_sizes(&self) -> Vec<usize> {
+        vec![self.get_state().len()]
+    }
+
+    // Single layer networks ignore this, their size is changed through set_state
+    fn set_layer_sizes(&mut self, _sizes: &[usize]) -> Result<(), NetworkError> {
+        Ok(())
+    }
+
+    // Networks built around a single learning rule ignore this, the rule is used from the next learn on
+    fn set_learning_rule(&mut self, _rule: LearningRule) {}
+
+    // Called by the iterative learning rules after every epoch, so that a long training can be followed and stopped
+    fn set_training_callback(&mut self, _callback: TrainingCallback) {}
+
+    // Stochastic networks never settle, a step without changes, or a state seen before, mean nothing for them
+    fn is_stochastic(&self) -> bool {
+        false
+    }
+
+    // Sequence networks go around their patterns on purpose, the cycle is the recall and not a reason to stop
+    fn plays_sequences(&self) -> bool {
+        false
+    }
 }
 
 // ---------------------------------Start of Network Type---------------------------------
-#[derive(EnumIter, Debug, PartialEq, Clone, Copy)]
+// The names describe both the learning rule and the layout, so the postfix repeats
+#[allow(clippy::enum_variant_names)]
+#[derive(EnumIter, Debug, PartialEq, Clone, Copy, serde::Deserialize, serde::Serialize)]
 pub enum NetworkType {
     StorkeySquareDiscrete,
     SquareDiscrete,
+    SequenceSquareDiscrete,
+    DelayedSequenceSquareDiscrete,
+    BamSquareDiscrete,
+    BoltzmannSquareDiscrete,
+    RbmSquareDiscrete,
 }
 
 impl Display for NetworkType {
@@ -39,23 +170,122 @@ impl Display for NetworkType {
         match self {
             NetworkType::StorkeySquareDiscrete => write!(f, "StorkeySquareDiscrete"),
             NetworkType::SquareDiscrete => write!(f, "HebbianSquareDiscrete"),
+            NetworkType::SequenceSquareDiscrete => write!(f, "SequenceSquareDiscrete"),
+            NetworkType::DelayedSequenceSquareDiscrete => {
+                write!(f, "DelayedSequenceSquareDiscrete")
+            }
+            NetworkType::BamSquareDiscrete => write!(f, "BamSquareDiscrete"),
+            NetworkType::BoltzmannSquareDiscrete => write!(f, "BoltzmannSquareDiscrete"),
+            NetworkType::RbmSquareDiscrete => write!(f, "RbmSquareDiscrete"),
             _ => panic!("Unknown network type"),
         }
     }
 }
 
+impl NetworkType {
+    // The networks whose state holds a second layer, after the one the patterns are drawn in
+    pub fn has_two_layers(&self) -> bool {
+        matches!(
+            self,
+            NetworkType::BamSquareDiscrete | NetworkType::RbmSquareDiscrete
+        )
+    }
+
+    // They move a pattern forward every sweep, so they start in synchronous mode whatever mode the others used
+    pub fn is_sequence(&self) -> bool {
+        matches!(
+            self,
+            NetworkType::SequenceSquareDiscrete | NetworkType::DelayedSequenceSquareDiscrete
+        )
+    }
+}
+
+// ---------------------------------Start of Step Speed---------------------------------
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StepSpeed {
+    // In synchronous mode every step updates all the nodes, so this is the same as the sweeps
+    StepsPerSecond(u64),
+    // Every node gets the chance to update this many times per second, whatever the size of the network
+    SweepsPerSecond(u64),
+    // No pause between the steps
+    Max,
+}
+
+impl StepSpeed {
+    // None if the network shouldn't wait at all
+    pub fn steps_per_second(&self, steps_per_sweep: usize) -> Option<f64> {
+        match self {
+            StepSpeed::StepsPerSecond(steps) => Some(*steps as f64),
+            StepSpeed::SweepsPerSecond(sweeps) => Some((*sweeps * steps_per_sweep as u64) as f64),
+            StepSpeed::Max => None,
+        }
+    }
+}
+
+// ---------------------------------Start of Update Mode---------------------------------
+#[derive(EnumIter, Debug, PartialEq, Clone, Copy)]
+pub enum UpdateMode {
+    // A single random node is updated every step, every node is updated once per sweep
+    Asynchronous,
+    // All the nodes are updated at the same time, so a step is also a whole sweep
+    Synchronous,
+}
+
+impl Display for UpdateMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateMode::Asynchronous => write!(f, "Asynchronous"),
+            UpdateMode::Synchronous => write!(f, "Synchronous"),
+        }
+    }
+}
+
 // ---------------------------------Comuincation Enums---------------------------------
 #[derive(PartialEq, Clone)]
 pub enum NetworkCommand {
     None,
     Learn(Vec<f64>),
+    // Forgets everything and learns the patterns, training only once on all of them
+    LearnAll(Vec<Vec<f64>>),
     Go,
     Stop,
     SetState(Vec<f64>),
-    SetSpeed(u64),
+    SetSpeed(StepSpeed),
+    // Single steps, taken while the network is stopped, the state is sent after each of them
+    StepNode,
+    StepSweep,
+    // Runs without pauses until the network settles, only the final state is sent
+    Converge,
     ResetWeights,
     //This command contais the type of net to setup, and its starting state,stored in a tuple
     ChangeNetType(NetworkType),
+    SetUpdateMode(UpdateMode),
+    // The type the weights are stored in, only the hebbian network uses it
+    SetPrecision(scalar::Precision),
+    // Only the hebbian network uses it, the patterns it already knows are learned again with the new rule
+    SetLearningRule(learning_rules::LearningRule),
+    // The sizes of the layers of the network, networks with a single layer ignore it
+    SetLayerSizes(Vec<usize>),
+    // Replaces the network with one that solves the given problem
+    Solve(optimization::SolverConfig),
+    Damage(damage::Damage),
+    // Undoes all the damage done since the last time the network learned something
+    RestoreWeights,
+    RunDamageExperiment(experiments::DamageExperiment),
+    // Removes the spurious attractors by dreaming, the state is left as it is
+    Unlearn(unlearning::Unlearning),
+    RunDiagnostics,
+    // Finds every fixed point by going through all the states, only for small networks
+    EnumerateStates,
+    // The energy on a plane of states, it only reads the weights so it can be asked while the network steps
+    ComputeLandscape(landscape::Plane),
+    // The steps taken so far and the energy of the state, it only reads the network too
+    GetStatus,
+    // The fields the nodes would have in the given state, the gui shows them when a node is hovered
+    ComputeLocalFields(Vec<f64>),
+    // Both change the weights, and are followed by a new diagnostics report
+    Symmetrize,
+    ClearDiagonal,
 }
 
 impl std::fmt::Debug for NetworkCommand {
@@ -63,14 +293,38 @@ impl std::fmt::Debug for NetworkCommand {
         match self {
             NetworkCommand::None => write!(f, "None"),
             NetworkCommand::Learn(state) => write!(f, "Learn(\n{})", state_vec_to_string(&state)),
+            NetworkCommand::LearnAll(patterns) => {
+                write!(f, "LearnAll({} patterns)", patterns.len())
+            }
             NetworkCommand::Go => write!(f, "Go"),
             NetworkCommand::Stop => write!(f, "Stop"),
             NetworkCommand::SetState(state) => {
                 write!(f, "SetState(\n{})", state_vec_to_string(&state))
             }
-            NetworkCommand::SetSpeed(speed) => write!(f, "SetSpeed({})", speed),
+            NetworkCommand::SetSpeed(speed) => write!(f, "SetSpeed({:?})", speed),
+            NetworkCommand::StepNode => write!(f, "StepNode"),
+            NetworkCommand::StepSweep => write!(f, "StepSweep"),
+            NetworkCommand::Converge => write!(f, "Converge"),
             NetworkCommand::ResetWeights => write!(f, "ResetWeights"),
             NetworkCommand::ChangeNetType(net_type) => write!(f, "ChangeNetType({:?})", net_type),
+            NetworkCommand::SetUpdateMode(mode) => write!(f, "SetUpdateMode({:?})", mode),
+            NetworkCommand::SetPrecision(precision) => write!(f, "SetPrecision({:?})", precision),
+            NetworkCommand::SetLearningRule(rule) => write!(f, "SetLearningRule({:?})", rule),
+            NetworkCommand::SetLayerSizes(sizes) => write!(f, "SetLayerSizes({:?})", sizes),
+            NetworkCommand::Solve(config) => write!(f, "Solve({:?})", config.problem.kind()),
+            NetworkCommand::Damage(damage) => write!(f, "Damage({:?})", damage),
+            NetworkCommand::RestoreWeights => write!(f, "RestoreWeights"),
+            NetworkCommand::RunDamageExperiment(experiment) => {
+                write!(f, "RunDamageExperiment({:?})", experiment)
+            }
+            NetworkCommand::Unlearn(unlearning) => write!(f, "Unlearn({:?})", unlearning),
+            NetworkCommand::RunDiagnostics => write!(f, "RunDiagnostics"),
+            NetworkCommand::EnumerateStates => write!(f, "EnumerateStates"),
+            NetworkCommand::ComputeLandscape(_) => write!(f, "ComputeLandscape"),
+            NetworkCommand::GetStatus => write!(f, "GetStatus"),
+            NetworkCommand::ComputeLocalFields(_) => write!(f, "ComputeLocalFields"),
+            NetworkCommand::Symmetrize => write!(f, "Symmetrize"),
+            NetworkCommand::ClearDiagonal => write!(f, "ClearDiagonal"),
         }
     }
 }
@@ -78,10 +332,51 @@ impl std::fmt::Debug for NetworkCommand {
 #[derive(Debug)]
 pub enum NetworkResponse {
     NewState(Vec<f64>),
+    // The nodes that changed since the last state that was sent, with their new values
+    StateDelta(Vec<(usize, f64)>),
+    // Sent right before Stopped, when the network is stuck in a cycle longer than a single state
+    CycleDetected(cycle_detection::Cycle),
+    // How many damage levels have been tested, out of how many
+    ExperimentProgress(usize, usize),
+    ExperimentResult(Vec<experiments::ExperimentPoint>),
+    Diagnostics(diagnostics::DiagnosticsReport),
+    // Sent after every learn, how many nodes of each stored pattern would flip, in the order they were learned
+    PatternStability(Vec<usize>),
+    TrainingProgress(learning_rules::TrainingProgress),
+    // How many dreams are over, out of how many
+    UnlearningProgress(usize, usize),
+    UnlearningResult(unlearning::UnlearningReport),
+    EnumerationProgress(usize, usize),
+    // None if the network is too big to go through all its states
+    EnumerationResult(Option<enumeration::Enumeration>),
+    // Sent every time the weights change, None if the states can be enumerated, otherwise why not
+    EnumerationAvailability(Option<&'static str>),
+    Landscape(landscape::Landscape),
+    Status(NetworkStatus),
+    // Sent after every step while the network is slow enough for the user to follow it
+    NodeUpdated(NodeUpdate),
+    // The state the fields were asked for, and the fields
+    LocalFields(Vec<f64>, Vec<f64>),
+    Error(network_error::NetworkError),
     Stopped,
     None,
 }
 
+// Where the network is in its descent, the comparison shows it under each of the networks
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct NetworkStatus {
+    pub steps: usize,
+    pub energy: f64,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct NodeUpdate {
+    pub index: usize,
+    // The field the node was updated with, its new value is its sign (or a sample, for the stochastic networks)
+    pub field: f64,
+    pub flipped: bool,
+}
+
 impl NetworkResponse {
     pub fn is_some(&self) -> bool {
         !matches!(self, NetworkResponse::None)
@@ -131,6 +426,33 @@ pub fn state_vec_to_string(state: &[f64]) -> String {
     result
 }
 
+// Every network needs at least MIN_STATE_SIZE nodes
+fn check_state_size(len: usize) -> Result<(), NetworkError> {
+    if len < MIN_STATE_SIZE {
+        return Err(NetworkError::StateTooShort {
+            len,
+            min: MIN_STATE_SIZE,
+        });
+    }
+    Ok(())
+}
+
+// The state a new network starts from, all the nodes are off if it isn't given
+fn start_state<T: Scalar>(
+    size: usize,
+    start_state: Option<&Vec<T>>,
+) -> Result<Vec<T>, NetworkError> {
+    check_state_size(size)?;
+    match start_state {
+        Some(state) if state.len() != size => Err(NetworkError::StateSizeMismatch {
+            expected: size,
+            found: state.len(),
+        }),
+        Some(state) => Ok(state.clone()),
+        None => Ok(vec![T::MINUS_ONE; size]),
+    }
+}
+
 fn reset_nodes_to_update(container: &mut Vec<usize>, lenght: usize) {
     // If the containere isn't already empty, we empty it
     while !container.is_empty() {
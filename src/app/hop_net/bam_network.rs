@@ -0,0 +1,204 @@
+use crate::app::hop_net;
+use crate::app::hop_net::batch_recall::RecallOptions;
+use crate::app::hop_net::batch_recall::RecallResult;
+use crate::app::hop_net::local_fields;
+use crate::app::hop_net::local_fields::LocalFields;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error::NetworkError;
+use std::borrow::Cow;
+
+// Bidirectional Associative Memory, it links the patterns of an input layer with the ones of an output layer.
+// The state holds the input layer first, and then the output layer. The weights are stored as a single
+// square matrix with empty diagonal blocks, so a node only listens to the nodes of the other layer.
+pub struct BamNetwork {
+    pub state: Vec<f64>,
+    input_size: usize,
+    output_size: usize,
+    weights: Matrix<f64>,
+    fields: LocalFields,
+    steps: usize,
+    nodes_yet_to_update: Vec<usize>,
+    last_update: Option<(usize, f64)>,
+    update_mode: hop_net::UpdateMode,
+    // In synchronous mode the layers take turns, the input is read first so the output layer goes first
+    output_next: bool,
+}
+
+impl hop_net::Net<f64> for BamNetwork {
+    fn get_state_ref(&self) -> &[f64] {
+        &self.state
+    }
+
+    // The state to learn is a pair, the input pattern followed by the output one
+    fn learn(&mut self, state: &[f64]) {
+        for i in 0..self.input_size {
+            for j in self.input_size..self.state.len() {
+                let product = state[i] * state[j];
+                self.weights[i][j] += product;
+                self.weights[j][i] += product;
+            }
+        }
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn step(&mut self) -> bool {
+        self.last_update = None;
+        if self.update_mode == hop_net::UpdateMode::Synchronous {
+            // The standard BAM recall, X -> Y -> X. Updating both layers at once can leave them swapping
+            // their patterns forever. The nodes of a layer don't listen to each other, so updating them
+            // one after the other is the same as updating them all at once
+            let layer = if self.output_next {
+                self.input_size..self.state.len()
+            } else {
+                0..self.input_size
+            };
+            self.output_next = !self.output_next;
+            self.steps += 1;
+            let mut changed = false;
+            for i in layer {
+                changed |=
+                    local_fields::update_node(&self.weights, &mut self.fields, &mut self.state, i);
+            }
+            return changed;
+        }
+
+        // The nodes of both layers are updated in the same random order
+        if self.nodes_yet_to_update.is_empty() {
+            hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+        }
+        let i = self.nodes_yet_to_update.pop().unwrap();
+        self.steps += 1;
+        self.last_update = Some((i, self.fields.get(i)));
+        // Same rule as the classic network, but half of the weights in each row are always 0
+        local_fields::update_node(&self.weights, &mut self.fields, &mut self.state, i)
+    }
+
+    fn get_steps(&self) -> usize {
+        self.steps
+    }
+
+    fn get_last_update(&self) -> Option<(usize, f64)> {
+        self.last_update
+    }
+
+    fn set_state(&mut self, state: &[f64]) -> Result<(), NetworkError> {
+        hop_net::check_state_size(state.len())?;
+
+        // If we are not told otherwise, the output layer keeps its size
+        if self.state.len() != state.len() {
+            let output_size = if state.len() > self.output_size {
+                self.output_size
+            } else {
+                0
+            };
+            self.resize(state.len() - output_size, output_size);
+        }
+        self.state = state.to_vec();
+        self.fields.recompute(&self.weights, &self.state);
+
+        // The starting state has just been set, so we are 0 steps away from it
+        self.steps = 0;
+
+        // We make sure that all nodes are marked as "to update"
+        hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+        self.output_next = true;
+        Ok(())
+    }
+
+    fn reset_weights(&mut self) {
+        self.weights = Matrix::square(self.state.len());
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn get_weights(&self) -> Cow<'_, Matrix<f64>> {
+        Cow::Borrowed(&self.weights)
+    }
+
+    fn get_recall_weights(&self) -> Option<&Matrix<f64>> {
+        Some(&self.weights)
+    }
+
+    // The batch recall updates every node together, in synchronous mode the layers have to take turns
+    fn batch_recall(
+        &self,
+        probes: &[Vec<f64>],
+        patterns: &[Vec<f64>],
+        options: RecallOptions,
+    ) -> Option<Result<Vec<RecallResult>, NetworkError>> {
+        if options.update_mode == hop_net::UpdateMode::Synchronous {
+            return None;
+        }
+        self.get_recall_weights()
+            .map(|weights| hop_net::batch_recall::batch_recall(weights, probes, patterns, options))
+    }
+
+    fn modify_weights(&mut self, modify: &mut dyn FnMut(&mut Matrix<f64>)) {
+        modify(&mut self.weights);
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn set_update_mode(&mut self, mode: hop_net::UpdateMode) {
+        self.update_mode = mode;
+    }
+
+    fn get_update_mode(&self) -> hop_net::UpdateMode {
+        self.update_mode
+    }
+
+    fn get_layer_sizes(&self) -> Vec<usize> {
+        vec![self.input_size, self.output_size]
+    }
+
+    fn set_layer_sizes(&mut self, sizes: &[usize]) -> Result<(), NetworkError> {
+        if sizes.len() != 2 {
+            return Err(NetworkError::LayerCountMismatch {
+                expected: 2,
+                found: sizes.len(),
+            });
+        }
+        if sizes[0] != self.input_size || sizes[1] != self.output_size {
+            self.resize(sizes[0], sizes[1]);
+        }
+        Ok(())
+    }
+}
+
+impl BamNetwork {
+    pub fn new(
+        input_size: usize,
+        output_size: usize,
+        start_state: Option<&Vec<f64>>,
+    ) -> Result<BamNetwork, NetworkError> {
+        let size = input_size + output_size;
+        let state = hop_net::start_state(size, start_state)?;
+
+        let mut nodes_to_update = Vec::with_capacity(size);
+        hop_net::reset_nodes_to_update(&mut nodes_to_update, size);
+
+        Ok(BamNetwork {
+            state,
+            input_size,
+            output_size,
+            weights: Matrix::square(size),
+            fields: LocalFields::new(size),
+            steps: 0,
+            nodes_yet_to_update: nodes_to_update,
+            last_update: None,
+            update_mode: hop_net::UpdateMode::Asynchronous,
+            output_next: true,
+        })
+    }
+
+    // Changing the size of a layer makes the old pairs meaningless, so the network forgets them
+    fn resize(&mut self, input_size: usize, output_size: usize) {
+        let size = input_size + output_size;
+        self.input_size = input_size;
+        self.output_size = output_size;
+        self.state = vec![-1.0; size];
+        self.weights = Matrix::square(size);
+        self.fields = LocalFields::new(size);
+        self.steps = 0;
+        hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, size);
+        self.output_next = true;
+    }
+}
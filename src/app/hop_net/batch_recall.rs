@@ -0,0 +1,216 @@
+use crate::app::hop_net;
+use crate::app::hop_net::experiments;
+use crate::app::hop_net::local_fields;
+use crate::app::hop_net::local_fields::LocalFields;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error;
+use crate::app::hop_net::network_error::NetworkError;
+use crate::app::hop_net::scalar::Scalar;
+use crate::app::hop_net::UpdateMode;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RecallOptions {
+    pub update_mode: UpdateMode,
+    // After this many sweeps a recall is over, even if the state is still changing
+    pub max_sweeps: usize,
+}
+
+impl Default for RecallOptions {
+    fn default() -> Self {
+        Self {
+            update_mode: UpdateMode::Asynchronous,
+            max_sweeps: experiments::MAX_RECALL_SWEEPS,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecallResult {
+    pub state: Vec<f64>,
+    // The number of node updates, for the synchronous mode a step updates every node
+    pub steps: usize,
+    // False if the recall was stopped by max_sweeps
+    pub converged: bool,
+    // The index of the first pattern the final state overlaps with at least RECALL_THRESHOLD
+    pub matched_pattern: Option<usize>,
+}
+
+// Runs every probe on the same weights, with the usual sign update rule.
+// The weights are only read, so the probes are split between as many threads as the machine has,
+// each thread keeps its own state and fields. The results are in the same order as the probes.
+// On the web threads aren't available, so the probes are run one after the other.
+// The probes and the results are f64, the recall itself runs in the type of the weights
+pub fn batch_recall<T: Scalar>(
+    weights: &Matrix<T>,
+    probes: &[Vec<f64>],
+    patterns: &[Vec<f64>],
+    options: RecallOptions,
+) -> Result<Vec<RecallResult>, NetworkError> {
+    // A probe of the wrong size would be recalled on part of the weights, with a result that looks fine
+    if let Some(probe) = probes.iter().find(|probe| probe.len() != weights.rows()) {
+        return Err(NetworkError::StateSizeMismatch {
+            expected: weights.rows(),
+            found: probe.len(),
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        if threads > 1 && probes.len() > 1 {
+            let chunk_size = (probes.len() + threads - 1) / threads;
+            return std::thread::scope(|scope| {
+                let handles: Vec<_> = probes
+                    .chunks(chunk_size)
+                    .map(|chunk| scope.spawn(move || recall_all(weights, chunk, patterns, options)))
+                    .collect();
+                // Every thread is joined before looking at the results, the scope would panic for the ones left behind
+                let joined: Vec<_> = handles.into_iter().map(|handle| handle.join()).collect();
+                let mut results = Vec::with_capacity(probes.len());
+                for chunk in joined {
+                    let chunk = chunk.map_err(|payload| {
+                        NetworkError::RecallCrashed(network_error::panic_message(payload.as_ref()))
+                    })?;
+                    results.extend(chunk);
+                }
+                Ok(results)
+            });
+        }
+    }
+
+    Ok(recall_all(weights, probes, patterns, options))
+}
+
+fn recall_all<T: Scalar>(
+    weights: &Matrix<T>,
+    probes: &[Vec<f64>],
+    patterns: &[Vec<f64>],
+    options: RecallOptions,
+) -> Vec<RecallResult> {
+    let mut fields = LocalFields::new(weights.rows());
+    let mut nodes_yet_to_update = Vec::with_capacity(weights.rows());
+    probes
+        .iter()
+        .map(|probe| {
+            let mut result = recall_from(
+                weights,
+                probe,
+                options,
+                &mut fields,
+                &mut nodes_yet_to_update,
+            );
+            result.matched_pattern = patterns.iter().position(|pattern| {
+                experiments::overlap(pattern, &result.state) >= experiments::RECALL_THRESHOLD
+            });
+            result
+        })
+        .collect()
+}
+
+// The fields and the update order are passed in, so that they are allocated once per thread instead of once per probe
+fn recall_from<T: Scalar>(
+    weights: &Matrix<T>,
+    probe: &[f64],
+    options: RecallOptions,
+    fields: &mut LocalFields<T>,
+    nodes_yet_to_update: &mut Vec<usize>,
+) -> RecallResult {
+    let mut state: Vec<T> = probe.iter().map(|x| T::from_f64(*x)).collect();
+    fields.recompute(weights, &state);
+    let mut steps = 0;
+    let mut converged = false;
+
+    for _ in 0..options.max_sweeps {
+        // A sweep without any change means that the state is stable
+        let changed = match options.update_mode {
+            UpdateMode::Synchronous => {
+                steps += 1;
+                local_fields::synchronous_update(weights, fields, &mut state)
+            }
+            UpdateMode::Asynchronous => {
+                hop_net::reset_nodes_to_update(nodes_yet_to_update, state.len());
+                let mut changed = false;
+                while let Some(i) = nodes_yet_to_update.pop() {
+                    steps += 1;
+                    changed |= local_fields::update_node(weights, fields, &mut state, i);
+                }
+                changed
+            }
+        };
+        if !changed {
+            converged = true;
+            break;
+        }
+    }
+
+    RecallResult {
+        state: state.into_iter().map(T::to_f64).collect(),
+        steps,
+        converged,
+        matched_pattern: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::hop_net::classic_network::ClassicNetworkDiscrete;
+    use crate::app::hop_net::Net;
+    use rand::Rng;
+
+    fn random_patterns(count: usize, size: usize) -> Vec<Vec<f64>> {
+        let mut rng = rand::thread_rng();
+        (0..count)
+            .map(|_| {
+                (0..size)
+                    .map(|_| if rng.gen_bool(0.5) { 1.0 } else { -1.0 })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // The synchronous update is deterministic, so the batch has to end where stepping the network ends.
+    // There are enough probes to split them between the threads
+    #[test]
+    fn matches_sequential_recall() {
+        let size = 25;
+        let patterns = random_patterns(3, size);
+        let mut net = ClassicNetworkDiscrete::<f64>::new(size, None).unwrap();
+        net.learn_all(&patterns);
+        net.set_update_mode(UpdateMode::Synchronous);
+        let mut probes: Vec<Vec<f64>> = (0..20)
+            .map(|i| experiments::add_noise(&patterns[i % patterns.len()], 0.2))
+            .collect();
+        probes.extend(random_patterns(10, size));
+
+        let options = RecallOptions {
+            update_mode: UpdateMode::Synchronous,
+            max_sweeps: experiments::MAX_RECALL_SWEEPS,
+        };
+        let weights = net.get_recall_weights().unwrap().clone();
+        let results = batch_recall(&weights, &probes, &patterns, options).unwrap();
+        assert_eq!(results.len(), probes.len());
+        for (probe, result) in probes.iter().zip(results.iter()) {
+            let expected = experiments::recall(&mut net, probe, options.max_sweeps).unwrap();
+            assert_eq!(result.state, expected);
+            let expected_match = patterns.iter().position(|pattern| {
+                experiments::overlap(pattern, &expected) >= experiments::RECALL_THRESHOLD
+            });
+            assert_eq!(result.matched_pattern, expected_match);
+        }
+    }
+
+    #[test]
+    fn refuses_probes_of_the_wrong_size() {
+        let weights: Matrix<f64> = Matrix::square(9);
+        let probes = vec![vec![1.0; 9], vec![1.0; 8]];
+        let result = batch_recall(&weights, &probes, &[], RecallOptions::default());
+        assert_eq!(
+            result,
+            Err(NetworkError::StateSizeMismatch {
+                expected: 9,
+                found: 8
+            })
+        );
+    }
+}
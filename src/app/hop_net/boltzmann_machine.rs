@@ -0,0 +1,482 @@
+use crate::app::hop_net;
+use crate::app::hop_net::learning_rules::TrainingCallback;
+use crate::app::hop_net::learning_rules::TrainingProgress;
+use crate::app::hop_net::local_fields::LocalFields;
+use crate::app::hop_net::matrix;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error::NetworkError;
+use rand::Rng;
+use std::borrow::Cow;
+
+// Every learn trains on all the stored patterns for at most this many epochs
+pub const TRAINING_EPOCHS: usize = 500;
+// The training is over when the error hasn't gone down by at least this fraction for PATIENCE epochs in a row,
+// the error of the fully visible machine comes from samples, so it never gets to 0
+const MIN_IMPROVEMENT: f64 = 0.01;
+const PATIENCE: usize = 50;
+const LEARNING_RATE: f64 = 0.1;
+// The fully visible machine estimates the statistics of the model from this many Markov chains,
+// they keep going from an epoch to the next one (persistent contrastive divergence)
+const PERSISTENT_CHAINS: usize = 20;
+// The hidden nodes of an RBM start with random weights in ±this, if they were all 0 every hidden node would
+// have an expected value of 0, the gradient would be 0, and the network would never learn
+const INITIAL_WEIGHT: f64 = 0.1;
+
+// A stochastic Hopfield network that learns a probability distribution over its states:
+// a node is set to +1 with probability 1 / (1 + exp(-2h)), h being its field plus its bias.
+// The fully visible machine connects every node with every other one, the restricted one (RBM) has
+// a layer of hidden nodes, and the connections only go from one layer to the other.
+// Like in the BAM, the state holds the visible layer first and then the hidden one, and the weights
+// are a single square matrix with empty diagonal blocks.
+pub struct BoltzmannMachine {
+    pub state: Vec<f64>,
+    pub rng: rand::rngs::ThreadRng,
+    visible_size: usize,
+    hidden_size: usize,
+    restricted: bool,
+    weights: Matrix<f64>,
+    biases: Vec<f64>,
+    // Only the part of the field that comes from the other nodes, the biases are added on top
+    fields: LocalFields,
+    steps: usize,
+    nodes_yet_to_update: Vec<usize>,
+    last_update: Option<(usize, f64)>,
+    update_mode: hop_net::UpdateMode,
+    // Every learn trains on all the patterns again
+    patterns: Vec<Vec<f64>>,
+    training_callback: Option<TrainingCallback>,
+}
+
+impl hop_net::Net<f64> for BoltzmannMachine {
+    fn get_state_ref(&self) -> &[f64] {
+        &self.state
+    }
+
+    // Only the visible part of the state is learned, the hidden nodes are there to explain it
+    fn learn(&mut self, state: &[f64]) {
+        self.patterns.push(state[..self.visible_size].to_vec());
+        self.train();
+    }
+
+    fn learn_all(&mut self, patterns: &[Vec<f64>]) {
+        self.reset_weights();
+        self.patterns = patterns
+            .iter()
+            .map(|pattern| pattern[..self.visible_size].to_vec())
+            .collect();
+        if !self.patterns.is_empty() {
+            self.train();
+        }
+    }
+
+    fn step(&mut self) -> bool {
+        self.last_update = None;
+        self.steps += 1;
+        match self.update_mode {
+            // The hidden nodes only listen to the visible ones and the other way around, so updating a
+            // whole layer after the other is the same as updating all its nodes at the same time
+            hop_net::UpdateMode::Synchronous if self.restricted => {
+                let mut changed = false;
+                for i in (self.visible_size..self.state.len()).chain(0..self.visible_size) {
+                    let h = self.field(i);
+                    changed |= self.sample_node(i, h);
+                }
+                changed
+            }
+            hop_net::UpdateMode::Synchronous => {
+                let fields: Vec<f64> = (0..self.state.len()).map(|i| self.field(i)).collect();
+                let mut changed = false;
+                for (i, h) in fields.into_iter().enumerate() {
+                    changed |= self.sample_node(i, h);
+                }
+                changed
+            }
+            hop_net::UpdateMode::Asynchronous => {
+                if self.nodes_yet_to_update.is_empty() {
+                    hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+                }
+                let i = self.nodes_yet_to_update.pop().unwrap();
+                let h = self.field(i);
+                self.last_update = Some((i, h));
+                self.sample_node(i, h)
+            }
+        }
+    }
+
+    fn get_steps(&self) -> usize {
+        self.steps
+    }
+
+    fn get_last_update(&self) -> Option<(usize, f64)> {
+        self.last_update
+    }
+
+    fn set_state(&mut self, state: &[f64]) -> Result<(), NetworkError> {
+        hop_net::check_state_size(state.len())?;
+
+        // If we are not told otherwise, the hidden layer keeps its size
+        if self.state.len() != state.len() {
+            let hidden_size = if state.len() > self.hidden_size {
+                self.hidden_size
+            } else {
+                0
+            };
+            self.resize(state.len() - hidden_size, hidden_size);
+        }
+        self.state = state.to_vec();
+        self.fields.recompute(&self.weights, &self.state);
+        self.steps = 0;
+        hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+        Ok(())
+    }
+
+    fn reset_weights(&mut self) {
+        self.randomize_weights();
+        self.biases = vec![0.0; self.state.len()];
+        self.patterns.clear();
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn get_weights(&self) -> Cow<'_, Matrix<f64>> {
+        Cow::Borrowed(&self.weights)
+    }
+
+    fn modify_weights(&mut self, modify: &mut dyn FnMut(&mut Matrix<f64>)) {
+        modify(&mut self.weights);
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn get_local_fields(&self, state: &[f64]) -> Vec<f64> {
+        let mut fields = vec![0.0; state.len()];
+        self.weights.mul_vec(state, &mut fields);
+        for (h, bias) in fields.iter_mut().zip(self.biases.iter()) {
+            *h += bias;
+        }
+        fields
+    }
+
+    fn set_update_mode(&mut self, mode: hop_net::UpdateMode) {
+        self.update_mode = mode;
+    }
+
+    fn get_update_mode(&self) -> hop_net::UpdateMode {
+        self.update_mode
+    }
+
+    fn get_layer_sizes(&self) -> Vec<usize> {
+        if self.restricted {
+            vec![self.visible_size, self.hidden_size]
+        } else {
+            vec![self.visible_size]
+        }
+    }
+
+    fn set_layer_sizes(&mut self, sizes: &[usize]) -> Result<(), NetworkError> {
+        if !self.restricted {
+            return Ok(());
+        }
+        if sizes.len() != 2 {
+            return Err(NetworkError::LayerCountMismatch {
+                expected: 2,
+                found: sizes.len(),
+            });
+        }
+        if sizes[0] != self.visible_size || sizes[1] != self.hidden_size {
+            self.resize(sizes[0], sizes[1]);
+        }
+        Ok(())
+    }
+
+    fn set_training_callback(&mut self, callback: TrainingCallback) {
+        self.training_callback = Some(callback);
+    }
+
+    fn is_stochastic(&self) -> bool {
+        true
+    }
+}
+
+impl BoltzmannMachine {
+    pub fn fully_visible(
+        size: usize,
+        start_state: Option<&Vec<f64>>,
+    ) -> Result<BoltzmannMachine, NetworkError> {
+        BoltzmannMachine::new(size, 0, false, start_state)
+    }
+
+    pub fn restricted(
+        visible_size: usize,
+        hidden_size: usize,
+        start_state: Option<&Vec<f64>>,
+    ) -> Result<BoltzmannMachine, NetworkError> {
+        BoltzmannMachine::new(visible_size, hidden_size, true, start_state)
+    }
+
+    fn new(
+        visible_size: usize,
+        hidden_size: usize,
+        restricted: bool,
+        start_state: Option<&Vec<f64>>,
+    ) -> Result<BoltzmannMachine, NetworkError> {
+        let size = visible_size + hidden_size;
+        let state = hop_net::start_state(size, start_state)?;
+
+        let mut nodes_to_update = Vec::with_capacity(size);
+        hop_net::reset_nodes_to_update(&mut nodes_to_update, size);
+
+        let mut machine = BoltzmannMachine {
+            state,
+            rng: rand::thread_rng(),
+            visible_size,
+            hidden_size,
+            restricted,
+            weights: Matrix::square(size),
+            biases: vec![0.0; size],
+            fields: LocalFields::new(size),
+            steps: 0,
+            nodes_yet_to_update: nodes_to_update,
+            last_update: None,
+            update_mode: hop_net::UpdateMode::Asynchronous,
+            patterns: Vec::new(),
+            training_callback: None,
+        };
+        machine.randomize_weights();
+        machine.fields.recompute(&machine.weights, &machine.state);
+        Ok(machine)
+    }
+
+    // Changing the size of a layer makes the old patterns meaningless, so the network forgets them
+    fn resize(&mut self, visible_size: usize, hidden_size: usize) {
+        let size = visible_size + hidden_size;
+        self.visible_size = visible_size;
+        self.hidden_size = hidden_size;
+        self.state = vec![-1.0; size];
+        self.randomize_weights();
+        self.biases = vec![0.0; size];
+        self.fields = LocalFields::new(size);
+        self.fields.recompute(&self.weights, &self.state);
+        self.patterns.clear();
+        self.steps = 0;
+        hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, size);
+    }
+
+    // Trains on all the patterns until the error stops going down
+    fn train(&mut self) {
+        let mut callback = self.training_callback.take();
+        let mut chains = self.random_states(PERSISTENT_CHAINS);
+        // The patterns don't change during the training, only the machine does
+        let data_statistics = if self.restricted {
+            None
+        } else {
+            Some(self.data_statistics())
+        };
+
+        let mut best_error = f64::INFINITY;
+        let mut epochs_without_improvement = 0;
+        for epoch in 1..=TRAINING_EPOCHS {
+            let error = match &data_statistics {
+                Some((means, correlations)) => {
+                    self.persistent_contrastive_divergence(&mut chains, means, correlations)
+                }
+                None => self.contrastive_divergence(),
+            };
+            if error < best_error * (1.0 - MIN_IMPROVEMENT) {
+                best_error = error;
+                epochs_without_improvement = 0;
+            } else {
+                epochs_without_improvement += 1;
+            }
+
+            let done = best_error == 0.0
+                || epochs_without_improvement >= PATIENCE
+                || epoch == TRAINING_EPOCHS;
+            let keep_going = match callback.as_mut() {
+                Some(callback) => callback(TrainingProgress {
+                    epoch,
+                    max_epochs: TRAINING_EPOCHS,
+                    error,
+                    done,
+                }),
+                None => true,
+            };
+            if done || !keep_going {
+                break;
+            }
+        }
+        self.training_callback = callback;
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    // The fully visible machine starts from 0, the RBM from small random weights between the layers
+    fn randomize_weights(&mut self) {
+        let size = self.visible_size + self.hidden_size;
+        self.weights = Matrix::square(size);
+        if !self.restricted {
+            return;
+        }
+        for i in 0..self.visible_size {
+            for j in self.visible_size..size {
+                let w = self.rng.gen_range(-INITIAL_WEIGHT..INITIAL_WEIGHT);
+                self.weights[i][j] = w;
+                self.weights[j][i] = w;
+            }
+        }
+    }
+
+    fn field(&self, i: usize) -> f64 {
+        self.fields.get(i) + self.biases[i]
+    }
+
+    fn sample_node(&mut self, i: usize, h: f64) -> bool {
+        let new_val = sample(&mut self.rng, h);
+        if new_val != self.state[i] {
+            self.fields
+                .node_changed(&self.weights, i, self.state[i], new_val);
+            self.state[i] = new_val;
+            return true;
+        }
+        false
+    }
+
+    fn random_states(&mut self, count: usize) -> Vec<Vec<f64>> {
+        let size = self.state.len();
+        (0..count)
+            .map(|_| {
+                (0..size)
+                    .map(|_| if self.rng.gen_bool(0.5) { 1.0 } else { -1.0 })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // A sweep of the chain in the usual random order, the fields are computed from scratch because
+    // there are many chains and a single set of fields. The order is the training's own,
+    // so that the sweep the visible state is in the middle of goes on after the learn
+    fn gibbs_sweep(&mut self, chain: &mut [f64], order: &mut Vec<usize>) {
+        hop_net::reset_nodes_to_update(order, chain.len());
+        while let Some(i) = order.pop() {
+            let h = matrix::dot(&self.weights[i], chain) + self.biases[i];
+            chain[i] = sample(&mut self.rng, h);
+        }
+    }
+
+    // The mean of every node and the correlation of every pair of nodes in the patterns
+    fn data_statistics(&self) -> (Vec<f64>, Matrix<f64>) {
+        let size = self.state.len();
+        let count = self.patterns.len() as f64;
+        let mut means = vec![0.0; size];
+        let mut correlations = Matrix::square(size);
+        for pattern in self.patterns.iter() {
+            matrix::add_scaled(&mut means, 1.0 / count, pattern);
+            for (i, row) in correlations.iter_rows_mut().enumerate() {
+                matrix::add_scaled(row, pattern[i] / count, pattern);
+            }
+        }
+        (means, correlations)
+    }
+
+    // The gradient of the likelihood is the difference between the correlations of the nodes in the patterns
+    // and in the states the machine generates on its own. The first are computed once per learn, the second are
+    // estimated from the chains, that are moved one sweep further at every epoch.
+    // Returns the mean squared difference of the correlations
+    fn persistent_contrastive_divergence(
+        &mut self,
+        chains: &mut [Vec<f64>],
+        data_means: &[f64],
+        data_correlations: &Matrix<f64>,
+    ) -> f64 {
+        let mut order = Vec::with_capacity(self.state.len());
+        for chain in chains.iter_mut() {
+            self.gibbs_sweep(chain, &mut order);
+        }
+
+        let size = self.state.len();
+        let chain_count = chains.len() as f64;
+        let mut squared_error = 0.0;
+        for i in 0..size {
+            let model_mean = chains.iter().map(|c| c[i]).sum::<f64>() / chain_count;
+            self.biases[i] += LEARNING_RATE * (data_means[i] - model_mean);
+
+            for j in (i + 1)..size {
+                let model = chains.iter().map(|c| c[i] * c[j]).sum::<f64>() / chain_count;
+                let difference = data_correlations[i][j] - model;
+                squared_error += difference * difference;
+                self.weights[i][j] += LEARNING_RATE * difference;
+                self.weights[j][i] = self.weights[i][j];
+            }
+        }
+        squared_error / (size * size.saturating_sub(1) / 2).max(1) as f64
+    }
+
+    // CD-1: the hidden layer is driven by a pattern, then the pattern is reconstructed from the hidden layer.
+    // The hidden nodes use their expected values instead of samples, which are tanh(h) for ±1 nodes.
+    // Returns the mean squared difference between the patterns and their reconstructions
+    fn contrastive_divergence(&mut self) -> f64 {
+        let visible = self.visible_size;
+        let size = self.state.len();
+        let mut weight_gradient = Matrix::<f64>::zeros(visible, self.hidden_size);
+        let mut bias_gradient = vec![0.0; size];
+        let mut squared_error = 0.0;
+        let patterns = std::mem::take(&mut self.patterns);
+
+        for pattern in patterns.iter() {
+            let mut state = pattern.clone();
+            state.resize(size, 0.0);
+
+            // The weights inside a layer are 0, so the whole rows can be used
+            let data_fields: Vec<f64> = (visible..size)
+                .map(|j| matrix::dot(&self.weights[j], &state) + self.biases[j])
+                .collect();
+            let data_hidden: Vec<f64> = data_fields.iter().map(|h| h.tanh()).collect();
+            // The reconstruction starts from a sample of the hidden layer
+            for (j, h) in (visible..size).zip(data_fields.iter()) {
+                state[j] = sample(&mut self.rng, *h);
+            }
+            for i in 0..visible {
+                let h = matrix::dot(&self.weights[i], &state) + self.biases[i];
+                state[i] = sample(&mut self.rng, h);
+            }
+            let model_hidden: Vec<f64> = (visible..size)
+                .map(|j| (matrix::dot(&self.weights[j], &state) + self.biases[j]).tanh())
+                .collect();
+
+            for i in 0..visible {
+                let difference = pattern[i] - state[i];
+                squared_error += difference * difference / 4.0;
+                bias_gradient[i] += difference;
+                for (k, (data, model)) in data_hidden.iter().zip(model_hidden.iter()).enumerate() {
+                    weight_gradient[i][k] += pattern[i] * data - state[i] * model;
+                }
+            }
+            for (k, (data, model)) in data_hidden.iter().zip(model_hidden.iter()).enumerate() {
+                bias_gradient[visible + k] += data - model;
+            }
+        }
+
+        let rate = LEARNING_RATE / patterns.len() as f64;
+        for i in 0..visible {
+            for k in 0..self.hidden_size {
+                let j = visible + k;
+                self.weights[i][j] += rate * weight_gradient[i][k];
+                self.weights[j][i] = self.weights[i][j];
+            }
+        }
+        for (bias, gradient) in self.biases.iter_mut().zip(bias_gradient.iter()) {
+            *bias += rate * gradient;
+        }
+
+        let error = squared_error / (patterns.len() * visible) as f64;
+        self.patterns = patterns;
+        error
+    }
+}
+
+// +1 with probability 1 / (1 + exp(-2h)), which is (1 + tanh(h)) / 2
+fn sample(rng: &mut impl Rng, h: f64) -> f64 {
+    let p_on = 1.0 / (1.0 + (-2.0 * h).exp());
+    if rng.gen_bool(p_on.clamp(0.0, 1.0)) {
+        1.0
+    } else {
+        -1.0
+    }
+}
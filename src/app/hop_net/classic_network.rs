@@ -1,26 +1,83 @@
 use crate::app::hop_net;
+use crate::app::hop_net::learning_rules;
+use crate::app::hop_net::learning_rules::LearningRule;
+use crate::app::hop_net::learning_rules::TrainingCallback;
+use crate::app::hop_net::local_fields;
+use crate::app::hop_net::local_fields::LocalFields;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error::NetworkError;
+use crate::app::hop_net::scalar::Scalar;
 use rand::Rng;
-pub struct ClassicNetworkDiscrete {
-    pub state: Vec<f64>,
+use std::borrow::Cow;
+
+// The state and the weights are stored as T, with ±1 patterns the hebbian weights are integers,
+// so the network works the same with any T, as long as the weights fit in it
+pub struct ClassicNetworkDiscrete<T: Scalar = f64> {
+    pub state: Vec<T>,
     pub rng: rand::rngs::ThreadRng,
-    weights: Vec<Vec<f64>>,
+    weights: Matrix<T>,
+    fields: LocalFields<T>,
     number_of_learned_states: f64,
     steps: usize,
     nodes_yet_to_update: Vec<usize>,
+    last_update: Option<(usize, f64)>,
+    update_mode: hop_net::UpdateMode,
+    learning_rule: LearningRule,
+    // The iterative learning rules train on every pattern again when a new one is learned
+    patterns: Vec<Vec<T>>,
+    training_callback: Option<TrainingCallback>,
 }
 
 // The network will mostly be interacted with trough this traits
-impl hop_net::Net<f64> for ClassicNetworkDiscrete {
-    fn get_state(&self) -> Vec<f64> {
-        self.state.clone()
+impl<T: Scalar> hop_net::Net<T> for ClassicNetworkDiscrete<T> {
+    fn get_state_ref(&self) -> &[T] {
+        &self.state
     }
 
-    fn learn(&mut self, state: &[f64]) {
+    fn learn(&mut self, state: &[T]) {
         self.number_of_learned_states += 1.0;
-        self.hebbian_learning(state);
+        self.patterns.push(state.to_vec());
+        let callback = &mut self.training_callback;
+        learning_rules::learn(
+            self.learning_rule,
+            &mut self.weights,
+            &self.patterns,
+            &mut |progress| match callback {
+                Some(callback) => callback(progress),
+                None => true,
+            },
+        );
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn learn_all(&mut self, patterns: &[Vec<T>]) {
+        self.reset_weights();
+        self.number_of_learned_states = patterns.len() as f64;
+        self.patterns = patterns.to_vec();
+        let callback = &mut self.training_callback;
+        learning_rules::learn_all(
+            self.learning_rule,
+            &mut self.weights,
+            &self.patterns,
+            &mut |progress| match callback {
+                Some(callback) => callback(progress),
+                None => true,
+            },
+        );
+        self.fields.weights_changed(&self.weights, &self.state);
     }
 
-    fn step(&mut self) -> (bool, Vec<f64>) {
+    fn step(&mut self) -> bool {
+        self.last_update = None;
+        if self.update_mode == hop_net::UpdateMode::Synchronous {
+            self.steps += 1;
+            return local_fields::synchronous_update(
+                &self.weights,
+                &mut self.fields,
+                &mut self.state,
+            );
+        }
+
         //generat e random index from 0 to state.len()
         //let i = self.rng.gen_range(0..self.state.len());
         if self.nodes_yet_to_update.is_empty() {
@@ -28,119 +85,131 @@ impl hop_net::Net<f64> for ClassicNetworkDiscrete {
         }
         let i = self.nodes_yet_to_update.pop().unwrap();
         self.steps += 1;
-        let state_changed = self.update_node(i);
-
-        (state_changed, self.state.clone())
+        self.last_update = Some((i, T::acc_to_f64(self.fields.get(i))));
+        local_fields::update_node(&self.weights, &mut self.fields, &mut self.state, i)
     }
 
     fn get_steps(&self) -> usize {
         self.steps
     }
 
-    fn set_state(&mut self, state: &[f64]) {
-        if state.len() < 4 {
-            panic!("State is too short");
-        }
+    fn get_last_update(&self) -> Option<(usize, f64)> {
+        self.last_update
+    }
+
+    fn set_state(&mut self, state: &[T]) -> Result<(), NetworkError> {
+        hop_net::check_state_size(state.len())?;
 
         if self.state.len() != state.len() {
             self.number_of_learned_states = 0.0;
-            self.weights = vec![vec![0.0; state.len()]; state.len()];
+            self.patterns.clear();
+            self.weights = Matrix::square(state.len());
             self.steps = 0;
         }
         self.state = state.to_vec();
+        self.fields.recompute(&self.weights, &self.state);
 
         // The starting state has just been set, so we are 0 steps away from it
         self.steps = 0;
 
         // We make sure that all nodes are marked as "to update"
         hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+        Ok(())
     }
 
     fn reset_weights(&mut self) {
-        self.weights = vec![vec![0.0; self.state.len()]; self.state.len()];
+        self.weights = Matrix::square(self.state.len());
         self.number_of_learned_states = 0.0;
+        self.patterns.clear();
+        self.fields.weights_changed(&self.weights, &self.state);
     }
 
-    fn get_weights(&self) -> Vec<Vec<f64>> {
-        self.weights.clone()
+    fn get_weights(&self) -> Cow<'_, Matrix<T>> {
+        Cow::Borrowed(&self.weights)
+    }
+
+    fn get_recall_weights(&self) -> Option<&Matrix<T>> {
+        Some(&self.weights)
+    }
+
+    fn modify_weights(&mut self, modify: &mut dyn FnMut(&mut Matrix<T>)) {
+        modify(&mut self.weights);
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn set_update_mode(&mut self, mode: hop_net::UpdateMode) {
+        self.update_mode = mode;
+    }
+
+    fn get_update_mode(&self) -> hop_net::UpdateMode {
+        self.update_mode
+    }
+
+    // The weights are always built with a single rule, the patterns learned so far are learned again with the new one
+    fn set_learning_rule(&mut self, rule: LearningRule) {
+        if rule == self.learning_rule {
+            return;
+        }
+        self.learning_rule = rule;
+        if !self.patterns.is_empty() {
+            let patterns = std::mem::take(&mut self.patterns);
+            self.learn_all(&patterns);
+        }
+    }
+
+    fn set_training_callback(&mut self, callback: TrainingCallback) {
+        self.training_callback = Some(callback);
     }
 }
-// In this case it gives a false allarm, the suggestion is not applicable
-#[allow(clippy::unnecessary_unwrap)]
-impl ClassicNetworkDiscrete {
-    pub fn new(size: usize, start_state: Option<&Vec<f64>>) -> ClassicNetworkDiscrete {
-        let state = if start_state.is_none() {
-            vec![-1.0; size]
-        } else {
-            let start_s = start_state.unwrap();
-            if start_s.len() != size {
-                panic!("Size and start size lenght are differnt");
-            }
-            start_s.clone()
-        };
+impl<T: Scalar> ClassicNetworkDiscrete<T> {
+    pub fn new(
+        size: usize,
+        start_state: Option<&Vec<T>>,
+    ) -> Result<ClassicNetworkDiscrete<T>, NetworkError> {
+        let state = hop_net::start_state(size, start_state)?;
 
         let mut nodes_to_update = Vec::with_capacity(size);
         hop_net::reset_nodes_to_update(&mut nodes_to_update, size);
 
-        ClassicNetworkDiscrete {
+        Ok(ClassicNetworkDiscrete {
             state,
             rng: rand::thread_rng(),
-            weights: vec![vec![0.0; size]; size],
+            weights: Matrix::square(size),
+            fields: LocalFields::new(size),
             steps: 0,
             number_of_learned_states: 0.0,
             nodes_yet_to_update: nodes_to_update,
-        }
+            last_update: None,
+            update_mode: hop_net::UpdateMode::Asynchronous,
+            learning_rule: LearningRule::default(),
+            patterns: Vec::new(),
+            training_callback: None,
+        })
     }
 
-    pub fn init(&mut self, state: Option<&Vec<f64>>) {
+    pub fn init(&mut self, state: Option<&Vec<T>>) {
         if let Some(s) = state {
             self.state = s.clone();
         } else {
             for i in 0..self.state.len() {
                 self.state[i] = if self.rng.gen_range(0..=1) == 1 {
-                    1.0
+                    T::ONE
                 } else {
-                    -1.0
+                    T::MINUS_ONE
                 };
             }
         }
         // self.setWeightsToRandom();
+        self.fields.recompute(&self.weights, &self.state);
         self.steps = 0;
     }
 
-    fn hebbian_learning(&mut self, state_to_learn: &[f64]) {
-        for i in 0..self.weights.len() {
-            for j in 0..self.weights[i].len() {
-                if i == j {
-                    self.weights[i][j] = 0.0;
-                } else {
-                    // self.weights[i][j] +=(1.0 / self.number_of_learned_states) *(state_to_learn[i] * state_to_learn[j]) + ((self.number_of_learned_states - 1.0) /self.number_of_learned_states) self.weights[i][j];
-                    self.weights[i][j] += state_to_learn[i] * state_to_learn[j];
-                }
-            }
-        }
-    }
-
-    // This function summs the weight coming into a node, and then takes the sign of the sum
-    // This is the standard upadate rule for the classic hopfield networks
-    fn update_node(&mut self, i: usize) -> bool {
-        let mut sum = 0.0;
-        for j in 0..self.weights[i].len() {
-            sum += self.weights[i][j] * self.state[j];
-        }
-        let new_val = if sum > 0.0 { 1.0 } else { -1.0 };
-        if new_val != self.state[i] {
-            self.state[i] = new_val;
-            return true;
-        }
-        false
-    }
-
     // Getters
 }
 
-impl std::fmt::Display for ClassicNetworkDiscrete {
+impl<T: Scalar> std::fmt::Display for ClassicNetworkDiscrete<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "state:\n{}", hop_net::state_vec_to_string(&self.state))
+        let state: Vec<f64> = self.state.iter().map(|x| x.to_f64()).collect();
+        write!(f, "state:\n{}", hop_net::state_vec_to_string(&state))
     }
 }
@@ -0,0 +1,112 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::Hasher;
+
+// How many past states are remembered, cycles longer than this can't be detected
+pub const CYCLE_HISTORY_WINDOW: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle {
+    // A period of 1 means the network reached a fixed point
+    pub period: usize,
+    // The states visited during one period, in the order the network visited them
+    pub states: Vec<Vec<f64>>,
+}
+
+// Keeps a sliding window of the last states of the network, if a state shows up twice
+// the network is trapped in a cycle, and the distance between the two occurences is the period
+pub struct CycleDetector {
+    window: usize,
+    hashes: VecDeque<u64>,
+    states: VecDeque<Vec<f64>>,
+}
+
+impl CycleDetector {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            hashes: VecDeque::with_capacity(window),
+            states: VecDeque::with_capacity(window),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+        self.states.clear();
+    }
+
+    // Records a new state, and returns the cycle it closes (if any)
+    pub fn push(&mut self, state: &[f64]) -> Option<Cycle> {
+        let hash = hash_state(state);
+
+        // We search from the most recent state, so the shortest period is found first
+        // the hash is only used to skip the comparisons, two different states could still have the same hash
+        for back in (0..self.hashes.len()).rev() {
+            if self.hashes[back] == hash && self.states[back] == state {
+                let states: Vec<Vec<f64>> = self.states.range(back..).cloned().collect();
+                return Some(Cycle {
+                    period: states.len(),
+                    states,
+                });
+            }
+        }
+
+        if self.hashes.len() == self.window {
+            self.hashes.pop_front();
+            self.states.pop_front();
+        }
+        self.hashes.push_back(hash);
+        self.states.push_back(state.to_vec());
+        None
+    }
+}
+
+impl Default for CycleDetector {
+    fn default() -> Self {
+        Self::new(CYCLE_HISTORY_WINDOW)
+    }
+}
+
+pub fn hash_state(state: &[f64]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for node in state {
+        hasher.write_u64(node.to_bits());
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_cycle_has_period_2() {
+        let a = vec![1.0, -1.0, 1.0, -1.0];
+        let b = vec![-1.0, 1.0, -1.0, 1.0];
+        let mut detector = CycleDetector::default();
+        assert_eq!(detector.push(&a), None);
+        assert_eq!(detector.push(&b), None);
+        let cycle = detector.push(&a).expect("the state came back");
+        assert_eq!(cycle.period, 2);
+        assert_eq!(cycle.states, vec![a, b]);
+    }
+
+    #[test]
+    fn fixed_point_has_period_1() {
+        let a = vec![1.0, 1.0, -1.0, -1.0];
+        let mut detector = CycleDetector::default();
+        assert_eq!(detector.push(&a), None);
+        assert_eq!(detector.push(&a).map(|cycle| cycle.period), Some(1));
+    }
+
+    #[test]
+    fn cycles_longer_than_the_window_are_missed() {
+        let states: Vec<Vec<f64>> = (0..3)
+            .map(|i| (0..4).map(|j| if i == j { 1.0 } else { -1.0 }).collect())
+            .collect();
+        let mut detector = CycleDetector::new(2);
+        for state in states.iter().chain(states.iter()) {
+            assert_eq!(detector.push(state), None);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::Net;
+use rand::seq::index;
+use rand::Rng;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use strum_macros::EnumIter;
+
+#[derive(EnumIter, Debug, PartialEq, Clone, Copy)]
+pub enum DamageKind {
+    // Every pair of symmetric weights is set to 0 with the given probability
+    Dilution,
+    // The given fraction of the neurons loses all its connections
+    KillNeurons,
+    // Every weight is replaced by its sign, the amount is ignored
+    ClipWeights,
+    // Gaussian noise is added to the weights, the amount is its standard deviation relative to the weights one
+    GaussianNoise,
+}
+
+impl Display for DamageKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DamageKind::Dilution => write!(f, "Dilution"),
+            DamageKind::KillNeurons => write!(f, "Kill neurons"),
+            DamageKind::ClipWeights => write!(f, "Clip weights"),
+            DamageKind::GaussianNoise => write!(f, "Gaussian noise"),
+        }
+    }
+}
+
+impl DamageKind {
+    // The biggest amount of damage that makes sense for this kind
+    pub fn max_amount(&self) -> f64 {
+        match self {
+            DamageKind::Dilution => 1.0,
+            DamageKind::KillNeurons => 1.0,
+            DamageKind::ClipWeights => 1.0,
+            DamageKind::GaussianNoise => 2.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Damage {
+    pub kind: DamageKind,
+    pub amount: f64,
+}
+
+// Damages every weight matrix of the network, the random choices (like which neurons to kill) are
+// made once, so networks with more than a matrix are damaged consistently
+// The dilution needs both weights[i][j] and weights[j][i], so it can't iterate over the rows
+#[allow(clippy::needless_range_loop)]
+pub fn apply_damage(net: &mut dyn Net<f64>, damage: Damage) {
+    let mut rng = rand::thread_rng();
+    let size = net.get_state().len();
+
+    match damage.kind {
+        DamageKind::Dilution => {
+            let probability = damage.amount.clamp(0.0, 1.0);
+            net.modify_weights(&mut |weights| {
+                for i in 0..weights.rows() {
+                    for j in (i + 1)..weights.cols() {
+                        if rng.gen_bool(probability) {
+                            weights[i][j] = 0.0;
+                            weights[j][i] = 0.0;
+                        }
+                    }
+                }
+            });
+        }
+        DamageKind::KillNeurons => {
+            let amount = (damage.amount.clamp(0.0, 1.0) * size as f64).round() as usize;
+            let dead = index::sample(&mut rng, size, amount).into_vec();
+            net.modify_weights(&mut |weights| {
+                for &neuron in dead.iter() {
+                    for row in weights.iter_rows_mut() {
+                        row[neuron] = 0.0;
+                    }
+                    for w in weights[neuron].iter_mut() {
+                        *w = 0.0;
+                    }
+                }
+            });
+        }
+        DamageKind::ClipWeights => {
+            net.modify_weights(&mut |weights| {
+                for w in weights.as_mut_slice().iter_mut() {
+                    if *w != 0.0 {
+                        *w = w.signum();
+                    }
+                }
+            });
+        }
+        DamageKind::GaussianNoise => {
+            net.modify_weights(&mut |weights| {
+                // The noise has to be compared with the weights, otherwise the same amount would mean
+                // very different things for different learning rules
+                let (sum, count) = weights
+                    .as_slice()
+                    .iter()
+                    .filter(|w| **w != 0.0)
+                    .fold((0.0, 0), |(sum, count), w| (sum + w * w, count + 1));
+                let weights_std = if count > 0 {
+                    (sum / count as f64).sqrt()
+                } else {
+                    1.0
+                };
+                let std = damage.amount * weights_std;
+                for w in weights.as_mut_slice().iter_mut() {
+                    *w += std * gaussian(&mut rng);
+                }
+            });
+        }
+    }
+}
+
+// Copies every weight matrix of the network, so that it can be put back later
+pub fn snapshot_weights(net: &mut dyn Net<f64>) -> Vec<Matrix<f64>> {
+    let mut snapshot = Vec::new();
+    net.modify_weights(&mut |weights| snapshot.push(weights.clone()));
+    snapshot
+}
+
+pub fn restore_weights(net: &mut dyn Net<f64>, snapshot: &[Matrix<f64>]) {
+    let mut matrices = snapshot.iter();
+    net.modify_weights(&mut |weights| {
+        if let Some(matrix) = matrices.next() {
+            if matrix.rows() == weights.rows() && matrix.cols() == weights.cols() {
+                weights.clone_from(matrix);
+            }
+        }
+    });
+}
+
+// Box-Muller transform, it gives a sample of a normal distribution with mean 0 and standard deviation 1
+fn gaussian(rng: &mut impl Rng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
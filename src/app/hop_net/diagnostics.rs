@@ -0,0 +1,179 @@
+use crate::app::hop_net::matrix;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::Net;
+use rand::Rng;
+use std::borrow::Cow;
+
+// The eigenvalues are estimated with power iteration, which stops when the estimate moves less than
+// this (relative to its size), or after MAX_POWER_ITERATIONS matrix products
+const POWER_TOLERANCE: f64 = 1e-6;
+const MAX_POWER_ITERATIONS: usize = 300;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DiagnosticsReport {
+    // |W - Wᵀ| / |W| with the Frobenius norm, 0 for a symmetric matrix
+    pub symmetry_error: f64,
+    // The biggest |w_ij - w_ji|
+    pub max_asymmetry: f64,
+    // The biggest |w_ii|
+    pub max_diagonal: f64,
+    // The extremes of the spectrum of the symmetric part of the weights
+    pub min_eigenvalue: f64,
+    pub max_eigenvalue: f64,
+    // Frobenius norm
+    pub weight_norm: f64,
+    // How many of the stored patterns are fixed points
+    pub stable_patterns: usize,
+    pub pattern_count: usize,
+}
+
+impl DiagnosticsReport {
+    pub fn fixed_point_fraction(&self) -> Option<f64> {
+        if self.pattern_count == 0 {
+            return None;
+        }
+        Some(self.stable_patterns as f64 / self.pattern_count as f64)
+    }
+
+    // With symmetric weights and an empty diagonal the energy can only go down while the nodes are updated
+    // one at a time, so the network always ends up in a fixed point
+    pub fn guarantees_convergence(&self) -> bool {
+        self.max_asymmetry == 0.0 && self.max_diagonal == 0.0
+    }
+}
+
+// Checks the weights the network steps with, for the networks that have more than one set of weights
+// these are the ones returned by get_recall_weights
+pub fn diagnose(net: &dyn Net<f64>, patterns: &[Vec<f64>]) -> DiagnosticsReport {
+    let weights = match net.get_recall_weights() {
+        Some(weights) => Cow::Borrowed(weights),
+        None => net.get_weights(),
+    };
+    let size = weights.rows().min(weights.cols());
+
+    let mut norm_squared = 0.0;
+    let mut asymmetry_squared = 0.0;
+    let mut max_asymmetry: f64 = 0.0;
+    let mut max_diagonal: f64 = 0.0;
+    for i in 0..size {
+        max_diagonal = max_diagonal.max(weights[i][i].abs());
+        for j in 0..size {
+            norm_squared += weights[i][j] * weights[i][j];
+            let difference = (weights[i][j] - weights[j][i]).abs();
+            asymmetry_squared += difference * difference;
+            max_asymmetry = max_asymmetry.max(difference);
+        }
+    }
+    let weight_norm = norm_squared.sqrt();
+    let symmetry_error = if weight_norm > 0.0 {
+        asymmetry_squared.sqrt() / weight_norm
+    } else {
+        0.0
+    };
+
+    let (min_eigenvalue, max_eigenvalue) = spectrum_range(&symmetric_part(&weights));
+
+    let stable_patterns = patterns
+        .iter()
+        .filter(|pattern| unstable_bits(net, pattern) == 0)
+        .count();
+
+    DiagnosticsReport {
+        symmetry_error,
+        max_asymmetry,
+        max_diagonal,
+        min_eigenvalue,
+        max_eigenvalue,
+        weight_norm,
+        stable_patterns,
+        pattern_count: patterns.len(),
+    }
+}
+
+// The nodes whose field disagrees with the pattern, they would flip if the network was in the pattern
+pub fn unstable_bits(net: &dyn Net<f64>, pattern: &[f64]) -> usize {
+    net.get_local_fields(pattern)
+        .iter()
+        .zip(pattern.iter())
+        .filter(|(h, x)| {
+            let new_val = if **h > 0.0 { 1.0 } else { -1.0 };
+            new_val != **x
+        })
+        .count()
+}
+
+// w_ij and w_ji are both set to their mean
+pub fn symmetrize(net: &mut dyn Net<f64>) {
+    net.modify_weights(&mut |weights| {
+        let size = weights.rows().min(weights.cols());
+        for i in 0..size {
+            for j in (i + 1)..size {
+                let mean = (weights[i][j] + weights[j][i]) / 2.0;
+                weights[i][j] = mean;
+                weights[j][i] = mean;
+            }
+        }
+    });
+}
+
+pub fn clear_diagonal(net: &mut dyn Net<f64>) {
+    net.modify_weights(&mut |weights| weights.set_diagonal(0.0));
+}
+
+// (W + Wᵀ) / 2, the energy only depends on this part of the weights
+fn symmetric_part(weights: &Matrix<f64>) -> Cow<'_, Matrix<f64>> {
+    if weights.is_symmetric() {
+        return Cow::Borrowed(weights);
+    }
+    let transposed = weights.transposed();
+    let mut result = weights.clone();
+    for (value, transposed_value) in result.as_mut_slice().iter_mut().zip(transposed.as_slice()) {
+        *value = (*value + transposed_value) / 2.0;
+    }
+    Cow::Owned(result)
+}
+
+// The smallest and the biggest eigenvalue of a symmetric matrix.
+// Power iteration finds the eigenvalue with the biggest magnitude, that's one of the two extremes,
+// shifting the matrix by it makes the other extreme the one with the biggest magnitude
+fn spectrum_range(matrix: &Matrix<f64>) -> (f64, f64) {
+    if matrix.rows() == 0 {
+        return (0.0, 0.0);
+    }
+    let first = dominant_eigenvalue(matrix, 0.0);
+    let second = dominant_eigenvalue(matrix, first) + first;
+    (first.min(second), first.max(second))
+}
+
+// The eigenvalue of matrix - shift * I with the biggest magnitude
+fn dominant_eigenvalue(matrix: &Matrix<f64>, shift: f64) -> f64 {
+    let size = matrix.rows();
+    let mut rng = rand::thread_rng();
+    let mut vector: Vec<f64> = (0..size).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    let mut product = vec![0.0; size];
+    let mut eigenvalue = 0.0;
+
+    for _ in 0..MAX_POWER_ITERATIONS {
+        let norm = matrix::dot(&vector, &vector).sqrt();
+        if norm == 0.0 {
+            return 0.0;
+        }
+        for x in vector.iter_mut() {
+            *x /= norm;
+        }
+
+        matrix.mul_vec(&vector, &mut product);
+        matrix::add_scaled(&mut product, -shift, &vector);
+
+        // The Rayleigh quotient, the vector has norm 1
+        let new_eigenvalue = matrix::dot(&vector, &product);
+        let converged =
+            (new_eigenvalue - eigenvalue).abs() <= POWER_TOLERANCE * new_eigenvalue.abs().max(1.0);
+        eigenvalue = new_eigenvalue;
+        std::mem::swap(&mut vector, &mut product);
+        if converged {
+            break;
+        }
+    }
+    eigenvalue
+}
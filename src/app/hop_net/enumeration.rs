@@ -0,0 +1,325 @@
+use crate::app::hop_net::experiments;
+use crate::app::hop_net::local_fields::LocalFields;
+use crate::app::hop_net::Net;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+// A 5x5 grid, its 2^25 states take a few seconds and about 160 MB, bigger networks would take too long
+pub const MAX_ENUMERATION_SIZE: usize = 25;
+
+// The progress is reported at most this many times for each of the two passes
+const PROGRESS_REPORTS: usize = 50;
+
+// Marks the states that end up in a cycle instead of a fixed point, it can't be a state because they are at most 2^25
+const NO_FIXED_POINT: u32 = u32::MAX;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FixedPointKind {
+    // The index of the pattern in the order it was learned
+    Pattern(usize),
+    Negation(usize),
+    // The sign of the sum of three patterns, each one with its sign
+    Mixture(Vec<(usize, f64)>),
+    Spurious,
+}
+
+impl Display for FixedPointKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FixedPointKind::Pattern(i) => write!(f, "pattern {}", i + 1),
+            FixedPointKind::Negation(i) => write!(f, "negation of {}", i + 1),
+            FixedPointKind::Mixture(components) => {
+                write!(f, "mixture")?;
+                for (i, sign) in components.iter() {
+                    write!(f, " {}{}", if *sign > 0.0 { '+' } else { '-' }, i + 1)?;
+                }
+                Ok(())
+            }
+            FixedPointKind::Spurious => write!(f, "spurious"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FixedPoint {
+    pub state: Vec<f64>,
+    pub energy: f64,
+    // How many states end up in this fixed point
+    pub basin_size: u64,
+    pub kind: FixedPointKind,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Enumeration {
+    pub fixed_points: Vec<FixedPoint>,
+    // 2^N
+    pub state_count: u64,
+    // The states that never reach a fixed point, only networks with asymmetric weights have them
+    pub cycling_states: u64,
+    pub layer_sizes: Vec<usize>,
+}
+
+impl Enumeration {
+    // One line per fixed point, the state is written as a string of + and -
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("kind,energy,basin_size,basin_fraction,state\n");
+        for point in self.fixed_points.iter() {
+            let state: String = point
+                .state
+                .iter()
+                .map(|x| if *x > 0.0 { '+' } else { '-' })
+                .collect();
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                point.kind,
+                point.energy,
+                point.basin_size,
+                point.basin_size as f64 / self.state_count as f64,
+                state
+            ));
+        }
+        csv
+    }
+}
+
+// Why following the dynamics from every state would find nothing worth listing, None if the network can be enumerated
+pub fn unavailable_reason(net: &dyn Net<f64>) -> Option<&'static str> {
+    if net.is_stochastic() {
+        return Some(
+            "The network samples its nodes at random, it has no fixed points to enumerate",
+        );
+    }
+    if net.plays_sequences() {
+        return Some(
+            "A sequence network moves on from every pattern, it has no fixed points to enumerate",
+        );
+    }
+    // The bigger networks are refused anyway, checking them could take a copy of their weights for nothing
+    if net.get_state_ref().len() <= MAX_ENUMERATION_SIZE {
+        let symmetric = match net.get_recall_weights() {
+            Some(weights) => weights.is_symmetric(),
+            None => net.get_weights().is_symmetric(),
+        };
+        if !symmetric {
+            return Some(
+                "The weights aren't symmetric, many states would never reach a fixed point",
+            );
+        }
+    }
+    None
+}
+
+// Goes through every state of the network, and follows the dynamics from each of them to find all the
+// fixed points and their exact basins. The random update order of the network would make the basins
+// random too, so here a state always flips its first node that disagrees with its field.
+// With symmetric weights the energy never goes up, so every state reaches a fixed point.
+// The states are visited in gray code order, so that consecutive states differ by a single node and
+// the fields can be updated instead of computed from scratch.
+// Returns None if the network has more than MAX_ENUMERATION_SIZE nodes
+pub fn enumerate_states(
+    net: &dyn Net<f64>,
+    patterns: &[Vec<f64>],
+    progress: &mut dyn FnMut(usize, usize),
+) -> Option<Enumeration> {
+    let size = net.get_state_ref().len();
+    if size == 0 || size > MAX_ENUMERATION_SIZE {
+        return None;
+    }
+
+    let weights = match net.get_recall_weights() {
+        Some(weights) => Cow::Borrowed(weights),
+        None => net.get_weights(),
+    };
+    // Whatever the network adds to the fields on top of the weights, like biases or thresholds
+    let thresholds = net.get_local_fields(&vec![0.0; size]);
+
+    // The bit i of a state index is set if node i is +1
+    let count = 1usize << size;
+    let report_every = (count / PROGRESS_REPORTS).max(1);
+    let total_work = 2 * count;
+    let mut next: Vec<u32> = vec![0; count];
+    let mut state = vec![-1.0; size];
+    let mut fields = LocalFields::new(size);
+    fields.recompute(&weights, &state);
+
+    for k in 0..count {
+        if k > 0 {
+            let i = k.trailing_zeros() as usize;
+            fields.node_changed(&weights, i, state[i], -state[i]);
+            state[i] = -state[i];
+        }
+        let index = k ^ (k >> 1);
+        let unstable = (0..size).find(|&i| {
+            let new_val = if fields.get(i) + thresholds[i] > 0.0 {
+                1.0
+            } else {
+                -1.0
+            };
+            new_val != state[i]
+        });
+        next[index] = match unstable {
+            Some(i) => (index ^ (1 << i)) as u32,
+            None => index as u32,
+        };
+        if (k + 1) % report_every == 0 {
+            progress(k + 1, total_work);
+        }
+    }
+
+    // Every state is followed until a state that is already resolved, then the whole path gets the same fixed point.
+    // Afterwards next holds the fixed point of every state instead of the state that follows it
+    const UNKNOWN: u8 = 0;
+    const ON_PATH: u8 = 1;
+    const RESOLVED: u8 = 2;
+    let mut status = vec![UNKNOWN; count];
+    let mut path = Vec::new();
+    for start in 0..count {
+        let mut x = start;
+        while status[x] == UNKNOWN {
+            status[x] = ON_PATH;
+            path.push(x);
+            x = next[x] as usize;
+        }
+        // The path either joined one that was already resolved, or it looped back on itself
+        let fixed_point = if status[x] == RESOLVED {
+            next[x]
+        } else if next[x] as usize == x {
+            x as u32
+        } else {
+            NO_FIXED_POINT
+        };
+        for p in path.drain(..) {
+            next[p] = fixed_point;
+            status[p] = RESOLVED;
+        }
+        if (start + 1) % report_every == 0 {
+            progress(count + start + 1, total_work);
+        }
+    }
+
+    let mut basins: HashMap<u32, u64> = HashMap::new();
+    for fixed_point in next.iter() {
+        *basins.entry(*fixed_point).or_insert(0) += 1;
+    }
+    let cycling_states = basins.remove(&NO_FIXED_POINT).unwrap_or(0);
+
+    let patterns: Vec<&Vec<f64>> = patterns.iter().filter(|p| p.len() == size).collect();
+    let mut fixed_points: Vec<FixedPoint> = basins
+        .into_iter()
+        .map(|(index, basin_size)| {
+            let state: Vec<f64> = (0..size)
+                .map(|i| if index >> i & 1 == 1 { 1.0 } else { -1.0 })
+                .collect();
+            FixedPoint {
+                energy: energy(net, &state, &thresholds),
+                kind: classify(&state, &patterns),
+                basin_size,
+                state,
+            }
+        })
+        .collect();
+    fixed_points.sort_by(|a, b| a.energy.total_cmp(&b.energy));
+
+    Some(Enumeration {
+        fixed_points,
+        state_count: count as u64,
+        cycling_states,
+        layer_sizes: net.get_layer_sizes(),
+    })
+}
+
+// E = -1/2 sᵀWs - θᵀs, the fields already hold Ws + θ, and the thresholds are the fields of the zero state
+pub fn energy(net: &dyn Net<f64>, state: &[f64], thresholds: &[f64]) -> f64 {
+    let fields = net.get_local_fields(state);
+    state
+        .iter()
+        .zip(fields.iter().zip(thresholds.iter()))
+        .map(|(s, (h, threshold))| -0.5 * s * (h - threshold) - threshold * s)
+        .sum()
+}
+
+fn classify(state: &[f64], patterns: &[&Vec<f64>]) -> FixedPointKind {
+    for (i, pattern) in patterns.iter().enumerate() {
+        let overlap = experiments::overlap(pattern, state);
+        if overlap == 1.0 {
+            return FixedPointKind::Pattern(i);
+        }
+        if overlap == -1.0 {
+            return FixedPointKind::Negation(i);
+        }
+    }
+
+    // The mixtures of three patterns are the textbook ones, the sign of the sum is never 0
+    for a in 0..patterns.len() {
+        for b in (a + 1)..patterns.len() {
+            for c in (b + 1)..patterns.len() {
+                for signs in 0..8 {
+                    let sign = |bit: usize| if signs >> bit & 1 == 1 { -1.0 } else { 1.0 };
+                    let components = [(a, sign(0)), (b, sign(1)), (c, sign(2))];
+                    let is_mixture = state.iter().enumerate().all(|(i, s)| {
+                        let sum: f64 = components
+                            .iter()
+                            .map(|(pattern, sign)| sign * patterns[*pattern][i])
+                            .sum();
+                        sum.signum() == *s
+                    });
+                    if is_mixture {
+                        return FixedPointKind::Mixture(components.to_vec());
+                    }
+                }
+            }
+        }
+    }
+    FixedPointKind::Spurious
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::hop_net::classic_network::ClassicNetworkDiscrete;
+
+    #[test]
+    fn finds_the_patterns_and_their_negations() {
+        // Orthogonal patterns don't disturb each other, so both are fixed points
+        let patterns = vec![
+            vec![1.0, 1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0],
+            vec![1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0],
+        ];
+        let mut net = ClassicNetworkDiscrete::<f64>::new(8, None).unwrap();
+        net.learn_all(&patterns);
+
+        let enumeration = enumerate_states(&net, &patterns, &mut |_, _| {}).unwrap();
+        assert_eq!(enumeration.state_count, 256);
+        assert_eq!(enumeration.cycling_states, 0);
+        let basins: u64 = enumeration
+            .fixed_points
+            .iter()
+            .map(|point| point.basin_size)
+            .sum();
+        assert_eq!(basins, 256);
+
+        for (i, pattern) in patterns.iter().enumerate() {
+            let negation: Vec<f64> = pattern.iter().map(|x| -x).collect();
+            for (state, kind) in [
+                (pattern, FixedPointKind::Pattern(i)),
+                (&negation, FixedPointKind::Negation(i)),
+            ] {
+                let point = enumeration
+                    .fixed_points
+                    .iter()
+                    .find(|point| &point.state == state)
+                    .expect("every pattern and its negation is a fixed point");
+                assert_eq!(point.kind, kind);
+                assert!(point.basin_size > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_networks_that_are_too_big() {
+        let net = ClassicNetworkDiscrete::<f64>::new(MAX_ENUMERATION_SIZE + 1, None).unwrap();
+        assert!(enumerate_states(&net, &[], &mut |_, _| {}).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::app::hop_net::batch_recall::RecallOptions;
+use crate::app::hop_net::cycle_detection::Cycle;
+use crate::app::hop_net::cycle_detection::CycleDetector;
+use crate::app::hop_net::damage;
+use crate::app::hop_net::damage::Damage;
+use crate::app::hop_net::damage::DamageKind;
+use crate::app::hop_net::network_error::NetworkError;
+use crate::app::hop_net::Net;
+use crate::app::hop_net::UpdateMode;
+use rand::seq::index;
+
+// After this many sweeps a recall is considered over, even if the network is still changing
+pub const MAX_RECALL_SWEEPS: usize = 100;
+
+// A recall is successful if the final state overlaps with the pattern at least this much
+pub const RECALL_THRESHOLD: f64 = 0.95;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DamageExperiment {
+    pub kind: DamageKind,
+    // The amount of damage goes from 0 to kind.max_amount() in this many steps
+    pub levels: usize,
+    // The fraction of the bits of a stored pattern that are flipped to build a probe
+    pub probe_noise: f64,
+    // How many probes are built for each stored pattern, at each damage level
+    pub trials: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ExperimentPoint {
+    pub amount: f64,
+    pub mean_overlap: f64,
+    pub recall_rate: f64,
+}
+
+// Measures how well the stored patterns are recalled at growing amounts of damage.
+// Every level starts again from the original weights, and when it's over the network gets back
+// both its weights and its state
+pub fn run_damage_experiment(
+    net: &mut dyn Net<f64>,
+    patterns: &[Vec<f64>],
+    experiment: DamageExperiment,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<Vec<ExperimentPoint>, NetworkError> {
+    let original_weights = damage::snapshot_weights(net);
+    let original_state = net.get_state();
+    let levels = experiment.levels.max(2);
+    let mut points = Vec::with_capacity(levels);
+
+    for level in 0..levels {
+        let amount = experiment.kind.max_amount() * level as f64 / (levels - 1) as f64;
+        damage::restore_weights(net, &original_weights);
+        if level > 0 {
+            damage::apply_damage(
+                net,
+                Damage {
+                    kind: experiment.kind,
+                    amount,
+                },
+            );
+        }
+
+        let (mean_overlap, recall_rate) =
+            measure_recall(net, patterns, experiment.probe_noise, experiment.trials)?;
+        points.push(ExperimentPoint {
+            amount,
+            mean_overlap,
+            recall_rate,
+        });
+        progress(level + 1, levels);
+    }
+
+    damage::restore_weights(net, &original_weights);
+    net.set_state(&original_state)?;
+    Ok(points)
+}
+
+// Returns the mean overlap between the recalled states and the patterns, and the fraction of successful recalls
+pub fn measure_recall(
+    net: &mut dyn Net<f64>,
+    patterns: &[Vec<f64>],
+    probe_noise: f64,
+    trials: usize,
+) -> Result<(f64, f64), NetworkError> {
+    if patterns.is_empty() || trials == 0 {
+        return Ok((0.0, 0.0));
+    }
+
+    // Every probe is followed by the pattern it was built from
+    let (probes, targets): (Vec<Vec<f64>>, Vec<&Vec<f64>>) = patterns
+        .iter()
+        .flat_map(|pattern| (0..trials).map(move |_| (add_noise(pattern, probe_noise), pattern)))
+        .unzip();
+
+    let options = RecallOptions {
+        update_mode: net.get_update_mode(),
+        max_sweeps: MAX_RECALL_SWEEPS,
+    };
+    let results: Vec<Vec<f64>> = match net.batch_recall(&probes, &[], options) {
+        Some(results) => results?.into_iter().map(|result| result.state).collect(),
+        None => probes
+            .iter()
+            .map(|probe| recall(net, probe, MAX_RECALL_SWEEPS))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let mut overlap_sum = 0.0;
+    let mut recalled = 0;
+    for (pattern, result) in targets.iter().zip(results.iter()) {
+        let m = overlap(pattern, result);
+        overlap_sum += m;
+        if m >= RECALL_THRESHOLD {
+            recalled += 1;
+        }
+    }
+
+    let total = (patterns.len() * trials) as f64;
+    Ok((overlap_sum / total, recalled as f64 / total))
+}
+
+// Lets the network run from the probe until it stops changing, like the network thread would do
+pub fn recall(
+    net: &mut dyn Net<f64>,
+    probe: &[f64],
+    max_sweeps: usize,
+) -> Result<Vec<f64>, NetworkError> {
+    net.set_state(probe)?;
+    let steps_per_sweep = steps_per_sweep(net);
+    let max_steps_without_change = steps_per_sweep + 1;
+    let mut steps_without_change = 0;
+
+    for _ in 0..max_sweeps * steps_per_sweep {
+        if net.step() {
+            steps_without_change = 0;
+        } else {
+            steps_without_change += 1;
+            if steps_without_change >= max_steps_without_change {
+                break;
+            }
+        }
+    }
+    Ok(net.get_state())
+}
+
+// Runs the network from where it is until it reaches a fixed point or a cycle, the fixed point is a cycle of period 1.
+// Only the synchronous update is checked for cycles, with the random order of the asynchronous one the same state
+// at the end of two sweeps doesn't mean the network is trapped, there a fixed point is all that's detected
+// Returns None if it's still going after max_sweeps, stochastic networks always run that long
+pub fn converge(net: &mut dyn Net<f64>, max_sweeps: usize) -> Option<Cycle> {
+    let steps_per_sweep = steps_per_sweep(net);
+    let max_steps_without_change = steps_per_sweep + 1;
+    let mut steps_without_change = 0;
+    let mut cycle_detector = CycleDetector::default();
+    let size = net.get_state_ref().len();
+
+    for step in 1..=max_sweeps * steps_per_sweep {
+        if net.step() {
+            steps_without_change = 0;
+        } else {
+            steps_without_change += 1;
+        }
+        if net.is_stochastic() {
+            continue;
+        }
+        if steps_without_change >= max_steps_without_change {
+            return Some(Cycle {
+                period: 1,
+                states: vec![net.get_state()],
+            });
+        }
+        if step % steps_per_sweep == 0 && net.get_update_mode() == UpdateMode::Synchronous {
+            if let Some(mut cycle) = cycle_detector.push(&net.get_dynamic_state()) {
+                // Only the part of the state that can be seen
+                for state in cycle.states.iter_mut() {
+                    state.truncate(size);
+                }
+                return Some(cycle);
+            }
+        }
+    }
+    None
+}
+
+// A sweep gives every node the chance to update once
+pub fn steps_per_sweep(net: &dyn Net<f64>) -> usize {
+    match net.get_update_mode() {
+        UpdateMode::Synchronous => 1,
+        UpdateMode::Asynchronous => net.get_state_ref().len(),
+    }
+}
+
+// Flips a random fraction of the nodes
+pub fn add_noise(pattern: &[f64], fraction: f64) -> Vec<f64> {
+    let mut noisy = pattern.to_vec();
+    let flips = (fraction.clamp(0.0, 1.0) * pattern.len() as f64).round() as usize;
+    for i in index::sample(&mut rand::thread_rng(), pattern.len(), flips) {
+        noisy[i] = -noisy[i];
+    }
+    noisy
+}
+
+// 1 if the states are the same, -1 if one is the negative of the other
+pub fn overlap(a: &[f64], b: &[f64]) -> f64 {
+    if a.is_empty() {
+        return 0.0;
+    }
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f64>() / a.len() as f64
+}
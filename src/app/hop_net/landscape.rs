@@ -0,0 +1,187 @@
+use crate::app::hop_net::matrix;
+use crate::app::hop_net::Net;
+use std::borrow::Cow;
+
+// The principal components are found with power iteration on the gram matrix of the states
+const PCA_ITERATIONS: usize = 200;
+
+// A plane in the space of the states, the point (a, b) of the plane is the state origin + a * axes[0] + b * axes[1].
+// The points of the plane aren't states of the network, their nodes can be anything, not just ±1
+#[derive(Debug, PartialEq, Clone)]
+pub struct Plane {
+    pub origin: Vec<f64>,
+    pub axes: [Vec<f64>; 2],
+}
+
+impl Plane {
+    // The plane of two stored patterns, the first pattern is (1, 0) and the second (0, 1)
+    pub fn from_patterns(first: &[f64], second: &[f64]) -> Plane {
+        Plane {
+            origin: vec![0.0; first.len()],
+            axes: [first.to_vec(), second.to_vec()],
+        }
+    }
+
+    // The plane through the mean of the states, that spans the two directions along which they change the most.
+    // Returns None if there are less than two states, or if they are all on the same line
+    pub fn from_pca(states: &[Vec<f64>]) -> Option<Plane> {
+        let size = states.first()?.len();
+        let count = states.len();
+        if count < 2 || states.iter().any(|state| state.len() != size) {
+            return None;
+        }
+
+        let mut origin = vec![0.0; size];
+        for state in states.iter() {
+            for (o, s) in origin.iter_mut().zip(state.iter()) {
+                *o += s / count as f64;
+            }
+        }
+        let centered: Vec<Vec<f64>> = states
+            .iter()
+            .map(|state| {
+                state
+                    .iter()
+                    .zip(origin.iter())
+                    .map(|(s, o)| s - o)
+                    .collect()
+            })
+            .collect();
+
+        // The gram matrix has the same nonzero eigenvalues as the covariance, but it's count x count instead
+        // of size x size, and there are way fewer states than nodes
+        let mut gram = vec![vec![0.0; count]; count];
+        for i in 0..count {
+            for j in i..count {
+                let product = matrix::dot(&centered[i], &centered[j]);
+                gram[i][j] = product;
+                gram[j][i] = product;
+            }
+        }
+
+        let mut axes = Vec::with_capacity(2);
+        for _ in 0..2 {
+            let (eigenvalue, eigenvector) = largest_eigenvector(&gram);
+            if eigenvalue <= f64::EPSILON {
+                return None;
+            }
+            // The principal component is the combination of the states given by the eigenvector
+            let mut axis = vec![0.0; size];
+            for (state, weight) in centered.iter().zip(eigenvector.iter()) {
+                matrix::add_scaled(&mut axis, *weight, state);
+            }
+            let norm = matrix::dot(&axis, &axis).sqrt();
+            axis.iter_mut().for_each(|x| *x /= norm);
+            axes.push(axis);
+
+            // The component that was just found is removed, so that the next iteration finds the second one
+            for i in 0..count {
+                for j in 0..count {
+                    gram[i][j] -= eigenvalue * eigenvector[i] * eigenvector[j];
+                }
+            }
+        }
+
+        let second = axes.pop()?;
+        let first = axes.pop()?;
+        Some(Plane {
+            origin,
+            axes: [first, second],
+        })
+    }
+}
+
+// The energy restricted to a plane, E = -1/2 sᵀWs - θᵀs is quadratic in the state, so on the plane
+// it's a quadratic polynomial in a and b, and can be drawn at any resolution without asking the network again
+#[derive(Debug, PartialEq, Clone)]
+pub struct Landscape {
+    pub plane: Plane,
+    // The coefficients of 1, a, b, a², b² and ab
+    coefficients: [f64; 6],
+    // The gram matrix of the axes, used to project the states on the plane
+    axes_gram: [[f64; 2]; 2],
+}
+
+impl Landscape {
+    pub fn energy(&self, a: f64, b: f64) -> f64 {
+        let c = &self.coefficients;
+        c[0] + c[1] * a + c[2] * b + c[3] * a * a + c[4] * b * b + c[5] * a * b
+    }
+
+    // The coordinates of the point of the plane closest to the state
+    pub fn project(&self, state: &[f64]) -> [f64; 2] {
+        let offset: Vec<f64> = state
+            .iter()
+            .zip(self.plane.origin.iter())
+            .map(|(s, o)| s - o)
+            .collect();
+        let r0 = matrix::dot(&self.plane.axes[0], &offset);
+        let r1 = matrix::dot(&self.plane.axes[1], &offset);
+        let [[g00, g01], [g10, g11]] = self.axes_gram;
+        let determinant = g00 * g11 - g01 * g10;
+
+        // Two patterns that are equal, or one the negation of the other, span only a line
+        if determinant.abs() <= f64::EPSILON * g00 * g11 {
+            return [if g00 > 0.0 { r0 / g00 } else { 0.0 }, 0.0];
+        }
+        [
+            (g11 * r0 - g01 * r1) / determinant,
+            (g00 * r1 - g10 * r0) / determinant,
+        ]
+    }
+}
+
+// Computes the energy of the network on the plane, it takes just three products between the weights and a vector
+pub fn compute_landscape(net: &dyn Net<f64>, plane: Plane) -> Landscape {
+    let weights = match net.get_recall_weights() {
+        Some(weights) => Cow::Borrowed(weights),
+        None => net.get_weights(),
+    };
+    let size = weights.rows();
+    // Whatever the network adds to the fields on top of the weights, like biases or thresholds
+    let thresholds = net.get_local_fields(&vec![0.0; size]);
+
+    let times_weights = |vector: &[f64]| {
+        let mut result = vec![0.0; size];
+        weights.mul_vec(vector, &mut result);
+        result
+    };
+    let origin = &plane.origin;
+    let [u, v] = &plane.axes;
+    let (w_origin, w_u, w_v) = (times_weights(origin), times_weights(u), times_weights(v));
+    let dot = |a: &[f64], b: &[f64]| matrix::dot(a, b);
+
+    let coefficients = [
+        -0.5 * dot(origin, &w_origin) - dot(&thresholds, origin),
+        -0.5 * (dot(u, &w_origin) + dot(origin, &w_u)) - dot(&thresholds, u),
+        -0.5 * (dot(v, &w_origin) + dot(origin, &w_v)) - dot(&thresholds, v),
+        -0.5 * dot(u, &w_u),
+        -0.5 * dot(v, &w_v),
+        -0.5 * (dot(u, &w_v) + dot(v, &w_u)),
+    ];
+    let axes_gram = [[dot(u, u), dot(u, v)], [dot(v, u), dot(v, v)]];
+
+    Landscape {
+        plane,
+        coefficients,
+        axes_gram,
+    }
+}
+
+// Power iteration, the gram matrix is symmetric and positive semidefinite so the largest eigenvalue is the dominant one
+fn largest_eigenvector(matrix: &[Vec<f64>]) -> (f64, Vec<f64>) {
+    let size = matrix.len();
+    // Any start works as long as it isn't orthogonal to the eigenvector, an uneven one makes that unlikely
+    let mut vector: Vec<f64> = (0..size).map(|i| 1.0 + i as f64 / size as f64).collect();
+    let mut eigenvalue = 0.0;
+    for _ in 0..PCA_ITERATIONS {
+        let next: Vec<f64> = matrix.iter().map(|row| matrix::dot(row, &vector)).collect();
+        let norm = matrix::dot(&next, &next).sqrt();
+        if norm <= f64::EPSILON {
+            return (0.0, vector);
+        }
+        eigenvalue = norm;
+        vector = next.into_iter().map(|x| x / norm).collect();
+    }
+    (eigenvalue, vector)
+}
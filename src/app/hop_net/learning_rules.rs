@@ -0,0 +1,309 @@
+use crate::app::hop_net::matrix;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::scalar::Precision;
+use crate::app::hop_net::scalar::Scalar;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use strum_macros::EnumIter;
+
+// The delta rule stops once the mean squared difference between the fields and the patterns is below this
+const DELTA_TOLERANCE: f64 = 1e-4;
+
+#[derive(EnumIter, Debug, PartialEq, Clone, Copy)]
+pub enum LearningRuleKind {
+    // Every pattern adds its outer product to the weights
+    Hebbian,
+    // The weights are the mean of the outer products instead of their sum, the network recalls
+    // exactly like the hebbian one, but the weights don't grow with the number of patterns
+    NormalizedHebbian,
+    // The old weights are multiplied by the decay before the new pattern is added, so the network
+    // forgets the oldest patterns instead of forgetting everything once it's over its capacity
+    Decaying,
+    // Trains on all the patterns until every node of every pattern is stable with at least the margin
+    Perceptron,
+    // Trains on all the patterns until every field is equal to its node
+    Delta,
+}
+
+impl Display for LearningRuleKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LearningRuleKind::Hebbian => write!(f, "Hebbian"),
+            LearningRuleKind::NormalizedHebbian => write!(f, "Normalized hebbian"),
+            LearningRuleKind::Decaying => write!(f, "Hebbian with decay"),
+            LearningRuleKind::Perceptron => write!(f, "Perceptron"),
+            LearningRuleKind::Delta => write!(f, "Delta"),
+        }
+    }
+}
+
+impl LearningRuleKind {
+    // The rules that go over the patterns many times, they report their progress after each epoch
+    pub fn is_iterative(&self) -> bool {
+        matches!(self, LearningRuleKind::Perceptron | LearningRuleKind::Delta)
+    }
+
+    // The rules that scale the weights make updates smaller than 1, integer weights round them to 0
+    // and the network would learn nothing
+    pub fn works_with(&self, precision: Precision) -> bool {
+        !precision.is_integer()
+            || matches!(
+                self,
+                LearningRuleKind::Hebbian | LearningRuleKind::Perceptron
+            )
+    }
+}
+
+// Only the parameters used by the kind matter, the others are kept so the gui remembers them
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LearningRule {
+    pub kind: LearningRuleKind,
+    // Perceptron: ξ_i h_i / |w_i| must be bigger than this for a node to be stable
+    pub margin: f64,
+    // Delta: how much of the error of a field is corrected at every update
+    pub rate: f64,
+    // Decaying: what the old weights are multiplied by, 1 is the plain hebbian rule
+    pub decay: f64,
+    // The iterative rules give up after this many passes over the patterns
+    pub max_epochs: usize,
+}
+
+impl Default for LearningRule {
+    fn default() -> Self {
+        Self {
+            kind: LearningRuleKind::Hebbian,
+            margin: 0.5,
+            rate: 0.5,
+            decay: 0.9,
+            max_epochs: 100,
+        }
+    }
+}
+
+// Called by the iterative rules after every epoch, the training stops early if it returns false
+pub type TrainingCallback = Box<dyn FnMut(TrainingProgress) -> bool + Send>;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TrainingProgress {
+    pub epoch: usize,
+    pub max_epochs: usize,
+    // Perceptron: the nodes that were updated during the epoch
+    // Delta: the mean squared difference between the fields and the patterns
+    pub error: f64,
+    // True for the last epoch, either because the patterns were learned, because max_epochs was reached
+    // or because the training was stopped
+    pub done: bool,
+}
+
+// The weights start from 0 and learn all the patterns, the one-shot rules add them one at a time,
+// and the iterative ones train once on all of them
+pub fn learn_all<T: Scalar>(
+    rule: LearningRule,
+    weights: &mut Matrix<T>,
+    patterns: &[Vec<T>],
+    progress: &mut dyn FnMut(TrainingProgress) -> bool,
+) {
+    match rule.kind {
+        LearningRuleKind::Perceptron | LearningRuleKind::Delta => {
+            learn(rule, weights, patterns, progress)
+        }
+        _ => {
+            for count in 1..=patterns.len() {
+                learn(rule, weights, &patterns[..count], progress);
+            }
+        }
+    }
+}
+
+// The patterns are all the ones the network has learned, the newest one last.
+// The one-shot rules only use the newest one, the iterative ones train on all of them.
+// The rules that scale the weights only make sense for float weights, see LearningRuleKind::works_with
+pub fn learn<T: Scalar>(
+    rule: LearningRule,
+    weights: &mut Matrix<T>,
+    patterns: &[Vec<T>],
+    progress: &mut dyn FnMut(TrainingProgress) -> bool,
+) {
+    let newest = match patterns.last() {
+        Some(pattern) => pattern,
+        None => return,
+    };
+    match rule.kind {
+        LearningRuleKind::Hebbian => hebbian(weights, newest),
+        LearningRuleKind::NormalizedHebbian => {
+            // w = (P - 1) / P * w + ξξᵀ / P keeps w equal to the mean of the P outer products.
+            // It holds only if the older patterns were learned with this rule too, the networks learn
+            // all their patterns again when their rule changes
+            let count = patterns.len() as f64;
+            decaying_hebbian(weights, newest, (count - 1.0) / count, 1.0 / count);
+        }
+        LearningRuleKind::Decaying => decaying_hebbian(weights, newest, rule.decay, 1.0),
+        LearningRuleKind::Perceptron => perceptron(weights, patterns, rule, progress),
+        LearningRuleKind::Delta => delta(weights, patterns, rule, progress),
+    }
+    weights.set_diagonal(T::default());
+}
+
+pub fn hebbian<T: Scalar>(weights: &mut Matrix<T>, pattern: &[T]) {
+    // Row i gets pattern[i] * pattern, it's the outer product of the pattern with itself
+    for (i, row) in weights.iter_rows_mut().enumerate() {
+        for (w, s_j) in row.iter_mut().zip(pattern.iter()) {
+            *w = w.saturating_add(pattern[i] * *s_j);
+        }
+    }
+}
+
+// w = decay * w + scale * ξξᵀ
+fn decaying_hebbian<T: Scalar>(weights: &mut Matrix<T>, pattern: &[T], decay: f64, scale: f64) {
+    for (i, row) in weights.iter_rows_mut().enumerate() {
+        let s_i = pattern[i].to_f64();
+        for (w, s_j) in row.iter_mut().zip(pattern.iter()) {
+            *w = T::from_f64(decay * w.to_f64() + scale * s_i * s_j.to_f64());
+        }
+    }
+}
+
+// The perceptron rule applied to every node: when a node isn't stable enough, its row moves
+// towards the pattern. The rows are trained independently, so the weights end up asymmetric.
+// The stability is divided by the norm of the row, so that the margin doesn't depend on the scale of the weights
+fn perceptron<T: Scalar>(
+    weights: &mut Matrix<T>,
+    patterns: &[Vec<T>],
+    rule: LearningRule,
+    progress: &mut dyn FnMut(TrainingProgress) -> bool,
+) {
+    for epoch in 1..=rule.max_epochs {
+        let mut updates = 0;
+        for pattern in patterns.iter() {
+            for (i, row) in weights.iter_rows_mut().enumerate() {
+                let field = T::acc_to_f64(matrix::dot(row, pattern));
+                let norm = row
+                    .iter()
+                    .map(|w| w.to_f64() * w.to_f64())
+                    .sum::<f64>()
+                    .sqrt();
+                if pattern[i].to_f64() * field <= rule.margin * norm {
+                    updates += 1;
+                    for (j, (w, s_j)) in row.iter_mut().zip(pattern.iter()).enumerate() {
+                        if j != i {
+                            *w = w.saturating_add(pattern[i] * *s_j);
+                        }
+                    }
+                }
+            }
+        }
+
+        let done = updates == 0 || epoch == rule.max_epochs;
+        let keep_going = progress(TrainingProgress {
+            epoch,
+            max_epochs: rule.max_epochs,
+            error: updates as f64,
+            done,
+        });
+        if done || !keep_going {
+            return;
+        }
+    }
+}
+
+// The Widrow-Hoff rule: every field is moved towards its node by rate times the difference.
+// Once it converges the fields of the patterns are the patterns themselves, like with the projection rule
+fn delta<T: Scalar>(
+    weights: &mut Matrix<T>,
+    patterns: &[Vec<T>],
+    rule: LearningRule,
+    progress: &mut dyn FnMut(TrainingProgress) -> bool,
+) {
+    let size = weights.cols() as f64;
+    let node_count = (patterns.len() * weights.rows()) as f64;
+    for epoch in 1..=rule.max_epochs {
+        let mut squared_error = 0.0;
+        for pattern in patterns.iter() {
+            for (i, row) in weights.iter_rows_mut().enumerate() {
+                let error = pattern[i].to_f64() - T::acc_to_f64(matrix::dot(row, pattern));
+                squared_error += error * error;
+                let step = rule.rate * error / size;
+                for (j, (w, s_j)) in row.iter_mut().zip(pattern.iter()).enumerate() {
+                    if j != i {
+                        *w = T::from_f64(w.to_f64() + step * s_j.to_f64());
+                    }
+                }
+            }
+        }
+
+        let error = squared_error / node_count;
+        let done = error < DELTA_TOLERANCE || epoch == rule.max_epochs;
+        let keep_going = progress(TrainingProgress {
+            epoch,
+            max_epochs: rule.max_epochs,
+            error,
+            done,
+        });
+        if done || !keep_going {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    const PATTERNS: [[f64; 8]; 3] = [
+        [1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0],
+        [-1.0, -1.0, 1.0, -1.0, 1.0, 1.0, 1.0, -1.0],
+        [1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0],
+    ];
+
+    fn patterns() -> Vec<Vec<f64>> {
+        PATTERNS.iter().map(|pattern| pattern.to_vec()).collect()
+    }
+
+    fn train(kind: LearningRuleKind, patterns: &[Vec<f64>]) -> Matrix<f64> {
+        let rule = LearningRule {
+            kind,
+            ..LearningRule::default()
+        };
+        let mut weights = Matrix::square(patterns[0].len());
+        learn_all(rule, &mut weights, patterns, &mut |_| true);
+        weights
+    }
+
+    #[test]
+    fn normalized_hebbian_is_the_mean_of_the_hebbian_weights() {
+        let patterns = patterns();
+        let hebbian = train(LearningRuleKind::Hebbian, &patterns);
+        let normalized = train(LearningRuleKind::NormalizedHebbian, &patterns);
+        let count = patterns.len() as f64;
+        for (w, mean) in hebbian.as_slice().iter().zip(normalized.as_slice()) {
+            assert!((w / count - mean).abs() < 1e-12);
+        }
+    }
+
+    // The iterative rules stop once every node of every pattern agrees with its field
+    #[test]
+    fn iterative_rules_make_the_patterns_stable() {
+        let patterns = patterns();
+        for kind in [LearningRuleKind::Perceptron, LearningRuleKind::Delta] {
+            let weights = train(kind, &patterns);
+            for pattern in patterns.iter() {
+                for (i, row) in weights.iter_rows().enumerate() {
+                    assert!(pattern[i] * matrix::dot(row, pattern) > 0.0, "{}", kind);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integer_weights_only_take_the_rules_without_scaling() {
+        let works_with_i8: Vec<LearningRuleKind> = LearningRuleKind::iter()
+            .filter(|kind| kind.works_with(Precision::I8))
+            .collect();
+        assert_eq!(
+            works_with_i8,
+            vec![LearningRuleKind::Hebbian, LearningRuleKind::Perceptron]
+        );
+        assert!(LearningRuleKind::iter().all(|kind| kind.works_with(Precision::F32)));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::app::hop_net::matrix;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::scalar::Scalar;
+
+// The local field of every node, h = W * s, kept up to date one change at a time.
+// A node that changes by delta moves every field by delta times its column of the weights, so a step
+// that changes nothing costs O(1) and one that changes a node costs O(N), instead of O(N) and O(N²)
+pub struct LocalFields<T: Scalar = f64> {
+    fields: Vec<T::Acc>,
+    // If the weights are symmetric a column is the same as its row, which can be read contiguously.
+    // Checking takes as long as computing the fields, so it's done again only after the weights change
+    symmetric: Option<bool>,
+}
+
+impl<T: Scalar> LocalFields<T> {
+    pub fn new(size: usize) -> LocalFields<T> {
+        LocalFields {
+            fields: vec![T::Acc::default(); size],
+            symmetric: None,
+        }
+    }
+
+    // Has to be called every time the state changes in any way other than trough node_changed
+    pub fn recompute(&mut self, weights: &Matrix<T>, state: &[T]) {
+        self.fields.resize(weights.rows(), T::Acc::default());
+        weights.mul_vec(state, &mut self.fields);
+        if self.symmetric.is_none() {
+            self.symmetric = Some(weights.is_symmetric());
+        }
+    }
+
+    // Has to be called instead of recompute every time the weights change
+    pub fn weights_changed(&mut self, weights: &Matrix<T>, state: &[T]) {
+        self.symmetric = None;
+        self.recompute(weights, state);
+    }
+
+    // Node i went from old_value to new_value
+    pub fn node_changed(&mut self, weights: &Matrix<T>, i: usize, old_value: T, new_value: T) {
+        let delta = new_value.to_acc() - old_value.to_acc();
+        if self.symmetric == Some(true) {
+            matrix::add_scaled(&mut self.fields, delta, &weights[i]);
+        } else {
+            // The column is read a node at a time, slower than a row but it needs no transposed copy
+            for (field, row) in self.fields.iter_mut().zip(weights.iter_rows()) {
+                *field += delta * row[i].to_acc();
+            }
+        }
+    }
+
+    pub fn get(&self, i: usize) -> T::Acc {
+        self.fields[i]
+    }
+
+    pub fn as_slice(&self) -> &[T::Acc] {
+        &self.fields
+    }
+}
+
+// The standard update rule, the node takes the sign of its field
+pub fn update_node<T: Scalar>(
+    weights: &Matrix<T>,
+    fields: &mut LocalFields<T>,
+    state: &mut [T],
+    i: usize,
+) -> bool {
+    let new_val = sign::<T>(fields.get(i));
+    if new_val != state[i] {
+        fields.node_changed(weights, i, state[i], new_val);
+        state[i] = new_val;
+        return true;
+    }
+    false
+}
+
+// Every node takes the sign of its field at the same time, the fields are then updated only for the nodes that changed
+pub fn synchronous_update<T: Scalar>(
+    weights: &Matrix<T>,
+    fields: &mut LocalFields<T>,
+    state: &mut [T],
+) -> bool {
+    // All the new values are decided before any field moves
+    let changed_nodes: Vec<(usize, T)> = (0..state.len())
+        .map(|i| (i, sign::<T>(fields.get(i))))
+        .filter(|&(i, new_val)| new_val != state[i])
+        .collect();
+
+    for &(i, new_val) in changed_nodes.iter() {
+        fields.node_changed(weights, i, state[i], new_val);
+        state[i] = new_val;
+    }
+    !changed_nodes.is_empty()
+}
+
+fn sign<T: Scalar>(field: T::Acc) -> T {
+    if field > T::Acc::default() {
+        T::ONE
+    } else {
+        T::MINUS_ONE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn random_weights<T: Scalar>(size: usize, symmetric: bool) -> Matrix<T> {
+        let mut rng = rand::thread_rng();
+        let mut weights = Matrix::square(size);
+        for i in 0..size {
+            for j in 0..size {
+                if symmetric && j < i {
+                    weights[i][j] = weights[j][i];
+                } else if i != j {
+                    weights[i][j] = T::from_f64(rng.gen_range(-5..=5) as f64);
+                }
+            }
+        }
+        weights
+    }
+
+    fn random_state<T: Scalar>(size: usize) -> Vec<T> {
+        let mut rng = rand::thread_rng();
+        (0..size)
+            .map(|_| {
+                if rng.gen_bool(0.5) {
+                    T::ONE
+                } else {
+                    T::MINUS_ONE
+                }
+            })
+            .collect()
+    }
+
+    fn assert_matches_recompute<T: Scalar>(
+        weights: &Matrix<T>,
+        fields: &LocalFields<T>,
+        state: &[T],
+    ) {
+        let mut expected = LocalFields::<T>::new(weights.rows());
+        expected.recompute(weights, state);
+        for (field, expected) in fields.as_slice().iter().zip(expected.as_slice()) {
+            assert!((T::acc_to_f64(*field) - T::acc_to_f64(*expected)).abs() < 1e-9);
+        }
+    }
+
+    // Flips random nodes, and lets others take the sign of their field, checking the fields after every change
+    fn follow_random_updates<T: Scalar>(symmetric: bool) {
+        let size = 12;
+        let weights = random_weights::<T>(size, symmetric);
+        let mut state = random_state::<T>(size);
+        let mut fields = LocalFields::new(size);
+        fields.recompute(&weights, &state);
+        assert_eq!(fields.symmetric, Some(symmetric));
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let i = rng.gen_range(0..size);
+            if rng.gen_bool(0.5) {
+                let new_val = if state[i] == T::ONE {
+                    T::MINUS_ONE
+                } else {
+                    T::ONE
+                };
+                fields.node_changed(&weights, i, state[i], new_val);
+                state[i] = new_val;
+            } else {
+                update_node(&weights, &mut fields, &mut state, i);
+            }
+            assert_matches_recompute(&weights, &fields, &state);
+        }
+
+        synchronous_update(&weights, &mut fields, &mut state);
+        assert_matches_recompute(&weights, &fields, &state);
+    }
+
+    #[test]
+    fn fields_follow_updates_with_symmetric_weights() {
+        follow_random_updates::<f64>(true);
+        follow_random_updates::<f32>(true);
+        follow_random_updates::<i8>(true);
+    }
+
+    #[test]
+    fn fields_follow_updates_with_asymmetric_weights() {
+        follow_random_updates::<f64>(false);
+        follow_random_updates::<i16>(false);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::app::hop_net::scalar::Scalar;
+use std::ops::Index;
+use std::ops::IndexMut;
+
+// A dense matrix stored row after row in a single allocation, so that walking a row touches
+// contiguous memory. Indexing it with a row number gives back the row as a slice, so
+// weights[i][j] works just like it did with the nested vectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix<T> {
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+}
+
+impl<T: Copy + Default> Matrix<T> {
+    pub fn zeros(rows: usize, cols: usize) -> Matrix<T> {
+        Matrix {
+            rows,
+            cols,
+            data: vec![T::default(); rows * cols],
+        }
+    }
+
+    pub fn square(size: usize) -> Matrix<T> {
+        Matrix::zeros(size, size)
+    }
+
+    // Panics if the rows don't all have the same length
+    pub fn from_rows(rows: &[Vec<T>]) -> Matrix<T> {
+        let cols = rows.first().map_or(0, |row| row.len());
+        let mut data = Vec::with_capacity(rows.len() * cols);
+        for row in rows {
+            if row.len() != cols {
+                panic!("The rows of a matrix must have the same length");
+            }
+            data.extend_from_slice(row);
+        }
+        Matrix {
+            rows: rows.len(),
+            cols,
+            data,
+        }
+    }
+
+    pub fn to_rows(&self) -> Vec<Vec<T>> {
+        self.iter_rows().map(|row| row.to_vec()).collect()
+    }
+
+    pub fn map<U>(&self, f: impl Fn(T) -> U) -> Matrix<U> {
+        Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(|value| f(*value)).collect(),
+        }
+    }
+
+    pub fn transposed(&self) -> Matrix<T> {
+        let mut result = Matrix::zeros(self.cols, self.rows);
+        for (i, row) in self.iter_rows().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                result.data[j * self.rows + i] = *value;
+            }
+        }
+        result
+    }
+
+    pub fn fill(&mut self, value: T) {
+        self.data.fill(value);
+    }
+
+    pub fn set_diagonal(&mut self, value: T) {
+        for i in 0..self.rows.min(self.cols) {
+            self.data[i * self.cols + i] = value;
+        }
+    }
+}
+
+impl<T> Matrix<T> {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+
+    pub fn iter_rows(&self) -> std::slice::ChunksExact<'_, T> {
+        self.data.chunks_exact(self.cols.max(1))
+    }
+
+    pub fn iter_rows_mut(&mut self) -> std::slice::ChunksExactMut<'_, T> {
+        self.data.chunks_exact_mut(self.cols.max(1))
+    }
+}
+
+impl<T: PartialEq> Matrix<T> {
+    pub fn is_symmetric(&self) -> bool {
+        if self.rows != self.cols {
+            return false;
+        }
+        (0..self.rows).all(|i| {
+            (i + 1..self.cols).all(|j| self.data[i * self.cols + j] == self.data[j * self.cols + i])
+        })
+    }
+}
+
+impl<T: Scalar> Matrix<T> {
+    // result = self * vector
+    pub fn mul_vec(&self, vector: &[T], result: &mut [T::Acc]) {
+        for (out, row) in result.iter_mut().zip(self.iter_rows()) {
+            *out = dot(row, vector);
+        }
+    }
+}
+
+impl<T> Index<usize> for Matrix<T> {
+    type Output = [T];
+
+    fn index(&self, row: usize) -> &[T] {
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+}
+
+impl<T> IndexMut<usize> for Matrix<T> {
+    fn index_mut(&mut self, row: usize) -> &mut [T] {
+        &mut self.data[row * self.cols..(row + 1) * self.cols]
+    }
+}
+
+// ---------------------------------Kernels---------------------------------
+// The loops are split in independent lanes, so that the additions don't have to wait for each
+// other and the compiler is free to turn them into simd instructions
+
+const LANES: usize = 8;
+
+pub fn dot<T: Scalar>(a: &[T], b: &[T]) -> T::Acc {
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+    let mut sums = [T::Acc::default(); LANES];
+
+    let mut a_chunks = a.chunks_exact(LANES);
+    let mut b_chunks = b.chunks_exact(LANES);
+    for (a_chunk, b_chunk) in (&mut a_chunks).zip(&mut b_chunks) {
+        for lane in 0..LANES {
+            sums[lane] += a_chunk[lane].to_acc() * b_chunk[lane].to_acc();
+        }
+    }
+
+    let mut sum = T::Acc::default();
+    for lane_sum in sums {
+        sum += lane_sum;
+    }
+    for (x, y) in a_chunks.remainder().iter().zip(b_chunks.remainder()) {
+        sum += x.to_acc() * y.to_acc();
+    }
+    sum
+}
+
+// y += scale * x
+pub fn add_scaled<T: Scalar>(y: &mut [T::Acc], scale: T::Acc, x: &[T]) {
+    let len = y.len().min(x.len());
+    let (y, x) = (&mut y[..len], &x[..len]);
+
+    let mut y_chunks = y.chunks_exact_mut(LANES);
+    let mut x_chunks = x.chunks_exact(LANES);
+    for (y_chunk, x_chunk) in (&mut y_chunks).zip(&mut x_chunks) {
+        for lane in 0..LANES {
+            y_chunk[lane] += scale * x_chunk[lane].to_acc();
+        }
+    }
+
+    for (a, b) in y_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(x_chunks.remainder())
+    {
+        *a += scale * b.to_acc();
+    }
+}
@@ -0,0 +1,108 @@
+use crate::app::hop_net::learning_rules::LearningRuleKind;
+use crate::app::hop_net::scalar::Precision;
+use std::any::Any;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+// What went wrong in the network thread, or between it and the gui. None of them stops the app,
+// the network either refuses the command or starts again from where it last was fine
+#[derive(Debug, PartialEq, Clone)]
+pub enum NetworkError {
+    // The command was ignored, the network can't work with so few nodes
+    StateTooShort {
+        len: usize,
+        min: usize,
+    },
+    // The state doesn't fit in the layers of the network, the command was ignored
+    StateSizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+    // The network has a different number of layers, the command was ignored
+    LayerCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    // The network panicked, it went back to the last weights and state it had before the command
+    Crashed {
+        during: String,
+        message: String,
+    },
+    // The network thread is gone, a new one was started and it learned the stored patterns again
+    ThreadRestarted,
+    // One of the threads of a batch recall panicked, the results of the others were thrown away
+    RecallCrashed(String),
+    // The network has no fixed points to look for, the message says why
+    NotEnumerable(&'static str),
+    // There are no weights from before a damage to go back to
+    NotDamaged,
+    // The weights of the optimization network come from its problem, the pattern was ignored
+    NotLearnable,
+    // The network can't dream without breaking its weights, the message says why
+    NotUnlearnable(&'static str),
+    // The rule would round its updates to 0 with these weights, the network kept its rule and its weights
+    RuleNeedsFloats {
+        rule: LearningRuleKind,
+        precision: Precision,
+    },
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkError::StateTooShort { len, min } => write!(
+                f,
+                "A state of {} nodes is too short, the network needs at least {}",
+                len, min
+            ),
+            NetworkError::StateSizeMismatch { expected, found } => write!(
+                f,
+                "A state of {} nodes doesn't fit in the layers of the network, they have {}",
+                found, expected
+            ),
+            NetworkError::LayerCountMismatch { expected, found } => write!(
+                f,
+                "The network has {} layers, {} sizes were given",
+                expected, found
+            ),
+            NetworkError::Crashed { during, message } => write!(
+                f,
+                "The network crashed during {} ({}), it went back to its last good weights",
+                during, message
+            ),
+            NetworkError::ThreadRestarted => write!(
+                f,
+                "The network stopped responding, it was started again and it learned the stored patterns again"
+            ),
+            NetworkError::NotEnumerable(reason) => write!(f, "{}", reason),
+            NetworkError::NotDamaged => write!(
+                f,
+                "The weights haven't been damaged since they last changed, there is nothing to restore"
+            ),
+            NetworkError::NotLearnable => write!(
+                f,
+                "The weights of the optimization network come from its problem, it can't learn patterns"
+            ),
+            NetworkError::NotUnlearnable(reason) => write!(f, "{}", reason),
+            NetworkError::RuleNeedsFloats { rule, precision } => write!(
+                f,
+                "The {} rule can't learn with {} weights, its updates round to 0",
+                rule, precision
+            ),
+            NetworkError::RecallCrashed(message) => {
+                write!(f, "A recall thread crashed ({}), no probe was recalled", message)
+            }
+        }
+    }
+}
+
+// What a panic was about, for the errors that replace it
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown reason".to_string()
+    }
+}
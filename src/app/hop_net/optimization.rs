@@ -0,0 +1,355 @@
+// Hopfield-Tank style encoding of combinatorial problems.
+// Every problem is written as the energy of a network of 0/1 neurons V:
+//      E(V) = -1/2 * Vᵀ W V - Iᵀ V
+// where the constraints are penalties, and the cost to minimize is added on top of them.
+// Since the networks work with -1/+1 nodes, the weights are then rewritten for s = 2V - 1.
+
+// The neurons are both indices of the matrices and cells of a grid, iterators would only hide that
+#![allow(clippy::needless_range_loop)]
+use rand::Rng;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use strum_macros::EnumIter;
+
+#[derive(EnumIter, Debug, PartialEq, Clone, Copy)]
+pub enum ProblemKind {
+    TravelingSalesman,
+    MaxCut,
+    NRooks,
+    NQueens,
+}
+
+impl Display for ProblemKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProblemKind::TravelingSalesman => write!(f, "Traveling salesman"),
+            ProblemKind::MaxCut => write!(f, "Max cut"),
+            ProblemKind::NRooks => write!(f, "N rooks"),
+            ProblemKind::NQueens => write!(f, "N queens"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    // Neuron x * n + i is on when city x is visited as the i-th stop, so every row of the grid is a city
+    // and every column is a position in the tour
+    TravelingSalesman {
+        cities: Vec<(f64, f64)>,
+        distances: Vec<Vec<f64>>,
+    },
+    // One neuron per vertex, its sign tells on which side of the cut the vertex is.
+    // The number of vertices is always a square, so that they fit in the grid
+    MaxCut {
+        edges: Vec<Vec<f64>>,
+    },
+    // Neuron r * n + c is on when there is a piece in row r and column c
+    NRooks {
+        n: usize,
+    },
+    NQueens {
+        n: usize,
+    },
+}
+
+// What the state of the network means for the problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub valid: bool,
+    // The quantity the problem cares about, lower is better for everything but max cut
+    pub cost: f64,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolverConfig {
+    pub problem: Problem,
+    // With a temperature greater than 0 the nodes are updated stochastically, and the network can escape local minima
+    pub start_temperature: f64,
+    // The temperature is multiplied by this after every sweep
+    pub cooling: f64,
+}
+
+// How much breaking a constraint costs, relative to the biggest term of the cost function
+const PENALTY_SCALE: f64 = 3.0;
+
+impl Problem {
+    // Random instances, n is the side of the grid that will show the neurons
+    pub fn random(kind: ProblemKind, n: usize) -> Problem {
+        let mut rng = rand::thread_rng();
+        match kind {
+            ProblemKind::TravelingSalesman => {
+                let cities: Vec<(f64, f64)> = (0..n)
+                    .map(|_| (rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)))
+                    .collect();
+                let distances = cities
+                    .iter()
+                    .map(|a| {
+                        cities
+                            .iter()
+                            .map(|b| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt())
+                            .collect()
+                    })
+                    .collect();
+                Problem::TravelingSalesman { cities, distances }
+            }
+            ProblemKind::MaxCut => {
+                let vertices = n * n;
+                let mut edges = vec![vec![0.0; vertices]; vertices];
+                for i in 0..vertices {
+                    for j in (i + 1)..vertices {
+                        if rng.gen_bool(0.3) {
+                            edges[i][j] = 1.0;
+                            edges[j][i] = 1.0;
+                        }
+                    }
+                }
+                Problem::MaxCut { edges }
+            }
+            ProblemKind::NRooks => Problem::NRooks { n },
+            ProblemKind::NQueens => Problem::NQueens { n },
+        }
+    }
+
+    pub fn kind(&self) -> ProblemKind {
+        match self {
+            Problem::TravelingSalesman { .. } => ProblemKind::TravelingSalesman,
+            Problem::MaxCut { .. } => ProblemKind::MaxCut,
+            Problem::NRooks { .. } => ProblemKind::NRooks,
+            Problem::NQueens { .. } => ProblemKind::NQueens,
+        }
+    }
+
+    // The side of the square grid of neurons
+    pub fn side(&self) -> usize {
+        match self {
+            Problem::TravelingSalesman { cities, .. } => cities.len(),
+            Problem::MaxCut { edges } => (edges.len() as f64).sqrt().round() as usize,
+            Problem::NRooks { n } => *n,
+            Problem::NQueens { n } => *n,
+        }
+    }
+
+    pub fn neuron_count(&self) -> usize {
+        match self {
+            Problem::MaxCut { edges } => edges.len(),
+            _ => self.side().pow(2),
+        }
+    }
+
+    // Returns the weights and the thresholds of a network of -1/+1 nodes whose energy minima are the solutions
+    pub fn build(&self) -> (Vec<Vec<f64>>, Vec<f64>) {
+        let size = self.neuron_count();
+        match self {
+            Problem::TravelingSalesman { distances, .. } => {
+                let n = self.side();
+                let max_distance = distances
+                    .iter()
+                    .flatten()
+                    .fold(0.0_f64, |max, d| max.max(*d));
+                let penalty = PENALTY_SCALE * max_distance.max(f64::EPSILON);
+
+                let mut weights = vec![vec![0.0; size]; size];
+                let mut thresholds = vec![0.0; size];
+                add_one_per_row_and_column(n, penalty, &mut weights, &mut thresholds);
+
+                // Going from city x at stop i to city y at stop i + 1 costs their distance
+                for x in 0..n {
+                    for y in 0..n {
+                        if x == y {
+                            continue;
+                        }
+                        for i in 0..n {
+                            let from = x * n + i;
+                            let to = y * n + (i + 1) % n;
+                            weights[from][to] -= distances[x][y];
+                            weights[to][from] -= distances[x][y];
+                        }
+                    }
+                }
+                qubo_to_spin(weights, thresholds)
+            }
+            Problem::MaxCut { edges } => {
+                // The cut is sum(w_ij * (1 - s_i * s_j) / 2), so the spins want to be different from their neighbours
+                let weights = edges
+                    .iter()
+                    .map(|row| row.iter().map(|w| -w).collect())
+                    .collect();
+                (weights, vec![0.0; size])
+            }
+            Problem::NRooks { n } => {
+                let mut weights = vec![vec![0.0; size]; size];
+                let mut thresholds = vec![0.0; size];
+                add_one_per_row_and_column(*n, PENALTY_SCALE, &mut weights, &mut thresholds);
+                qubo_to_spin(weights, thresholds)
+            }
+            Problem::NQueens { n } => {
+                let n = *n;
+                let mut weights = vec![vec![0.0; size]; size];
+                let mut thresholds = vec![0.0; size];
+                add_one_per_row_and_column(n, PENALTY_SCALE, &mut weights, &mut thresholds);
+
+                // At most one queen per diagonal, so only the pairs are penalized
+                for a in 0..size {
+                    for b in 0..size {
+                        let (ra, ca) = ((a / n) as i64, (a % n) as i64);
+                        let (rb, cb) = ((b / n) as i64, (b % n) as i64);
+                        if a != b && (ra - rb).abs() == (ca - cb).abs() {
+                            weights[a][b] -= PENALTY_SCALE;
+                        }
+                    }
+                }
+                qubo_to_spin(weights, thresholds)
+            }
+        }
+    }
+
+    // The cities in the order they are visited, if the state is a valid tour
+    pub fn decode_tour(&self, state: &[f64]) -> Option<Vec<usize>> {
+        if let Problem::TravelingSalesman { .. } = self {
+            let n = self.side();
+            let on: Vec<bool> = state.iter().map(|s| *s > 0.0).collect();
+            if state.len() != n * n || count_row_and_column_violations(n, &on) > 0 {
+                return None;
+            }
+            return Some(
+                (0..n)
+                    .map(|i| (0..n).find(|x| on[x * n + i]).unwrap())
+                    .collect(),
+            );
+        }
+        None
+    }
+
+    // Decodes the state of the network into a solution of the problem
+    pub fn evaluate(&self, state: &[f64]) -> Solution {
+        let on: Vec<bool> = state.iter().map(|s| *s > 0.0).collect();
+        match self {
+            Problem::TravelingSalesman { distances, .. } => {
+                let n = self.side();
+                let tour = match self.decode_tour(state) {
+                    Some(tour) => tour,
+                    None => {
+                        return Solution {
+                            valid: false,
+                            cost: f64::INFINITY,
+                            description: format!(
+                                "Not a tour: {} rows or columns don't have exactly one city",
+                                count_row_and_column_violations(n, &on)
+                            ),
+                        }
+                    }
+                };
+
+                let length: f64 = (0..n).map(|i| distances[tour[i]][tour[(i + 1) % n]]).sum();
+                let stops: Vec<String> = tour.iter().map(|city| city.to_string()).collect();
+                Solution {
+                    valid: true,
+                    cost: length,
+                    description: format!("Tour {}, length {:.3}", stops.join(" → "), length),
+                }
+            }
+            Problem::MaxCut { edges } => {
+                let mut cut = 0.0;
+                let mut total = 0.0;
+                for i in 0..edges.len() {
+                    for j in (i + 1)..edges.len() {
+                        total += edges[i][j];
+                        if on[i] != on[j] {
+                            cut += edges[i][j];
+                        }
+                    }
+                }
+                Solution {
+                    valid: true,
+                    cost: cut,
+                    description: format!("Cut weight {} of {}", cut, total),
+                }
+            }
+            Problem::NRooks { n } => {
+                let violations = count_row_and_column_violations(*n, &on);
+                Solution {
+                    valid: violations == 0,
+                    cost: violations as f64,
+                    description: format!(
+                        "{} rows or columns don't have exactly one rook",
+                        violations
+                    ),
+                }
+            }
+            Problem::NQueens { n } => {
+                let n = *n;
+                let violations = count_row_and_column_violations(n, &on);
+                let queens: Vec<(i64, i64)> = (0..n * n)
+                    .filter(|i| on[*i])
+                    .map(|i| ((i / n) as i64, (i % n) as i64))
+                    .collect();
+                let mut attacks = 0;
+                for a in 0..queens.len() {
+                    for b in (a + 1)..queens.len() {
+                        let (ra, ca) = queens[a];
+                        let (rb, cb) = queens[b];
+                        if (ra - rb).abs() == (ca - cb).abs() {
+                            attacks += 1;
+                        }
+                    }
+                }
+                Solution {
+                    valid: violations == 0 && attacks == 0,
+                    cost: (violations + attacks) as f64,
+                    description: format!(
+                        "{} rows or columns without exactly one queen, {} pairs on the same diagonal",
+                        violations, attacks
+                    ),
+                }
+            }
+        }
+    }
+}
+
+// Adds the penalty for (sum(V) - 1)² on every row and every column of a n*n grid of neurons.
+// Expanding the square for 0/1 neurons, each pair of neurons on the same line costs 2 * penalty,
+// and each single neuron gains penalty
+fn add_one_per_row_and_column(
+    n: usize,
+    penalty: f64,
+    weights: &mut [Vec<f64>],
+    thresholds: &mut [f64],
+) {
+    for a in 0..n * n {
+        let (ra, ca) = (a / n, a % n);
+        for b in 0..n * n {
+            let (rb, cb) = (b / n, b % n);
+            if a != b && (ra == rb || ca == cb) {
+                weights[a][b] -= 2.0 * penalty;
+            }
+        }
+        // One term for the row and one for the column
+        thresholds[a] += 2.0 * penalty;
+    }
+}
+
+fn count_row_and_column_violations(n: usize, on: &[bool]) -> usize {
+    let rows = (0..n)
+        .filter(|r| (0..n).filter(|c| on[r * n + c]).count() != 1)
+        .count();
+    let columns = (0..n)
+        .filter(|c| (0..n).filter(|r| on[r * n + c]).count() != 1)
+        .count();
+    rows + columns
+}
+
+// Rewrites E(V) = -1/2 * Vᵀ W V - Iᵀ V for V = (s + 1) / 2, dropping the constant:
+// E(s) = -1/2 * sᵀ (W / 4) s - (W * 1 / 4 + I / 2)ᵀ s
+fn qubo_to_spin(weights: Vec<Vec<f64>>, thresholds: Vec<f64>) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let spin_thresholds = weights
+        .iter()
+        .zip(thresholds.iter())
+        .map(|(row, threshold)| row.iter().sum::<f64>() / 4.0 + threshold / 2.0)
+        .collect();
+    let spin_weights = weights
+        .into_iter()
+        .map(|row| row.into_iter().map(|w| w / 4.0).collect())
+        .collect();
+    (spin_weights, spin_thresholds)
+}
@@ -0,0 +1,199 @@
+use crate::app::hop_net;
+use crate::app::hop_net::local_fields::LocalFields;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error::NetworkError;
+use crate::app::hop_net::optimization::SolverConfig;
+use rand::Rng;
+use std::borrow::Cow;
+
+// Below this temperature the stochastic updates are indistinguishable from the deterministic ones
+const FREEZING_TEMPERATURE: f64 = 1e-3;
+
+// A network whose weights and thresholds come from a problem, instead of from the stored patterns.
+// At temperature 0 it follows the usual update rule, above 0 a node is set to +1 with probability
+// 1 / (1 + exp(-2h / T)), and the temperature slowly goes down after every sweep (simulated annealing)
+pub struct OptimizationNetwork {
+    pub state: Vec<f64>,
+    pub rng: rand::rngs::ThreadRng,
+    weights: Matrix<f64>,
+    thresholds: Vec<f64>,
+    // Only the part of the field that comes from the other nodes, the thresholds are added on top
+    fields: LocalFields,
+    start_temperature: f64,
+    temperature: f64,
+    cooling: f64,
+    steps: usize,
+    nodes_yet_to_update: Vec<usize>,
+    last_update: Option<(usize, f64)>,
+    update_mode: hop_net::UpdateMode,
+}
+
+impl hop_net::Net<f64> for OptimizationNetwork {
+    fn get_state_ref(&self) -> &[f64] {
+        &self.state
+    }
+
+    // The weights come from the problem, the patterns are refused before they get here
+    fn learn(&mut self, _state: &[f64]) {}
+
+    fn step(&mut self) -> bool {
+        self.last_update = None;
+        self.steps += 1;
+        match self.update_mode {
+            hop_net::UpdateMode::Synchronous => {
+                let fields: Vec<f64> = (0..self.state.len()).map(|i| self.field(i)).collect();
+                let mut changed = false;
+                for (i, h) in fields.into_iter().enumerate() {
+                    changed |= self.set_node(i, h);
+                }
+                self.cool_down();
+                changed
+            }
+            hop_net::UpdateMode::Asynchronous => {
+                if self.nodes_yet_to_update.is_empty() {
+                    hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+                }
+                let i = self.nodes_yet_to_update.pop().unwrap();
+                let h = self.field(i);
+                self.last_update = Some((i, h));
+                let changed = self.set_node(i, h);
+                if self.nodes_yet_to_update.is_empty() {
+                    self.cool_down();
+                }
+                changed
+            }
+        }
+    }
+
+    fn get_steps(&self) -> usize {
+        self.steps
+    }
+
+    fn get_last_update(&self) -> Option<(usize, f64)> {
+        self.last_update
+    }
+
+    // The size of an optimization network is fixed by its problem
+    fn set_state(&mut self, state: &[f64]) -> Result<(), NetworkError> {
+        if state.len() != self.state.len() {
+            return Err(NetworkError::StateSizeMismatch {
+                expected: self.state.len(),
+                found: state.len(),
+            });
+        }
+        self.state = state.to_vec();
+        self.fields.recompute(&self.weights, &self.state);
+        self.steps = 0;
+
+        // Every new starting state gets a new annealing run
+        self.temperature = self.start_temperature;
+        hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+        Ok(())
+    }
+
+    fn reset_weights(&mut self) {
+        self.weights = Matrix::square(self.state.len());
+        self.thresholds = vec![0.0; self.state.len()];
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn get_weights(&self) -> Cow<'_, Matrix<f64>> {
+        Cow::Borrowed(&self.weights)
+    }
+
+    fn modify_weights(&mut self, modify: &mut dyn FnMut(&mut Matrix<f64>)) {
+        modify(&mut self.weights);
+        self.fields.weights_changed(&self.weights, &self.state);
+    }
+
+    fn get_local_fields(&self, state: &[f64]) -> Vec<f64> {
+        let mut fields = vec![0.0; state.len()];
+        self.weights.mul_vec(state, &mut fields);
+        for (h, threshold) in fields.iter_mut().zip(self.thresholds.iter()) {
+            *h += threshold;
+        }
+        fields
+    }
+
+    fn set_update_mode(&mut self, mode: hop_net::UpdateMode) {
+        self.update_mode = mode;
+    }
+
+    fn get_update_mode(&self) -> hop_net::UpdateMode {
+        self.update_mode
+    }
+
+    // While the network is cooling down, the same state at two different temperatures is not a cycle
+    fn get_dynamic_state(&self) -> Vec<f64> {
+        let mut dynamic_state = self.state.clone();
+        dynamic_state.push(self.temperature);
+        dynamic_state
+    }
+}
+
+impl OptimizationNetwork {
+    // The network starts from a random state, so that different runs end up in different minima
+    pub fn new(config: &SolverConfig) -> OptimizationNetwork {
+        let (weights, thresholds) = config.problem.build();
+        let weights = Matrix::from_rows(&weights);
+        let size = thresholds.len();
+        let mut rng = rand::thread_rng();
+        let state: Vec<f64> = (0..size)
+            .map(|_| if rng.gen_bool(0.5) { 1.0 } else { -1.0 })
+            .collect();
+        let mut fields = LocalFields::new(size);
+        fields.recompute(&weights, &state);
+
+        let mut nodes_to_update = Vec::with_capacity(size);
+        hop_net::reset_nodes_to_update(&mut nodes_to_update, size);
+
+        OptimizationNetwork {
+            state,
+            rng,
+            weights,
+            thresholds,
+            fields,
+            start_temperature: config.start_temperature,
+            temperature: config.start_temperature,
+            cooling: config.cooling,
+            steps: 0,
+            nodes_yet_to_update: nodes_to_update,
+            last_update: None,
+            update_mode: hop_net::UpdateMode::Asynchronous,
+        }
+    }
+
+    fn field(&self, i: usize) -> f64 {
+        self.fields.get(i) + self.thresholds[i]
+    }
+
+    fn set_node(&mut self, i: usize, h: f64) -> bool {
+        let new_val = if self.temperature > 0.0 {
+            let p_on = 1.0 / (1.0 + (-2.0 * h / self.temperature).exp());
+            if self.rng.gen_bool(p_on.clamp(0.0, 1.0)) {
+                1.0
+            } else {
+                -1.0
+            }
+        } else if h > 0.0 {
+            1.0
+        } else {
+            -1.0
+        };
+
+        if new_val != self.state[i] {
+            self.fields
+                .node_changed(&self.weights, i, self.state[i], new_val);
+            self.state[i] = new_val;
+            return true;
+        }
+        false
+    }
+
+    fn cool_down(&mut self) {
+        self.temperature *= self.cooling;
+        if self.temperature < FREEZING_TEMPERATURE {
+            self.temperature = 0.0;
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::app::hop_net;
+use crate::app::hop_net::batch_recall::RecallOptions;
+use crate::app::hop_net::batch_recall::RecallResult;
+use crate::app::hop_net::learning_rules::LearningRule;
+use crate::app::hop_net::learning_rules::TrainingCallback;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error::NetworkError;
+use crate::app::hop_net::scalar::Scalar;
+use crate::app::hop_net::Net;
+use std::borrow::Cow;
+
+// Lets a network that works with T be used where a Net<f64> is expected (the gui and the network thread),
+// the values are converted every time they cross the boundary.
+// A copy of the state is kept as f64, and it's updated only when the state changes
+pub struct PrecisionAdapter<T: Scalar> {
+    net: Box<dyn Net<T>>,
+    state: Vec<f64>,
+}
+
+impl<T: Scalar> PrecisionAdapter<T> {
+    pub fn new(net: Box<dyn Net<T>>) -> PrecisionAdapter<T> {
+        let state = to_f64(net.get_state_ref());
+        PrecisionAdapter { net, state }
+    }
+
+    fn sync_state(&mut self) {
+        self.state.clear();
+        self.state
+            .extend(self.net.get_state_ref().iter().map(|x| x.to_f64()));
+    }
+}
+
+impl<T: Scalar> hop_net::Net<f64> for PrecisionAdapter<T> {
+    fn get_state_ref(&self) -> &[f64] {
+        &self.state
+    }
+
+    fn learn(&mut self, state: &[f64]) {
+        self.net.learn(&from_f64(state));
+    }
+
+    fn learn_all(&mut self, patterns: &[Vec<f64>]) {
+        let patterns: Vec<Vec<T>> = patterns.iter().map(|pattern| from_f64(pattern)).collect();
+        self.net.learn_all(&patterns);
+    }
+
+    fn step(&mut self) -> bool {
+        let state_changed = self.net.step();
+        if state_changed {
+            self.sync_state();
+        }
+        state_changed
+    }
+
+    fn get_steps(&self) -> usize {
+        self.net.get_steps()
+    }
+
+    fn get_last_update(&self) -> Option<(usize, f64)> {
+        self.net.get_last_update()
+    }
+
+    fn set_state(&mut self, state: &[f64]) -> Result<(), NetworkError> {
+        self.net.set_state(&from_f64(state))?;
+        self.sync_state();
+        Ok(())
+    }
+
+    fn reset_weights(&mut self) {
+        self.net.reset_weights();
+    }
+
+    fn get_weights(&self) -> Cow<'_, Matrix<f64>> {
+        Cow::Owned(self.net.get_weights().map(|w| w.to_f64()))
+    }
+
+    // The recall weights stay in T, copying them to f64 would take longer than the recall
+    fn batch_recall(
+        &self,
+        probes: &[Vec<f64>],
+        patterns: &[Vec<f64>],
+        options: RecallOptions,
+    ) -> Option<Result<Vec<RecallResult>, NetworkError>> {
+        self.net.batch_recall(probes, patterns, options)
+    }
+
+    // The weights are converted to f64 to be modified, and back to T afterwards
+    fn modify_weights(&mut self, modify: &mut dyn FnMut(&mut Matrix<f64>)) {
+        self.net.modify_weights(&mut |weights| {
+            let mut converted = weights.map(|w| w.to_f64());
+            modify(&mut converted);
+            *weights = converted.map(T::from_f64);
+        });
+    }
+
+    fn get_local_fields(&self, state: &[f64]) -> Vec<f64> {
+        self.net.get_local_fields(&from_f64(state))
+    }
+
+    fn set_update_mode(&mut self, mode: hop_net::UpdateMode) {
+        self.net.set_update_mode(mode);
+    }
+
+    fn get_update_mode(&self) -> hop_net::UpdateMode {
+        self.net.get_update_mode()
+    }
+
+    fn get_dynamic_state(&self) -> Vec<f64> {
+        to_f64(&self.net.get_dynamic_state())
+    }
+
+    fn get_layer_sizes(&self) -> Vec<usize> {
+        self.net.get_layer_sizes()
+    }
+
+    fn set_layer_sizes(&mut self, sizes: &[usize]) -> Result<(), NetworkError> {
+        self.net.set_layer_sizes(sizes)?;
+        self.sync_state();
+        Ok(())
+    }
+
+    fn set_learning_rule(&mut self, rule: LearningRule) {
+        self.net.set_learning_rule(rule);
+    }
+
+    fn set_training_callback(&mut self, callback: TrainingCallback) {
+        self.net.set_training_callback(callback);
+    }
+}
+
+fn to_f64<T: Scalar>(values: &[T]) -> Vec<f64> {
+    values.iter().map(|x| x.to_f64()).collect()
+}
+
+fn from_f64<T: Scalar>(values: &[f64]) -> Vec<T> {
+    values.iter().map(|x| T::from_f64(*x)).collect()
+}
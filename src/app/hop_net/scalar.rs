@@ -0,0 +1,135 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::ops::Add;
+use std::ops::AddAssign;
+use std::ops::Mul;
+use std::ops::Sub;
+use strum_macros::EnumIter;
+
+// A number a network can store its state and weights in.
+// Sums of many weights don't fit in the small integer types, so the fields are computed in Acc
+pub trait Scalar:
+    Copy + Default + PartialEq + PartialOrd + Debug + Mul<Output = Self> + Send + Sync + 'static
+{
+    type Acc: Copy
+        + Default
+        + PartialOrd
+        + Debug
+        + Add<Output = Self::Acc>
+        + AddAssign
+        + Sub<Output = Self::Acc>
+        + Mul<Output = Self::Acc>
+        + Send
+        + Sync;
+
+    const ONE: Self;
+    const MINUS_ONE: Self;
+
+    fn to_acc(self) -> Self::Acc;
+
+    // Integers round to the closest value they can hold
+    fn from_f64(value: f64) -> Self;
+
+    fn to_f64(self) -> f64;
+
+    fn acc_to_f64(acc: Self::Acc) -> f64;
+
+    // The same as +, but the integers stop at their biggest value instead of wrapping around
+    fn saturating_add(self, other: Self) -> Self;
+}
+
+macro_rules! float_scalar {
+    ($t:ty) => {
+        impl Scalar for $t {
+            type Acc = $t;
+
+            const ONE: Self = 1.0;
+            const MINUS_ONE: Self = -1.0;
+
+            fn to_acc(self) -> Self::Acc {
+                self
+            }
+
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn acc_to_f64(acc: Self::Acc) -> f64 {
+                acc as f64
+            }
+
+            fn saturating_add(self, other: Self) -> Self {
+                self + other
+            }
+        }
+    };
+}
+
+macro_rules! integer_scalar {
+    ($t:ty) => {
+        impl Scalar for $t {
+            type Acc = i32;
+
+            const ONE: Self = 1;
+            const MINUS_ONE: Self = -1;
+
+            fn to_acc(self) -> Self::Acc {
+                self as i32
+            }
+
+            // The cast saturates, so values out of range become the biggest (or smallest) one
+            fn from_f64(value: f64) -> Self {
+                value.round() as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn acc_to_f64(acc: Self::Acc) -> f64 {
+                acc as f64
+            }
+
+            fn saturating_add(self, other: Self) -> Self {
+                <$t>::saturating_add(self, other)
+            }
+        }
+    };
+}
+
+float_scalar!(f64);
+float_scalar!(f32);
+integer_scalar!(i16);
+integer_scalar!(i8);
+
+// The type the weights of a network are stored in. The integer types can store the hebbian weights exactly,
+// as long as fewer patterns than their biggest value are learned, after that the weights stop growing
+#[derive(EnumIter, Debug, PartialEq, Clone, Copy)]
+pub enum Precision {
+    F64,
+    F32,
+    I16,
+    I8,
+}
+
+impl Display for Precision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Precision::F64 => write!(f, "f64"),
+            Precision::F32 => write!(f, "f32"),
+            Precision::I16 => write!(f, "i16"),
+            Precision::I8 => write!(f, "i8"),
+        }
+    }
+}
+
+impl Precision {
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Precision::I16 | Precision::I8)
+    }
+}
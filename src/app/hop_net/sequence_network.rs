@@ -0,0 +1,222 @@
+use crate::app::hop_net;
+use crate::app::hop_net::matrix;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error::NetworkError;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+// How much stronger the push towards the next pattern is compared to the pull of the current one,
+// it has to be greater than 1, otherwise the network would just settle on the current pattern
+const TRANSITION_STRENGTH: f64 = 2.0;
+
+// Learns an ordered sequence of patterns (the order is the one in which they are learned) and
+// plays it back in a loop, the last pattern is followed by the first one.
+// The local field of a node is h = Ws * s(t) + TRANSITION_STRENGTH * Wa * s(t - delay) where
+// Ws is the usual symmetric hebbian matrix, and Wa = sum(ξ[μ+1] * ξ[μ]ᵀ) / N is the asymmetric one.
+// With no delay the state moves to the next pattern every sweep, with a delay the network
+// stays on each pattern for some time, because the slow synapse still "sees" the previous one.
+pub struct SequenceNetwork {
+    pub state: Vec<f64>,
+    patterns: Vec<Vec<f64>>,
+    symmetric_weights: Matrix<f64>,
+    transition_weights: Matrix<f64>,
+    // Ws + TRANSITION_STRENGTH * Wa, those are the weights the network would use if the delayed state matched the current one
+    combined_weights: Matrix<f64>,
+    // The states of the last sweeps, the front is the one seen by the delayed synapses
+    delay_line: VecDeque<Vec<f64>>,
+    delay: usize,
+    steps: usize,
+    nodes_yet_to_update: Vec<usize>,
+    last_update: Option<(usize, f64)>,
+    update_mode: hop_net::UpdateMode,
+}
+
+impl hop_net::Net<f64> for SequenceNetwork {
+    fn get_state_ref(&self) -> &[f64] {
+        &self.state
+    }
+
+    fn learn(&mut self, state: &[f64]) {
+        self.patterns.push(state.to_vec());
+        self.compute_weights();
+    }
+
+    fn step(&mut self) -> bool {
+        self.last_update = None;
+        self.steps += 1;
+
+        let mut state_changed = false;
+        let sweep_completed = match self.update_mode {
+            hop_net::UpdateMode::Synchronous => {
+                let len = self.state.len();
+                let mut fields = vec![0.0; len];
+                let mut delayed_fields = vec![0.0; len];
+                let delayed_state = self.delay_line.front().unwrap_or(&self.state);
+                self.symmetric_weights.mul_vec(&self.state, &mut fields);
+                self.transition_weights
+                    .mul_vec(delayed_state, &mut delayed_fields);
+                matrix::add_scaled(&mut fields, TRANSITION_STRENGTH, &delayed_fields);
+
+                for (i, h) in fields.into_iter().enumerate() {
+                    state_changed |= self.set_node(i, h);
+                }
+                true
+            }
+            hop_net::UpdateMode::Asynchronous => {
+                if self.nodes_yet_to_update.is_empty() {
+                    hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+                }
+                let i = self.nodes_yet_to_update.pop().unwrap();
+                let delayed_state = self.delay_line.front().unwrap_or(&self.state);
+                let h = matrix::dot(&self.symmetric_weights[i], &self.state)
+                    + TRANSITION_STRENGTH * matrix::dot(&self.transition_weights[i], delayed_state);
+                self.last_update = Some((i, h));
+                state_changed = self.set_node(i, h);
+                self.nodes_yet_to_update.is_empty()
+            }
+        };
+
+        // The delayed synapses are part of the state of the network, even if the nodes didn't change
+        // the network can still be moving towards the next pattern
+        if sweep_completed && self.delay > 0 {
+            let oldest = self.delay_line.pop_front();
+            state_changed |= oldest.as_ref() != Some(&self.state);
+            self.delay_line.push_back(self.state.clone());
+        }
+
+        state_changed
+    }
+
+    fn get_steps(&self) -> usize {
+        self.steps
+    }
+
+    fn get_last_update(&self) -> Option<(usize, f64)> {
+        self.last_update
+    }
+
+    fn set_state(&mut self, state: &[f64]) -> Result<(), NetworkError> {
+        hop_net::check_state_size(state.len())?;
+
+        let size_changed = self.state.len() != state.len();
+        self.state = state.to_vec();
+        if size_changed {
+            // compute_weights takes the size from the state, so it has to be called after the state is set
+            self.patterns.clear();
+            self.compute_weights();
+        }
+        self.steps = 0;
+
+        // The network has no past, so we pretend it has always been in the new state
+        self.reset_delay_line();
+        hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+        Ok(())
+    }
+
+    fn reset_weights(&mut self) {
+        self.patterns.clear();
+        self.compute_weights();
+    }
+
+    fn get_weights(&self) -> Cow<'_, Matrix<f64>> {
+        Cow::Borrowed(&self.combined_weights)
+    }
+
+    fn modify_weights(&mut self, modify: &mut dyn FnMut(&mut Matrix<f64>)) {
+        modify(&mut self.symmetric_weights);
+        modify(&mut self.transition_weights);
+        self.combine_weights();
+    }
+
+    fn set_update_mode(&mut self, mode: hop_net::UpdateMode) {
+        self.update_mode = mode;
+    }
+
+    fn get_update_mode(&self) -> hop_net::UpdateMode {
+        self.update_mode
+    }
+
+    fn get_dynamic_state(&self) -> Vec<f64> {
+        let mut dynamic_state = self.state.clone();
+        for past_state in &self.delay_line {
+            dynamic_state.extend_from_slice(past_state);
+        }
+        dynamic_state
+    }
+
+    fn plays_sequences(&self) -> bool {
+        true
+    }
+}
+
+impl SequenceNetwork {
+    pub fn new(
+        size: usize,
+        start_state: Option<&Vec<f64>>,
+        delay: usize,
+    ) -> Result<SequenceNetwork, NetworkError> {
+        let state = hop_net::start_state(size, start_state)?;
+
+        let mut nodes_to_update = Vec::with_capacity(size);
+        hop_net::reset_nodes_to_update(&mut nodes_to_update, size);
+
+        let mut net = SequenceNetwork {
+            state,
+            patterns: Vec::new(),
+            symmetric_weights: Matrix::square(size),
+            transition_weights: Matrix::square(size),
+            combined_weights: Matrix::square(size),
+            delay_line: VecDeque::with_capacity(delay),
+            delay,
+            steps: 0,
+            nodes_yet_to_update: nodes_to_update,
+            last_update: None,
+            update_mode: hop_net::UpdateMode::Synchronous,
+        };
+        net.reset_delay_line();
+        Ok(net)
+    }
+
+    fn reset_delay_line(&mut self) {
+        self.delay_line.clear();
+        for _ in 0..self.delay {
+            self.delay_line.push_back(self.state.clone());
+        }
+    }
+
+    // The weights are computed from scratch, the asymmetric part depends on the order of all the patterns
+    fn compute_weights(&mut self) {
+        let len = self.state.len();
+        let norm = len as f64;
+        self.symmetric_weights = Matrix::square(len);
+        self.transition_weights = Matrix::square(len);
+
+        for (mu, pattern) in self.patterns.iter().enumerate() {
+            let next = &self.patterns[(mu + 1) % self.patterns.len()];
+            for i in 0..len {
+                matrix::add_scaled(&mut self.symmetric_weights[i], pattern[i] / norm, pattern);
+                matrix::add_scaled(&mut self.transition_weights[i], next[i] / norm, pattern);
+            }
+        }
+        self.symmetric_weights.set_diagonal(0.0);
+        self.combine_weights();
+    }
+
+    fn combine_weights(&mut self) {
+        self.combined_weights.clone_from(&self.symmetric_weights);
+        matrix::add_scaled(
+            self.combined_weights.as_mut_slice(),
+            TRANSITION_STRENGTH,
+            self.transition_weights.as_slice(),
+        );
+    }
+
+    fn set_node(&mut self, i: usize, h: f64) -> bool {
+        let new_val = if h > 0.0 { 1.0 } else { -1.0 };
+        if new_val != self.state[i] {
+            self.state[i] = new_val;
+            return true;
+        }
+        false
+    }
+}
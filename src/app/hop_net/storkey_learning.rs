@@ -1,158 +1,240 @@
 use crate::app::hop_net;
+use crate::app::hop_net::local_fields;
+use crate::app::hop_net::local_fields::LocalFields;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error::NetworkError;
+use std::borrow::Cow;
 
 pub struct StorkeyLearningNetwork {
     pub state: Vec<f64>,
     pub rng: rand::rngs::ThreadRng,
-    weights: Vec<Vec<f64>>,
-    inference_weights: Vec<Vec<f64>>,
+    weights: Matrix<f64>,
+    inference_weights: Matrix<f64>,
+    // The fields computed with the inference weights
+    fields: LocalFields,
     number_of_learned_states: f64,
     steps: usize,
     nodes_yet_to_update: Vec<usize>,
+    last_update: Option<(usize, f64)>,
+    update_mode: hop_net::UpdateMode,
 }
 
 impl hop_net::Net<f64> for StorkeyLearningNetwork {
-    fn get_state(&self) -> Vec<f64> {
-        self.state.clone()
+    fn get_state_ref(&self) -> &[f64] {
+        &self.state
     }
 
     fn learn(&mut self, state: &[f64]) {
         self.number_of_learned_states += 1.0;
         self.storkey_learning(state);
+        self.update_inference_weights();
     }
 
-    fn step(&mut self) -> (bool, Vec<f64>) {
+    fn step(&mut self) -> bool {
+        self.last_update = None;
+        if self.update_mode == hop_net::UpdateMode::Synchronous {
+            self.steps += 1;
+            return local_fields::synchronous_update(
+                &self.inference_weights,
+                &mut self.fields,
+                &mut self.state,
+            );
+        }
+
         if self.nodes_yet_to_update.is_empty() {
             hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
         }
         let i = self.nodes_yet_to_update.pop().unwrap();
         self.steps += 1;
-        let state_changed = self.update_node(i);
-
-        (state_changed, self.state.clone())
+        self.last_update = Some((i, self.fields.get(i)));
+        local_fields::update_node(
+            &self.inference_weights,
+            &mut self.fields,
+            &mut self.state,
+            i,
+        )
     }
 
     fn get_steps(&self) -> usize {
         self.steps
     }
 
-    fn set_state(&mut self, state: &[f64]) {
-        if state.len() < 4 {
-            panic!("State is too short");
-        }
+    fn get_last_update(&self) -> Option<(usize, f64)> {
+        self.last_update
+    }
+
+    fn set_state(&mut self, state: &[f64]) -> Result<(), NetworkError> {
+        hop_net::check_state_size(state.len())?;
 
         if self.state.len() != state.len() {
             self.number_of_learned_states = 0.0;
-            self.weights = vec![vec![0.0; state.len()]; state.len()];
+            self.weights = Matrix::square(state.len());
+            self.inference_weights = Matrix::square(state.len());
             self.steps = 0;
         }
         self.state = state.to_vec();
+        self.fields.recompute(&self.inference_weights, &self.state);
 
         // The starting state has just been set, so we are 0 steps away from it
         self.steps = 0;
 
         // We make sure that all nodes are marked as "to update"
         hop_net::reset_nodes_to_update(&mut self.nodes_yet_to_update, self.state.len());
+        Ok(())
     }
 
     fn reset_weights(&mut self) {
-        self.weights = vec![vec![0.0; self.state.len()]; self.state.len()];
+        self.weights = Matrix::square(self.state.len());
         self.number_of_learned_states = 0.0;
+        self.update_inference_weights();
+    }
+
+    fn get_weights(&self) -> Cow<'_, Matrix<f64>> {
+        Cow::Borrowed(&self.weights)
     }
 
-    fn get_weights(&self) -> Vec<Vec<f64>> {
-        self.weights.clone()
+    fn get_recall_weights(&self) -> Option<&Matrix<f64>> {
+        Some(&self.inference_weights)
+    }
+
+    fn modify_weights(&mut self, modify: &mut dyn FnMut(&mut Matrix<f64>)) {
+        modify(&mut self.weights);
+        self.update_inference_weights();
+    }
+
+    fn set_update_mode(&mut self, mode: hop_net::UpdateMode) {
+        self.update_mode = mode;
+    }
+
+    fn get_update_mode(&self) -> hop_net::UpdateMode {
+        self.update_mode
     }
 }
 
 impl StorkeyLearningNetwork {
-    pub fn new(size: usize, start_state: Option<&Vec<f64>>) -> StorkeyLearningNetwork {
-        let state = if start_state.is_none() {
-            vec![-1.0; size]
-        } else {
-            let start_s = start_state.unwrap();
-            if start_s.len() != size {
-                panic!("Size and start size lenght are differnt");
-            }
-            start_s.clone()
-        };
+    pub fn new(
+        size: usize,
+        start_state: Option<&Vec<f64>>,
+    ) -> Result<StorkeyLearningNetwork, NetworkError> {
+        let state = hop_net::start_state(size, start_state)?;
 
         let mut nodes_to_update = Vec::with_capacity(size);
         hop_net::reset_nodes_to_update(&mut nodes_to_update, size);
 
-        StorkeyLearningNetwork {
+        Ok(StorkeyLearningNetwork {
             state,
             rng: rand::thread_rng(),
-            weights: vec![vec![0.0; size]; size],
+            weights: Matrix::square(size),
             steps: 0,
             number_of_learned_states: 0.0,
             nodes_yet_to_update: nodes_to_update,
-            inference_weights: vec![vec![0.0; size]; size],
-        }
+            last_update: None,
+            update_mode: hop_net::UpdateMode::Asynchronous,
+            inference_weights: Matrix::square(size),
+            fields: LocalFields::new(size),
+        })
     }
 
-    fn update_node(&mut self, i: usize) -> bool {
-        let mut sum = 0.0;
-        for j in 0..self.inference_weights[i].len() {
-            sum += self.inference_weights[i][j] * self.state[j];
-        }
-        let new_val = if sum > 0.0 { 1.0 } else { -1.0 };
-        if new_val != self.state[i] {
-            self.state[i] = new_val;
-            return true;
-        }
-        false
+    // In the paper it said that havind 0 on the diagonal improves retrival, but hinders learning, so i just store 2 copies of the weights
+    fn update_inference_weights(&mut self) {
+        self.inference_weights.clone_from(&self.weights);
+        self.inference_weights.set_diagonal(0.0);
+        self.fields
+            .weights_changed(&self.inference_weights, &self.state);
     }
 
+    // w_ij += (ξ_i * ξ_j - ξ_i * h_ji - h_ij * ξ_j) / n, where h_ij = sum over every k of w_ik * ξ_k,
+    // only h_ii leaves out its own term. So h_ij is the same for every j, and computing the fields once
+    // is enough to make learning O(N²) instead of O(N³)
     fn storkey_learning(&mut self, state: &[f64]) {
-        let old_weights = self.weights.clone();
         let len = self.state.len();
-        let _c = self.c(state, &old_weights);
+        let norm = len as f64;
+        let mut fields = vec![0.0; len];
+        self.weights.mul_vec(state, &mut fields);
+        let diagonal: Vec<f64> = (0..len).map(|i| self.weights[i][i]).collect();
 
+        // The change is the same for w_ij and w_ji, so each pair is updated at the same time, before any of the two changes
         for i in 0..len {
-            for j in 0..len {
+            let h_ii = fields[i] - diagonal[i] * state[i];
+            self.weights[i][i] = diagonal[i] + (1.0 - 2.0 * state[i] * h_ii) / norm;
+
+            for j in (i + 1)..len {
+                let h_ij = fields[i];
+                let h_ji = fields[j];
+
                 // when state procuct is +1, if node i and j want to be on/off at the same time, and
                 // new_state = state * weights will keep changing until that constraint is met
                 // for -1 they want to be different
                 let state_product = state[i] * state[j];
 
                 //I have't been able to find mutch about this term
-                let noise_reduction = state[i] * self.h(j, i, &state, &old_weights)
-                    + state[j] * self.h(i, j, &state, &old_weights);
-                let _h_product =
-                    self.h(i, j, &state, &old_weights) * self.h(j, i, &state, &old_weights);
+                let noise_reduction = state[i] * h_ji + h_ij * state[j];
 
-                self.weights[i][j] = old_weights[i][j]
-                    +/* c */ ((state_product /*+ h_product*/) - noise_reduction) / (len as f64);
+                let delta = (state_product - noise_reduction) / norm;
+                self.weights[i][j] += delta;
+                self.weights[j][i] += delta;
             }
         }
+    }
+}
 
-        self.inference_weights = self.weights.clone();
-        // In the paper it said that havind 0 on the diagonal improves retrival, but hinders learning, so i just store 2 copies of the weights
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::hop_net::Net;
+
+    // The rule as it was first written, O(N³) but straight from the formula
+    fn baseline_storkey(weights: &mut [Vec<f64>], state: &[f64]) {
+        let old_weights = weights.to_vec();
+        let len = state.len();
+        let h = |i: usize, j: usize| -> f64 {
+            let mut sum = 0.0;
+            for k in 0..len {
+                if k != j || k != i {
+                    sum += old_weights[i][k] * state[k];
+                }
+            }
+            sum
+        };
         for i in 0..len {
-            self.inference_weights[i][i] = 0.0;
+            for j in 0..len {
+                let noise_reduction = state[i] * h(j, i) + state[j] * h(i, j);
+                weights[i][j] =
+                    old_weights[i][j] + (state[i] * state[j] - noise_reduction) / len as f64;
+            }
         }
     }
 
-    //h should be the interaction of the new state with the weights matrix (aka the other stored states)
-    fn h(&self, i: usize, j: usize, new_state: &[f64], old_weights: &Vec<Vec<f64>>) -> f64 {
-        let mut sum = 0.0;
-        for iter in 0..self.state.len() {
-            if iter != j || iter != i {
-                sum += old_weights[i][iter] * new_state[iter];
+    #[test]
+    fn matches_the_baseline_rule() {
+        let patterns = [
+            vec![1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0],
+            vec![-1.0, -1.0, 1.0, -1.0, 1.0, 1.0, 1.0, -1.0],
+            vec![1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0],
+            vec![-1.0, 1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0],
+        ];
+        let size = patterns[0].len();
+        let mut net = StorkeyLearningNetwork::new(size, None).unwrap();
+        let mut expected = vec![vec![0.0; size]; size];
+        for pattern in patterns.iter() {
+            net.learn(pattern);
+            baseline_storkey(&mut expected, pattern);
+
+            let weights = net.get_weights();
+            for (row, expected_row) in weights.iter_rows().zip(expected.iter()) {
+                for (w, expected_w) in row.iter().zip(expected_row.iter()) {
+                    assert!((w - expected_w).abs() < 1e-12, "{} != {}", w, expected_w);
+                }
             }
         }
-        sum
-    }
 
-    // Pseudo inverse rule
-    fn c(&self, new_state: &[f64], old_weights: &Vec<Vec<f64>>) -> f64 {
-        let len = self.state.len();
-        let mut sum = 0.0;
-        for i in 0..len {
-            for j in 0..len {
-                sum += new_state[i] * old_weights[i][j] * new_state[j];
+        // The inference weights are the same, without the diagonal
+        let recall_weights = net.get_recall_weights().unwrap();
+        for i in 0..size {
+            for j in 0..size {
+                let expected_w = if i == j { 0.0 } else { expected[i][j] };
+                assert!((recall_weights[i][j] - expected_w).abs() < 1e-12);
             }
         }
-        1.0 - sum / (len as f64)
     }
 }
@@ -0,0 +1,150 @@
+use crate::app::hop_net::diagnostics;
+use crate::app::hop_net::experiments;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error::NetworkError;
+use crate::app::hop_net::Net;
+use crate::app::hop_net::NetworkType;
+use rand::Rng;
+
+// The probes used to measure the recall before and after the dreams
+const PROBE_NOISE: f64 = 0.1;
+const PROBE_TRIALS: usize = 5;
+
+// The progress is reported at most this many times, a message per dream would flood the gui
+const PROGRESS_REPORTS: usize = 100;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Unlearning {
+    pub dreams: usize,
+    // How much of the attractor is removed at every dream, relative to the size of the weights
+    pub rate: f64,
+}
+
+// How well the network remembered the stored patterns, before and after dreaming
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Capacity {
+    // The stored patterns that are fixed points
+    pub stable_patterns: usize,
+    // The fraction of the probes (patterns with PROBE_NOISE of their nodes flipped) that were recalled
+    pub recall_rate: f64,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct UnlearningReport {
+    pub before: Capacity,
+    pub after: Capacity,
+    pub pattern_count: usize,
+    // The dreams that ended far from every stored pattern (and their negatives), in spurious states
+    pub spurious_dreams: usize,
+}
+
+// Only the networks with a single matrix of symmetric hebbian-like weights can dream, the others keep more than
+// one matrix, or blocks that must stay empty, and the anti-hebbian update would break them.
+// None if the network can dream. The optimization network (a None type) has no memories to dream about
+pub fn unavailable_reason(net_type: Option<NetworkType>) -> Option<&'static str> {
+    match net_type {
+        Some(NetworkType::SquareDiscrete | NetworkType::StorkeySquareDiscrete) => None,
+        None => Some("The optimization network has no memories to dream about"),
+        Some(_) => {
+            Some("Only the hebbian and Storkey networks can dream, the other weights would break")
+        }
+    }
+}
+
+// Hopfield, Feinstein and Palmer's unlearning: the network relaxes from random states, and the attractors
+// it ends up in are made a bit shallower with an anti-hebbian update. The spurious attractors are reached
+// more often than the stored patterns, so they are removed faster.
+// The rate is multiplied by the root mean square of the weights, so the same rate works with any learning rule,
+// Each dream adds its change to the weights of the network, what they can't hold (integer weights round the
+// small changes away) is kept and added to the next one, so the small changes still add up.
+// The network gets its state back at the end
+pub fn unlearn(
+    net: &mut dyn Net<f64>,
+    patterns: &[Vec<f64>],
+    unlearning: Unlearning,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<UnlearningReport, NetworkError> {
+    let original_state = net.get_state();
+    let size = original_state.len();
+    let before = measure_capacity(net, patterns)?;
+
+    let mean_square = {
+        let weights = net.get_weights();
+        weights.as_slice().iter().map(|w| w * w).sum::<f64>()
+            / weights.as_slice().len().max(1) as f64
+    };
+    let step = unlearning.rate * mean_square.sqrt();
+    // The change the weights still have to take
+    let mut pending: Matrix<f64> = Matrix::square(size);
+
+    let mut rng = rand::thread_rng();
+    let mut spurious_dreams = 0;
+    let report_every = (unlearning.dreams / PROGRESS_REPORTS).max(1);
+    for dream in 0..unlearning.dreams {
+        let start: Vec<f64> = (0..size)
+            .map(|_| if rng.gen_bool(0.5) { 1.0 } else { -1.0 })
+            .collect();
+        let attractor = experiments::recall(net, &start, experiments::MAX_RECALL_SWEEPS)?;
+        let is_stored = patterns.iter().any(|pattern| {
+            experiments::overlap(pattern, &attractor).abs() >= experiments::RECALL_THRESHOLD
+        });
+        if !is_stored {
+            spurious_dreams += 1;
+        }
+
+        for (i, row) in pending.iter_rows_mut().enumerate() {
+            let s_i = attractor[i];
+            for (j, w) in row.iter_mut().enumerate() {
+                if j != i {
+                    *w -= step * s_i * attractor[j];
+                }
+            }
+        }
+        let before = net.get_weights().into_owned();
+        net.modify_weights(&mut |weights| {
+            // Only a matrix that links every node with every other can take the change
+            if weights.rows() == size && weights.cols() == size {
+                for (w, change) in weights.as_mut_slice().iter_mut().zip(pending.as_slice()) {
+                    *w += change;
+                }
+            }
+        });
+        let after = net.get_weights();
+        for ((change, old), new) in pending
+            .as_mut_slice()
+            .iter_mut()
+            .zip(before.as_slice())
+            .zip(after.as_slice())
+        {
+            *change -= new - old;
+        }
+
+        if (dream + 1) % report_every == 0 || dream + 1 == unlearning.dreams {
+            progress(dream + 1, unlearning.dreams);
+        }
+    }
+
+    let after = measure_capacity(net, patterns)?;
+    net.set_state(&original_state)?;
+    Ok(UnlearningReport {
+        before,
+        after,
+        pattern_count: patterns.len(),
+        spurious_dreams,
+    })
+}
+
+fn measure_capacity(
+    net: &mut dyn Net<f64>,
+    patterns: &[Vec<f64>],
+) -> Result<Capacity, NetworkError> {
+    let stable_patterns = patterns
+        .iter()
+        .filter(|pattern| diagnostics::unstable_bits(net, pattern) == 0)
+        .count();
+    let (_, recall_rate) = experiments::measure_recall(net, patterns, PROBE_NOISE, PROBE_TRIALS)?;
+    Ok(Capacity {
+        stable_patterns,
+        recall_rate,
+    })
+}
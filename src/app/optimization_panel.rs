@@ -0,0 +1,166 @@
+use crate::app::hop_net::optimization::Problem;
+use crate::app::hop_net::optimization::ProblemKind;
+use crate::app::hop_net::optimization::Solution;
+use crate::app::hop_net::optimization::SolverConfig;
+use strum::IntoEnumIterator;
+
+pub struct OptimizationPanel {
+    kind: ProblemKind,
+    size: usize,
+    start_temperature: f64,
+    cooling: f64,
+    problem: Option<Problem>,
+    solve_pressed: bool,
+    // The decoded state of the network, it's Some only while the network is solving a problem
+    solution: Option<Solution>,
+    tour: Option<Vec<usize>>,
+}
+
+impl OptimizationPanel {
+    pub fn new() -> Self {
+        Self {
+            kind: ProblemKind::TravelingSalesman,
+            size: 6,
+            start_temperature: 2.0,
+            cooling: 0.95,
+            problem: None,
+            solve_pressed: false,
+            solution: None,
+            tour: None,
+        }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        self.solve_pressed = false;
+
+        egui::CollapsingHeader::new("Optimization solver").show(ui, |ui| {
+            egui::ComboBox::new("problem_kind_combo", "problem")
+                .selected_text(self.kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in ProblemKind::iter() {
+                        ui.selectable_value(&mut self.kind, kind, kind.to_string());
+                    }
+                });
+
+            let size_text = match self.kind {
+                ProblemKind::TravelingSalesman => "cities",
+                ProblemKind::MaxCut => "side of the graph",
+                ProblemKind::NRooks | ProblemKind::NQueens => "board side",
+            };
+            ui.add(egui::Slider::new(&mut self.size, 3..=10).text(size_text));
+            ui.add(
+                egui::Slider::new(&mut self.start_temperature, 0.0..=5.0).text("start temperature"),
+            );
+            ui.add(egui::Slider::new(&mut self.cooling, 0.8..=0.999).text("cooling"));
+
+            ui.horizontal(|ui| {
+                if ui.button("New instance").clicked() {
+                    self.problem = Some(Problem::random(self.kind, self.size));
+                }
+                self.solve_pressed = ui
+                    .button("Solve")
+                    .on_hover_text(
+                        "Replaces the network with one whose energy minima are the solutions",
+                    )
+                    .clicked();
+            });
+
+            // A new instance is needed only if the old one doesn't match the settings
+            if self.solve_pressed {
+                let outdated = match &self.problem {
+                    Some(problem) => problem.kind() != self.kind || problem.side() != self.size,
+                    None => true,
+                };
+                if outdated {
+                    self.problem = Some(Problem::random(self.kind, self.size));
+                }
+            }
+
+            if let Some(solution) = &self.solution {
+                let color = if solution.valid {
+                    egui::Color32::from_rgb(0, 180, 0)
+                } else {
+                    egui::Color32::from_rgb(220, 120, 0)
+                };
+                ui.colored_label(color, &solution.description);
+            }
+
+            if let Some(Problem::TravelingSalesman { cities, .. }) = &self.problem {
+                self.draw_cities(ui, cities);
+            }
+        });
+    }
+
+    // The cities are drawn in the unit square, with the decoded tour on top when there is one
+    fn draw_cities(&self, ui: &mut egui::Ui, cities: &[(f64, f64)]) {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(150.0, 150.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.bg_stroke);
+
+        let to_screen = |city: &(f64, f64)| {
+            rect.min
+                + egui::vec2(city.0 as f32, city.1 as f32) * (rect.width() - 10.0)
+                + egui::vec2(5.0, 5.0)
+        };
+
+        if let Some(tour) = &self.tour {
+            let mut points: Vec<egui::Pos2> = tour.iter().map(|c| to_screen(&cities[*c])).collect();
+            points.push(points[0]);
+            painter.add(egui::Shape::line(
+                points,
+                egui::Stroke::new(1.5, egui::Color32::from_rgb(0, 180, 0)),
+            ));
+        }
+
+        for (i, city) in cities.iter().enumerate() {
+            let pos = to_screen(city);
+            painter.circle_filled(pos, 3.0, ui.visuals().text_color());
+            painter.text(
+                pos + egui::vec2(4.0, -4.0),
+                egui::Align2::LEFT_BOTTOM,
+                i.to_string(),
+                egui::FontId::proportional(10.0),
+                ui.visuals().text_color(),
+            );
+        }
+    }
+
+    // Getters
+
+    pub fn solve_pressed(&self) -> bool {
+        self.solve_pressed
+    }
+
+    pub fn get_solver_config(&self) -> Option<SolverConfig> {
+        self.problem.as_ref().map(|problem| SolverConfig {
+            problem: problem.clone(),
+            start_temperature: self.start_temperature,
+            cooling: self.cooling,
+        })
+    }
+
+    // Setters
+
+    // Decodes the state shown to the user, it should be called only while the network is solving the problem
+    pub fn evaluate(&mut self, state: &[f64]) {
+        if let Some(problem) = &self.problem {
+            if state.len() == problem.neuron_count() {
+                self.solution = Some(problem.evaluate(state));
+                self.tour = problem.decode_tour(state);
+                return;
+            }
+        }
+        self.stop_evaluating();
+    }
+
+    pub fn stop_evaluating(&mut self) {
+        self.solution = None;
+        self.tour = None;
+    }
+}
+
+impl Default for OptimizationPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
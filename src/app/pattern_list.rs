@@ -0,0 +1,237 @@
+use crate::app::central_panel::state_renderer;
+use crate::app::shortcuts::Action;
+use crate::app::shortcuts::ShortcutHints;
+
+// Keeps a copy of every pattern the network has learned, in the order they were learned.
+// The network itself only knows its weights, so this is the only place where the patterns can be seen again
+pub struct PatternList {
+    patterns: Vec<Vec<f64>>,
+    // All the patterns share the layout of the network that learned them
+    layer_sizes: Vec<usize>,
+    thumbnail_cell_size: f32,
+    pattern_to_load: Option<usize>,
+    learn_as_sequence: bool,
+    pattern_removed: bool,
+    // How many nodes of each pattern would flip, None until the network has checked it
+    unstable_bits: Vec<Option<usize>>,
+    // The network numbers the patterns in the order it learned them, after the list is edited that
+    // order is lost, until the patterns are learned again
+    in_learning_order: bool,
+    // The patterns that were fixed points before the last one was learned, and aren't anymore
+    newly_unstable: Vec<usize>,
+    shortcut_hints: ShortcutHints,
+}
+
+impl PatternList {
+    pub fn new() -> Self {
+        Self {
+            patterns: Vec::new(),
+            layer_sizes: Vec::new(),
+            thumbnail_cell_size: 3.0,
+            pattern_to_load: None,
+            learn_as_sequence: false,
+            pattern_removed: false,
+            unstable_bits: Vec::new(),
+            in_learning_order: true,
+            newly_unstable: Vec::new(),
+            shortcut_hints: ShortcutHints::default(),
+        }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        self.pattern_to_load = None;
+        self.learn_as_sequence = false;
+        self.pattern_removed = false;
+
+        ui.label("Stored patterns:");
+        if self.patterns.is_empty() {
+            ui.label("No pattern has been learned yet");
+            return;
+        }
+
+        let mut move_up = None;
+        let mut move_down = None;
+        let mut remove = None;
+        let len = self.patterns.len();
+
+        egui::ScrollArea::vertical()
+            .id_source("pattern_list_scroll")
+            .max_height(250.0)
+            .show(ui, |ui| {
+                for i in 0..len {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}.", i + 1));
+                        stability_badge(ui, self.unstable_bits[i]);
+                        let response = state_renderer::render_thumbnail(
+                            ui,
+                            &self.patterns[i],
+                            &self.layer_sizes,
+                            self.thumbnail_cell_size,
+                        );
+                        let response = self.shortcut_hints.tooltip(
+                            response,
+                            Action::LoadPattern(i),
+                            "Click to load",
+                        );
+                        if response.clicked() {
+                            self.pattern_to_load = Some(i);
+                        }
+                        if ui.add_enabled(i > 0, egui::Button::new("⬆")).clicked() {
+                            move_up = Some(i);
+                        }
+                        if ui
+                            .add_enabled(i + 1 < len, egui::Button::new("⬇"))
+                            .clicked()
+                        {
+                            move_down = Some(i);
+                        }
+                        if ui.button("🗑").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+            });
+
+        // The list is edited only after it has been drawn, so the indices used above stay valid
+        if let Some(i) = move_up {
+            self.patterns.swap(i, i - 1);
+            self.unstable_bits.swap(i, i - 1);
+            self.in_learning_order = false;
+        }
+        if let Some(i) = move_down {
+            self.patterns.swap(i, i + 1);
+            self.unstable_bits.swap(i, i + 1);
+            self.in_learning_order = false;
+        }
+        // The network still remembers the removed pattern, so the others may be less stable than they would be without it
+        if let Some(i) = remove {
+            self.patterns.remove(i);
+            self.unstable_bits.remove(i);
+            self.in_learning_order = false;
+            self.newly_unstable.clear();
+            self.pattern_removed = true;
+        }
+
+        if !self.newly_unstable.is_empty() {
+            let names: Vec<String> = self
+                .newly_unstable
+                .iter()
+                .map(|i| (i + 1).to_string())
+                .collect();
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "The last pattern made {} unstable, the network may be over its capacity",
+                    if names.len() == 1 {
+                        format!("pattern {}", names[0])
+                    } else {
+                        format!("patterns {}", names.join(", "))
+                    }
+                ),
+            );
+        }
+
+        let response = ui
+            .button("Learn as sequence")
+            .on_hover_text("Forgets everything and learns the patterns again, from top to bottom.\nSequence networks play them back in this order");
+        self.learn_as_sequence = response.clicked();
+        if self.learn_as_sequence {
+            self.relearned();
+        }
+    }
+
+    // The network learns the list from scratch, so its order is the one of the list again
+    pub fn relearned(&mut self) {
+        self.in_learning_order = true;
+        self.unstable_bits.fill(None);
+        self.newly_unstable.clear();
+    }
+
+    // Getters
+
+    pub fn get_patterns(&self) -> &[Vec<f64>] {
+        &self.patterns
+    }
+
+    pub fn pattern_to_load(&self) -> Option<&Vec<f64>> {
+        self.pattern_to_load.map(|i| &self.patterns[i])
+    }
+
+    pub fn learn_as_sequence(&self) -> bool {
+        self.learn_as_sequence
+    }
+
+    pub fn pattern_removed(&self) -> bool {
+        self.pattern_removed
+    }
+
+    // Setters
+
+    pub fn set_shortcut_hints(&mut self, hints: ShortcutHints) {
+        self.shortcut_hints = hints;
+    }
+
+    pub fn push(&mut self, pattern: Vec<f64>, layer_sizes: &[usize]) {
+        if layer_sizes != self.layer_sizes.as_slice() {
+            // Patterns with a different layout come from a different network
+            self.patterns.clear();
+            self.layer_sizes = layer_sizes.to_vec();
+        }
+        self.patterns.push(pattern);
+        self.unstable_bits.push(None);
+    }
+
+    // The counts are in the order the network learned the patterns, if the list has been edited since then
+    // they can't be matched with the patterns anymore
+    pub fn set_unstable_bits(&mut self, unstable_bits: &[usize]) {
+        self.newly_unstable.clear();
+        if !self.in_learning_order || unstable_bits.len() != self.patterns.len() {
+            return;
+        }
+        for (i, (old, new)) in self
+            .unstable_bits
+            .iter_mut()
+            .zip(unstable_bits.iter())
+            .enumerate()
+        {
+            if *old == Some(0) && *new > 0 {
+                self.newly_unstable.push(i);
+            }
+            *old = Some(*new);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.patterns.clear();
+        self.unstable_bits.clear();
+        self.newly_unstable.clear();
+        // The network is going to forget everything too, so the next patterns are learned in order again
+        self.in_learning_order = true;
+    }
+}
+
+fn stability_badge(ui: &mut egui::Ui, unstable_bits: Option<usize>) {
+    match unstable_bits {
+        Some(0) => {
+            ui.colored_label(egui::Color32::GREEN, "✔")
+                .on_hover_text("The pattern is a fixed point of the network");
+        }
+        Some(bits) => {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠{}", bits))
+                .on_hover_text(format!(
+                    "{} nodes would flip if the network was in this pattern",
+                    bits
+                ));
+        }
+        None => {
+            ui.label("?")
+                .on_hover_text("Not checked, learn the patterns as a sequence to check them again");
+        }
+    }
+}
+
+impl Default for PatternList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
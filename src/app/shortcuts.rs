@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+// The stored patterns that can be loaded with the number keys, from the first one
+const PATTERN_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+// Everything that can be done from the keyboard or from the command palette
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, serde::Deserialize, serde::Serialize)]
+pub enum Action {
+    StartStop,
+    StepNode,
+    StepSweep,
+    Converge,
+    LearnCurrentState,
+    ForgetAll,
+    ResetState,
+    SaveState,
+    AddNoise,
+    Undo,
+    Redo,
+    CommandPalette,
+    // The patterns are counted from 0, the keys from 1
+    LoadPattern(usize),
+}
+
+impl Action {
+    // In the order they are listed in the palette and in the shortcuts window
+    pub fn all() -> Vec<Action> {
+        let mut actions = vec![
+            Action::StartStop,
+            Action::StepNode,
+            Action::StepSweep,
+            Action::Converge,
+            Action::LearnCurrentState,
+            Action::ForgetAll,
+            Action::ResetState,
+            Action::SaveState,
+            Action::AddNoise,
+            Action::Undo,
+            Action::Redo,
+            Action::CommandPalette,
+        ];
+        actions.extend((0..PATTERN_KEYS.len()).map(Action::LoadPattern));
+        actions
+    }
+
+    fn default_shortcut(self) -> Option<Shortcut> {
+        let key = |key| Some(Shortcut::new(egui::Modifiers::NONE, key));
+        let command = |key| Some(Shortcut::new(egui::Modifiers::COMMAND, key));
+        match self {
+            Action::StartStop => key(egui::Key::Space),
+            Action::StepNode => key(egui::Key::ArrowRight),
+            Action::StepSweep => key(egui::Key::ArrowDown),
+            Action::Converge => key(egui::Key::C),
+            Action::LearnCurrentState => key(egui::Key::L),
+            Action::ResetState => key(egui::Key::R),
+            Action::AddNoise => key(egui::Key::N),
+            Action::Undo => command(egui::Key::Z),
+            Action::Redo => command(egui::Key::Y),
+            Action::CommandPalette => Some(Shortcut::new(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::P,
+            )),
+            Action::LoadPattern(i) => PATTERN_KEYS.get(i).and_then(|k| key(*k)),
+            Action::ForgetAll | Action::SaveState => None,
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::StartStop => write!(f, "Start or stop the network"),
+            Action::StepNode => write!(f, "Step a node"),
+            Action::StepSweep => write!(f, "Step a sweep"),
+            Action::Converge => write!(f, "Converge"),
+            Action::LearnCurrentState => write!(f, "Learn the current state"),
+            Action::ForgetAll => write!(f, "Forget all"),
+            Action::ResetState => write!(f, "Reset to the starting state"),
+            Action::SaveState => write!(f, "Set the current state as starting state"),
+            Action::AddNoise => write!(f, "Add noise to the state"),
+            Action::Undo => write!(f, "Undo"),
+            Action::Redo => write!(f, "Redo"),
+            Action::CommandPalette => write!(f, "Open the command palette"),
+            Action::LoadPattern(i) => write!(f, "Load pattern {}", i + 1),
+        }
+    }
+}
+
+// egui's KeyboardShortcut can't be saved, so the shortcuts are kept as the two parts it's made of
+#[derive(Debug, PartialEq, Eq, Clone, Copy, serde::Deserialize, serde::Serialize)]
+pub struct Shortcut {
+    pub modifiers: egui::Modifiers,
+    pub key: egui::Key,
+}
+
+impl Shortcut {
+    pub fn new(modifiers: egui::Modifiers, key: egui::Key) -> Self {
+        Self { modifiers, key }
+    }
+}
+
+impl Display for Shortcut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let shortcut = egui::KeyboardShortcut::new(self.modifiers, self.key);
+        let is_mac = cfg!(target_os = "macos");
+        write!(
+            f,
+            "{}",
+            shortcut.format(&egui::ModifierNames::NAMES, is_mac)
+        )
+    }
+}
+
+// The shortcuts written out, the panels add them to the tooltips of their buttons
+#[derive(Debug, Default, Clone)]
+pub struct ShortcutHints(HashMap<Action, String>);
+
+impl ShortcutHints {
+    pub fn get(&self, action: Action) -> Option<&str> {
+        self.0.get(&action).map(|shortcut| shortcut.as_str())
+    }
+
+    // An empty text adds a tooltip only if the action has a shortcut
+    pub fn tooltip(&self, response: egui::Response, action: Action, text: &str) -> egui::Response {
+        match (self.get(action), text.is_empty()) {
+            (Some(shortcut), true) => response.on_hover_text(format!("Shortcut: {}", shortcut)),
+            (Some(shortcut), false) => {
+                response.on_hover_text(format!("{}\nShortcut: {}", text, shortcut))
+            }
+            (None, true) => response,
+            (None, false) => response.on_hover_text(text),
+        }
+    }
+}
+
+pub struct Shortcuts {
+    // Every action with its shortcut, in the order of Action::all
+    bindings: Vec<(Action, Option<Shortcut>)>,
+    bindings_changed: bool,
+    // The actions asked for in the last frame, from the keyboard or from the palette
+    triggered: Vec<Action>,
+    palette_open: bool,
+    palette_query: String,
+    palette_selected: usize,
+    editor_open: bool,
+    // The action whose shortcut is being changed, the next key pressed becomes its shortcut
+    recording: Option<Action>,
+}
+
+impl Shortcuts {
+    // The saved shortcuts replace the default ones, the actions that didn't exist when they were saved keep theirs
+    pub fn new(saved: &[(Action, Option<Shortcut>)]) -> Self {
+        let bindings = Action::all()
+            .into_iter()
+            .map(|action| {
+                let shortcut = match saved.iter().find(|(saved, _)| *saved == action) {
+                    Some((_, shortcut)) => *shortcut,
+                    None => action.default_shortcut(),
+                };
+                (action, shortcut)
+            })
+            .collect();
+        Self {
+            bindings,
+            bindings_changed: false,
+            triggered: Vec::new(),
+            palette_open: false,
+            palette_query: String::new(),
+            palette_selected: 0,
+            editor_open: false,
+            recording: None,
+        }
+    }
+
+    pub fn generate_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("⌨ Shortcuts").clicked() {
+                self.editor_open = true;
+            }
+            let response = ui.button("🔍 Commands");
+            let text = "Every action, searchable by name";
+            if self.hint(Action::CommandPalette, response, text).clicked() {
+                self.open_palette();
+            }
+        });
+    }
+
+    // Reads the keyboard and draws the palette and the shortcuts window, it has to be called every frame
+    pub fn generate_windows(&mut self, ctx: &egui::Context) {
+        self.triggered.clear();
+        self.bindings_changed = false;
+
+        if self.recording.is_some() {
+            self.record_shortcut(ctx);
+        } else if !ctx.wants_keyboard_input() {
+            // The text fields get the keys first, otherwise typing an L would learn the state
+            ctx.input_mut(|i| {
+                for (action, shortcut) in self.bindings.iter() {
+                    if let Some(shortcut) = shortcut {
+                        if i.consume_key(shortcut.modifiers, shortcut.key) {
+                            self.triggered.push(*action);
+                        }
+                    }
+                }
+            });
+        }
+
+        if self.triggered.contains(&Action::CommandPalette) {
+            self.open_palette();
+        }
+        self.palette_ui(ctx);
+        self.editor_ui(ctx);
+    }
+
+    fn open_palette(&mut self) {
+        self.palette_open = true;
+        self.palette_query.clear();
+        self.palette_selected = 0;
+    }
+
+    fn palette_ui(&mut self, ctx: &egui::Context) {
+        if !self.palette_open {
+            return;
+        }
+
+        let mut chosen = None;
+        let mut open = true;
+        egui::Window::new("Command palette")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.palette_query).hint_text("Type to search"),
+                );
+                response.request_focus();
+
+                let query = self.palette_query.to_lowercase();
+                let matches: Vec<Action> = Action::all()
+                    .into_iter()
+                    .filter(|action| *action != Action::CommandPalette)
+                    .filter(|action| action.to_string().to_lowercase().contains(&query))
+                    .collect();
+
+                let (up, down, enter, escape) = ui.input_mut(|i| {
+                    (
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                    )
+                });
+                if down {
+                    self.palette_selected += 1;
+                }
+                if up {
+                    self.palette_selected = self.palette_selected.saturating_sub(1);
+                }
+                self.palette_selected = self.palette_selected.min(matches.len().saturating_sub(1));
+
+                if matches.is_empty() {
+                    ui.label("No command matches the search");
+                }
+                egui::Grid::new("command_palette_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (n, action) in matches.iter().enumerate() {
+                            let selected = n == self.palette_selected;
+                            if ui.selectable_label(selected, action.to_string()).clicked() {
+                                chosen = Some(*action);
+                            }
+                            if let Some(shortcut) = self.shortcut(*action) {
+                                ui.weak(shortcut.to_string());
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                if enter {
+                    chosen = matches.get(self.palette_selected).copied();
+                }
+                if escape {
+                    self.palette_open = false;
+                }
+            });
+
+        if let Some(action) = chosen {
+            self.triggered.push(action);
+            self.palette_open = false;
+        }
+        if !open {
+            self.palette_open = false;
+        }
+    }
+
+    fn editor_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.editor_open;
+        egui::Window::new("Keyboard shortcuts")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Click on a shortcut and press the new keys.\nEscape cancels, Backspace removes the shortcut");
+                ui.add_space(5.0);
+                egui::Grid::new("shortcuts_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (action, shortcut) in self.bindings.iter() {
+                            ui.label(action.to_string());
+                            let text = if self.recording == Some(*action) {
+                                "Press the keys...".to_string()
+                            } else {
+                                match shortcut {
+                                    Some(shortcut) => shortcut.to_string(),
+                                    None => "None".to_string(),
+                                }
+                            };
+                            if ui.button(text).clicked() {
+                                self.recording = Some(*action);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.add_space(5.0);
+                if ui.button("Reset to defaults").clicked() {
+                    for (action, shortcut) in self.bindings.iter_mut() {
+                        *shortcut = action.default_shortcut();
+                    }
+                    self.recording = None;
+                    self.bindings_changed = true;
+                }
+            });
+        self.editor_open = open;
+        if !self.editor_open {
+            self.recording = None;
+        }
+    }
+
+    // The key is taken from the events, so that it doesn't reach the other widgets too
+    fn record_shortcut(&mut self, ctx: &egui::Context) {
+        let action = match self.recording {
+            Some(action) => action,
+            None => return,
+        };
+        let pressed = ctx.input_mut(|i| {
+            let pressed = i.events.iter().find_map(|event| match event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some((*key, *modifiers)),
+                _ => None,
+            });
+            if let Some((key, modifiers)) = pressed {
+                i.consume_key(modifiers, key);
+            }
+            pressed
+        });
+        let (key, modifiers) = match pressed {
+            Some(pressed) => pressed,
+            None => return,
+        };
+
+        self.recording = None;
+        let shortcut = match key {
+            egui::Key::Escape => return,
+            egui::Key::Backspace | egui::Key::Delete => None,
+            // Ctrl on windows and linux is Cmd on mac, so the shortcuts work on both
+            _ => {
+                let mut kept = egui::Modifiers::NONE;
+                kept.alt = modifiers.alt;
+                kept.shift = modifiers.shift;
+                kept.command = modifiers.command || modifiers.ctrl;
+                Some(Shortcut::new(kept, key))
+            }
+        };
+
+        // A shortcut does a single thing, the action that had it loses it
+        for (other, other_shortcut) in self.bindings.iter_mut() {
+            if *other == action {
+                *other_shortcut = shortcut;
+            } else if shortcut.is_some() && *other_shortcut == shortcut {
+                *other_shortcut = None;
+            }
+        }
+        self.bindings_changed = true;
+    }
+
+    fn hint(&self, action: Action, response: egui::Response, text: &str) -> egui::Response {
+        self.hints().tooltip(response, action, text)
+    }
+
+    // Getters
+
+    pub fn shortcut(&self, action: Action) -> Option<Shortcut> {
+        self.bindings
+            .iter()
+            .find(|(other, _)| *other == action)
+            .and_then(|(_, shortcut)| *shortcut)
+    }
+
+    pub fn triggered_actions(&self) -> &[Action] {
+        &self.triggered
+    }
+
+    pub fn have_bindings_changed(&self) -> bool {
+        self.bindings_changed
+    }
+
+    pub fn get_bindings(&self) -> Vec<(Action, Option<Shortcut>)> {
+        self.bindings.clone()
+    }
+
+    pub fn hints(&self) -> ShortcutHints {
+        ShortcutHints(
+            self.bindings
+                .iter()
+                .filter_map(|(action, shortcut)| Some((*action, (*shortcut)?.to_string())))
+                .collect(),
+        )
+    }
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
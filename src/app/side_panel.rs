@@ -1,20 +1,69 @@
 use crate::app::hop_net;
+use crate::app::hop_net::boltzmann_machine;
+use crate::app::hop_net::learning_rules::LearningRule;
+use crate::app::hop_net::learning_rules::LearningRuleKind;
+use crate::app::hop_net::learning_rules::TrainingProgress;
+use crate::app::hop_net::scalar::Precision;
+use crate::app::hop_net::unlearning::Unlearning;
+use crate::app::hop_net::unlearning::UnlearningReport;
+use crate::app::shortcuts::Action;
+use crate::app::shortcuts::ShortcutHints;
 use crate::app::utilities;
 use strum::IntoEnumIterator;
 
+// The sides of the square grids the user can pick, for both layers
+pub const MIN_SIDE: usize = 2;
+pub const MAX_SIDE: usize = 99;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum SpeedUnit {
+    Steps,
+    Sweeps,
+    Max,
+}
+
 pub struct SidePanel {
     reset: bool,
     save_current_state: bool,
     node_dim: utilities::EditableValue<f32>,
     network: utilities::EditableValue<hop_net::NetworkType>,
+    // The type of the network that is running, the combo may hold one that wasn't applied yet
+    net_type: hop_net::NetworkType,
     state_size: utilities::EditableValue<usize>,
     text_holder: String,
+    // Only used by the networks with a second layer
+    output_layer_size: utilities::EditableValue<usize>,
+    output_text_holder: String,
     learn_current_state: bool,
+    // The optimization network has no memories, it can't learn the state
+    is_solving: bool,
     forget_all: bool,
     start_stepping_pressed: bool,
     stop_stepping_pressed: bool,
     is_stepping: bool,
-    remember_speed: utilities::EditableValue<u64>,
+    step_node_pressed: bool,
+    step_sweep_pressed: bool,
+    converge_pressed: bool,
+    // The speed is remembered for each unit, so that going back and forth between them doesn't lose it
+    steps_per_second: u64,
+    sweeps_per_second: u64,
+    speed_unit: SpeedUnit,
+    speed_changed: bool,
+    update_mode: utilities::EditableValue<hop_net::UpdateMode>,
+    // Only used by the hebbian network
+    precision: utilities::EditableValue<Precision>,
+    learning_rule: utilities::EditableValue<LearningRule>,
+    // The rule the network is using, the one being edited is sent only when Apply is pressed
+    applied_learning_rule: LearningRule,
+    // The last epoch of the iterative learning rules
+    training_progress: Option<TrainingProgress>,
+    // Only used by the single layer networks that don't play sequences
+    unlearning: Unlearning,
+    unlearn_pressed: bool,
+    // Dreams done out of the total, it's Some only while the network is dreaming
+    unlearning_progress: Option<(usize, usize)>,
+    unlearning_report: Option<UnlearningReport>,
+    shortcut_hints: ShortcutHints,
 }
 
 impl SidePanel {
@@ -23,15 +72,38 @@ impl SidePanel {
             node_dim: utilities::EditableValue::new(20.0),
             reset: false,
             network: utilities::EditableValue::new(network_type),
+            net_type: network_type,
             state_size: utilities::EditableValue::new(state_size),
             text_holder: state_size.to_string(),
+            output_layer_size: utilities::EditableValue::new(5),
+            output_text_holder: 5.to_string(),
             save_current_state: false,
             learn_current_state: false,
+            is_solving: false,
             forget_all: false,
             start_stepping_pressed: false,
             stop_stepping_pressed: false,
             is_stepping: false,
-            remember_speed: utilities::EditableValue::new(10),
+            step_node_pressed: false,
+            step_sweep_pressed: false,
+            converge_pressed: false,
+            steps_per_second: 10,
+            sweeps_per_second: 2,
+            speed_unit: SpeedUnit::Steps,
+            speed_changed: false,
+            update_mode: utilities::EditableValue::new(hop_net::UpdateMode::Asynchronous),
+            precision: utilities::EditableValue::new(Precision::F64),
+            learning_rule: utilities::EditableValue::new(LearningRule::default()),
+            applied_learning_rule: LearningRule::default(),
+            training_progress: None,
+            unlearning: Unlearning {
+                dreams: 100,
+                rate: 0.01,
+            },
+            unlearn_pressed: false,
+            unlearning_progress: None,
+            unlearning_report: None,
+            shortcut_hints: ShortcutHints::default(),
         }
     }
 
@@ -43,11 +115,12 @@ impl SidePanel {
         ui.add_space(std_space);
 
         // Start of state reset
+        let hints = &self.shortcut_hints;
         let response = ui.add(egui::Button::new("Reset to starting state"));
-        self.reset = response.clicked();
+        self.reset = hints.tooltip(response, Action::ResetState, "").clicked();
         ui.add_space(std_space / 3.0);
         let response = ui.add(egui::Button::new("Set current state as starting state"));
-        self.save_current_state = response.clicked();
+        self.save_current_state = hints.tooltip(response, Action::SaveState, "").clicked();
         // End of state reset
 
         ui.add_space(std_space);
@@ -76,17 +149,23 @@ impl SidePanel {
 
         // Start of learning section
         ui.horizontal(|ui| {
-            let response = ui.button("Learn current state");
-            self.learn_current_state = response.clicked();
+            let hints = &self.shortcut_hints;
+            let response =
+                ui.add_enabled(!self.is_solving, egui::Button::new("Learn current state"));
+            self.learn_current_state = hints
+                .tooltip(response, Action::LearnCurrentState, "")
+                .on_disabled_hover_text("The network is solving a problem, it can't learn patterns")
+                .clicked();
             let response = ui.button("Forget all");
-            self.forget_all = response.clicked();
+            self.forget_all = hints.tooltip(response, Action::ForgetAll, "").clicked();
         });
         ui.label("Memory recovery");
         ui.horizontal(|ui| {
+            let hints = &self.shortcut_hints;
             let response = ui.selectable_label(!self.is_stepping, "Start");
-            self.start_stepping_pressed = response.clicked();
+            self.start_stepping_pressed = hints.tooltip(response, Action::StartStop, "").clicked();
             let response = ui.selectable_label(self.is_stepping, "Stop");
-            self.stop_stepping_pressed = response.clicked();
+            self.stop_stepping_pressed = hints.tooltip(response, Action::StartStop, "").clicked();
 
             if self.start_stepping_pressed {
                 self.is_stepping = true;
@@ -94,9 +173,100 @@ impl SidePanel {
                 self.is_stepping = false;
             }
         });
-        let response =
-            ui.add(egui::Slider::new(&mut self.remember_speed.value, 1..=600).text("step/sec"));
-        self.remember_speed.changed = response.dragged();
+        ui.horizontal(|ui| {
+            let hints = &self.shortcut_hints;
+            let response = ui.add_enabled(!self.is_stepping, egui::Button::new("Step node"));
+            self.step_node_pressed = hints
+                .tooltip(
+                    response,
+                    Action::StepNode,
+                    "Updates a single node, or all of them in synchronous mode",
+                )
+                .clicked();
+            let response = ui.add_enabled(!self.is_stepping, egui::Button::new("Step sweep"));
+            self.step_sweep_pressed = hints
+                .tooltip(
+                    response,
+                    Action::StepSweep,
+                    "Gives every node the chance to update once",
+                )
+                .clicked();
+            let response = ui.add_enabled(!self.is_stepping, egui::Button::new("Converge"));
+            self.converge_pressed = hints
+                .tooltip(
+                    response,
+                    Action::Converge,
+                    "Runs without pauses until the network settles, only the state it ends in is shown",
+                )
+                .clicked();
+        });
+
+        let old_speed = self.get_stepping_speed();
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.speed_unit, SpeedUnit::Steps, "steps");
+            ui.radio_value(&mut self.speed_unit, SpeedUnit::Sweeps, "sweeps");
+            ui.radio_value(&mut self.speed_unit, SpeedUnit::Max, "max speed");
+        });
+        match self.speed_unit {
+            SpeedUnit::Steps => ui.add(
+                egui::Slider::new(&mut self.steps_per_second, 1..=10_000)
+                    .logarithmic(true)
+                    .text("step/sec"),
+            ),
+            SpeedUnit::Sweeps => ui.add(
+                egui::Slider::new(&mut self.sweeps_per_second, 1..=100)
+                    .logarithmic(true)
+                    .text("sweep/sec"),
+            ),
+            SpeedUnit::Max => ui
+                .label("The network doesn't wait between the steps, the grid is redrawn as often as the screen allows"),
+        };
+        self.speed_changed = self.get_stepping_speed() != old_speed;
+
+        let old_update_mode = self.update_mode.value;
+        egui::ComboBox::new("update_mode_combo", "update mode")
+            .selected_text(self.update_mode.value.to_string())
+            .show_ui(ui, |ui| {
+                for mode in hop_net::UpdateMode::iter() {
+                    ui.selectable_value(&mut self.update_mode.value, mode, mode.to_string());
+                }
+            });
+        self.update_mode.changed = old_update_mode != self.update_mode.value;
+
+        let old_precision = self.precision.value;
+        if self.net_type == hop_net::NetworkType::SquareDiscrete {
+            egui::ComboBox::new("precision_combo", "weights type")
+                .selected_text(self.precision.value.to_string())
+                .show_ui(ui, |ui| {
+                    // The integer types only for the rules that can learn with them
+                    for precision in Precision::iter()
+                        .filter(|precision| self.applied_learning_rule.kind.works_with(*precision))
+                    {
+                        ui.selectable_value(
+                            &mut self.precision.value,
+                            precision,
+                            precision.to_string(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("The integer types use less memory, but stop learning once the weights reach their biggest value");
+        }
+        self.precision.changed = old_precision != self.precision.value;
+
+        self.learning_rule.changed = false;
+        if self.net_type == hop_net::NetworkType::SquareDiscrete {
+            self.learning_rule_ui(ui);
+        }
+        self.training_progress_ui(ui);
+
+        self.unlearn_pressed = false;
+        if matches!(
+            self.net_type,
+            hop_net::NetworkType::SquareDiscrete | hop_net::NetworkType::StorkeySquareDiscrete
+        ) {
+            self.unlearning_ui(ui);
+        }
         // End of learning section
 
         ui.add_space(std_space);
@@ -125,7 +295,7 @@ impl SidePanel {
             if response.clicked() {
                 let num = self.text_holder.parse::<usize>();
                 if let Ok(num) = num {
-                    if num > 1 && num < 100 && num != self.state_size.value {
+                    if (MIN_SIDE..=MAX_SIDE).contains(&num) && num != self.state_size.value {
                         self.state_size.value = num;
                         // Since the state size changed, we set the changed flag to true
                         self.state_size.changed = true;
@@ -135,17 +305,239 @@ impl SidePanel {
                 self.text_holder = self.state_size.value.to_string();
             }
         });
+
+        self.output_layer_size.changed = false;
+        if self.network.value.has_two_layers() {
+            if self.network.value == hop_net::NetworkType::RbmSquareDiscrete {
+                ui.label("Hidden layer size:");
+            } else {
+                ui.label("Output layer size:");
+            }
+            ui.horizontal(|ui| {
+                let text_edit_singleline = egui::TextEdit::singleline(&mut self.output_text_holder)
+                    .desired_width(50.0)
+                    .min_size((10.0, 0.0).into());
+
+                ui.add(text_edit_singleline);
+                if ui.button("Apply").clicked() {
+                    let num = self.output_text_holder.parse::<usize>();
+                    if let Ok(num) = num {
+                        if (MIN_SIDE..=MAX_SIDE).contains(&num)
+                            && num != self.output_layer_size.value
+                        {
+                            self.output_layer_size.value = num;
+                            self.output_layer_size.changed = true;
+                        }
+                    }
+                    self.output_text_holder = self.output_layer_size.value.to_string();
+                }
+            });
+        }
         // End of state size selection
     }
 
+    fn learning_rule_ui(&mut self, ui: &mut egui::Ui) {
+        let rule = &mut self.learning_rule.value;
+        let precision = self.precision.value;
+        ui.horizontal(|ui| {
+            egui::ComboBox::new("learning_rule_combo", "")
+                .selected_text(rule.kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in LearningRuleKind::iter().filter(|kind| kind.works_with(precision)) {
+                        ui.selectable_value(&mut rule.kind, kind, kind.to_string());
+                    }
+                });
+            // Changing the rule makes the network learn everything again, so it's done only on request
+            self.learning_rule.changed = ui
+                .add_enabled(rule.kind.works_with(precision), egui::Button::new("Apply"))
+                .on_hover_text(
+                    "Forgets everything and learns the stored patterns again with this rule",
+                )
+                .on_disabled_hover_text(format!(
+                    "The {} rule can't learn with {} weights, its updates round to 0",
+                    rule.kind, precision
+                ))
+                .clicked();
+        });
+        if self.learning_rule.changed {
+            self.applied_learning_rule = *rule;
+        }
+
+        match rule.kind {
+            LearningRuleKind::Decaying => {
+                ui.add(egui::Slider::new(&mut rule.decay, 0.5..=1.0).text("decay"));
+            }
+            LearningRuleKind::Perceptron => {
+                ui.add(egui::Slider::new(&mut rule.margin, 0.0..=3.0).text("margin κ"));
+            }
+            LearningRuleKind::Delta => {
+                ui.add(egui::Slider::new(&mut rule.rate, 0.01..=1.0).text("learning rate"));
+            }
+            _ => {}
+        }
+        if rule.kind.is_iterative() {
+            ui.add(egui::Slider::new(&mut rule.max_epochs, 1..=1000).text("max epochs"));
+        }
+    }
+
+    // The iterative learning rules and the boltzmann machines report how their training is going
+    fn training_progress_ui(&self, ui: &mut egui::Ui) {
+        match self.training_progress {
+            Some(progress) if !progress.done => {
+                ui.add(
+                    egui::ProgressBar::new(progress.epoch as f32 / progress.max_epochs as f32)
+                        .text(format!(
+                            "epoch {} of {}",
+                            progress.epoch, progress.max_epochs
+                        )),
+                );
+            }
+            Some(progress) => {
+                let error = match self.net_type {
+                    hop_net::NetworkType::BoltzmannSquareDiscrete => {
+                        format!("correlation error {:.2e}", progress.error)
+                    }
+                    hop_net::NetworkType::RbmSquareDiscrete => {
+                        format!("reconstruction error {:.2e}", progress.error)
+                    }
+                    _ => match self.applied_learning_rule.kind {
+                        LearningRuleKind::Delta => {
+                            format!("mean squared error {:.2e}", progress.error)
+                        }
+                        _ => format!("{} updates in the last one", progress.error),
+                    },
+                };
+                ui.label(format!(
+                    "Last training: {} epochs, {}",
+                    progress.epoch, error
+                ));
+            }
+            None => {}
+        }
+    }
+
+    fn unlearning_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Unlearning").show(ui, |ui| {
+            ui.add(egui::Slider::new(&mut self.unlearning.dreams, 1..=1000).text("dreams"));
+            ui.add(
+                egui::Slider::new(&mut self.unlearning.rate, 0.001..=0.1)
+                    .logarithmic(true)
+                    .text("rate ε"),
+            );
+
+            match self.unlearning_progress {
+                Some((done, total)) => {
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / total as f32)
+                            .text(format!("{} of {} dreams", done, total)),
+                    );
+                }
+                None => {
+                    self.unlearn_pressed = ui
+                        .button("Dream")
+                        .on_hover_text("Relaxes from random states and makes the attractors it finds shallower,\nthe spurious ones are found more often, so they go away first")
+                        .clicked();
+                }
+            }
+
+            if let Some(report) = &self.unlearning_report {
+                egui::Grid::new("unlearning_grid")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        ui.label("");
+                        ui.label("before");
+                        ui.label("after");
+                        ui.end_row();
+
+                        ui.label("fixed points");
+                        ui.label(format!(
+                            "{} of {}",
+                            report.before.stable_patterns, report.pattern_count
+                        ));
+                        ui.label(format!(
+                            "{} of {}",
+                            report.after.stable_patterns, report.pattern_count
+                        ));
+                        ui.end_row();
+
+                        ui.label("recall rate");
+                        ui.label(format!("{:.0}%", report.before.recall_rate * 100.0));
+                        ui.label(format!("{:.0}%", report.after.recall_rate * 100.0));
+                        ui.end_row();
+                    });
+                ui.label(format!(
+                    "{} dreams ended in spurious states",
+                    report.spurious_dreams
+                ));
+            }
+        });
+    }
+
     // Getters
 
-    pub fn get_stepping_speed(&self) -> u64 {
-        self.remember_speed.value
+    pub fn get_stepping_speed(&self) -> hop_net::StepSpeed {
+        match self.speed_unit {
+            SpeedUnit::Steps => hop_net::StepSpeed::StepsPerSecond(self.steps_per_second),
+            SpeedUnit::Sweeps => hop_net::StepSpeed::SweepsPerSecond(self.sweeps_per_second),
+            SpeedUnit::Max => hop_net::StepSpeed::Max,
+        }
     }
 
     pub fn has_stepping_speed_changed(&self) -> bool {
-        self.remember_speed.changed
+        self.speed_changed
+    }
+
+    pub fn step_node_pressed(&self) -> bool {
+        self.step_node_pressed
+    }
+
+    pub fn step_sweep_pressed(&self) -> bool {
+        self.step_sweep_pressed
+    }
+
+    pub fn converge_pressed(&self) -> bool {
+        self.converge_pressed
+    }
+
+    pub fn get_update_mode(&self) -> hop_net::UpdateMode {
+        self.update_mode.value
+    }
+
+    pub fn has_update_mode_changed(&self) -> bool {
+        self.update_mode.changed
+    }
+
+    pub fn get_precision(&self) -> Precision {
+        self.precision.value
+    }
+
+    pub fn has_precision_changed(&self) -> bool {
+        self.precision.changed
+    }
+
+    pub fn get_learning_rule(&self) -> LearningRule {
+        self.learning_rule.value
+    }
+
+    pub fn has_learning_rule_changed(&self) -> bool {
+        self.learning_rule.changed
+    }
+
+    pub fn unlearn_pressed(&self) -> bool {
+        self.unlearn_pressed
+    }
+
+    pub fn get_unlearning(&self) -> Unlearning {
+        self.unlearning
+    }
+
+    pub fn is_unlearning(&self) -> bool {
+        self.unlearning_progress.is_some()
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training_progress
+            .map_or(false, |progress| !progress.done)
     }
 
     pub fn start_stepping_pressed(&self) -> bool {
@@ -180,6 +572,15 @@ impl SidePanel {
         self.network.changed
     }
 
+    // The side of the square grid, get_state_size is the number of nodes in it
+    pub fn get_state_side(&self) -> usize {
+        self.state_size.value
+    }
+
+    pub fn get_output_layer_side(&self) -> usize {
+        self.output_layer_size.value
+    }
+
     pub fn get_state_size(&self) -> usize {
         self.state_size.value.pow(2)
     }
@@ -188,6 +589,19 @@ impl SidePanel {
         self.state_size.changed
     }
 
+    pub fn has_output_layer_size_changed(&self) -> bool {
+        self.output_layer_size.changed
+    }
+
+    // The number of nodes in each layer of the given network type
+    pub fn get_layer_sizes(&self, network_type: hop_net::NetworkType) -> Vec<usize> {
+        if network_type.has_two_layers() {
+            vec![self.get_state_size(), self.output_layer_size.value.pow(2)]
+        } else {
+            vec![self.get_state_size()]
+        }
+    }
+
     pub fn save_current_state(&self) -> bool {
         self.save_current_state
     }
@@ -201,4 +615,78 @@ impl SidePanel {
     pub fn set_is_stepping(&mut self, is_stepping: bool) {
         self.is_stepping = is_stepping;
     }
+
+    pub fn set_is_solving(&mut self, is_solving: bool) {
+        self.is_solving = is_solving;
+    }
+
+    pub fn set_training_progress(&mut self, progress: TrainingProgress) {
+        self.training_progress = Some(progress);
+    }
+
+    // The network crashed, the training and the dreams it was busy with are over
+    pub fn cancel_jobs(&mut self) {
+        self.training_progress = None;
+        self.unlearning_progress = None;
+    }
+
+    pub fn set_unlearning_progress(&mut self, done: usize, total: usize) {
+        self.unlearning_progress = Some((done, total));
+    }
+
+    pub fn set_unlearning_report(&mut self, report: UnlearningReport) {
+        self.unlearning_progress = None;
+        self.unlearning_report = Some(report);
+    }
+
+    // Called when the network is about to learn, so that the gui keeps updating until the training is over.
+    // The one-shot rules send no progress, so nothing changes for them
+    pub fn start_training(&mut self, network_type: hop_net::NetworkType) {
+        let max_epochs = match network_type {
+            hop_net::NetworkType::SquareDiscrete
+                if self.applied_learning_rule.kind.is_iterative() =>
+            {
+                self.applied_learning_rule.max_epochs
+            }
+            hop_net::NetworkType::BoltzmannSquareDiscrete
+            | hop_net::NetworkType::RbmSquareDiscrete => boltzmann_machine::TRAINING_EPOCHS,
+            _ => return,
+        };
+        self.training_progress = Some(TrainingProgress {
+            epoch: 0,
+            max_epochs,
+            error: 0.0,
+            done: false,
+        });
+    }
+
+    pub fn set_shortcut_hints(&mut self, hints: ShortcutHints) {
+        self.shortcut_hints = hints;
+    }
+
+    // Used when the network picks its own mode, it doesn't count as a change
+    pub fn set_update_mode(&mut self, mode: hop_net::UpdateMode) {
+        self.update_mode.value = mode;
+    }
+
+    // The type of the network that is running, the settings of the combo only count once they are applied
+    pub fn set_net_type(&mut self, network_type: hop_net::NetworkType) {
+        self.net_type = network_type;
+    }
+
+    // Used when the network type is decided by something else than the user, it doesn't count as a change
+    pub fn set_selected_network(&mut self, network_type: hop_net::NetworkType) {
+        self.network.value = network_type;
+    }
+
+    // Used when the size of the network is decided by something else than the user
+    pub fn set_state_size(&mut self, side: usize) {
+        self.state_size.value = side;
+        self.text_holder = side.to_string();
+    }
+
+    pub fn set_output_layer_size(&mut self, side: usize) {
+        self.output_layer_size.value = side;
+        self.output_text_holder = side.to_string();
+    }
 }
@@ -1,14 +1,150 @@
 use crate::app::hop_net;
+use crate::app::hop_net::bam_network;
+use crate::app::hop_net::boltzmann_machine::BoltzmannMachine;
 use crate::app::hop_net::classic_network;
+use crate::app::hop_net::cycle_detection::Cycle;
+use crate::app::hop_net::cycle_detection::CycleDetector;
+use crate::app::hop_net::damage;
+use crate::app::hop_net::diagnostics;
+use crate::app::hop_net::enumeration;
+use crate::app::hop_net::experiments;
+use crate::app::hop_net::landscape;
+use crate::app::hop_net::learning_rules::LearningRule;
+use crate::app::hop_net::matrix::Matrix;
+use crate::app::hop_net::network_error;
+use crate::app::hop_net::network_error::NetworkError;
+use crate::app::hop_net::optimization_network;
+use crate::app::hop_net::precision_adapter::PrecisionAdapter;
+use crate::app::hop_net::scalar::Precision;
+use crate::app::hop_net::sequence_network;
 use crate::app::hop_net::storkey_learning;
+use crate::app::hop_net::unlearning;
 use crate::app::hop_net::Net;
 use crate::app::hop_net::NetworkCommand;
+use crate::app::hop_net::NetworkStatus;
 use crate::app::hop_net::NetworkType;
+use crate::app::hop_net::NodeUpdate;
+use crate::app::hop_net::StepSpeed;
+use crate::app::hop_net::UpdateMode;
 use crate::app::NetworkResponse;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::time::Duration;
+use std::time::Instant;
+
+// How many sweeps the slow synapses of the delayed sequence network lag behind
+const SEQUENCE_DELAY: usize = 4;
+// The changes to the state are sent at most this often, about the rate the gui is drawn at.
+// A network slower than this sends every change as soon as it happens
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(16);
+// Converge gives up after this many sweeps, stochastic networks never settle
+const MAX_CONVERGE_SWEEPS: usize = 500;
+// The updated node is reported only if the user can follow the steps one by one
+const MAX_HIGHLIGHT_SPEED: f64 = 30.0;
+
+// What the network thread has to remember to build the network again
+pub struct NetSetup {
+    // None while the network is solving a problem
+    pub net_type: Option<NetworkType>,
+    // Only the hebbian network can store its weights in a precision other than f64,
+    // and learn with a rule other than its own
+    pub precision: Precision,
+    pub learning_rule: LearningRule,
+    // Raised by the gui when it sends a stop, the training checks it between the epochs.
+    // It's lowered when the stop gets to the network
+    pub interrupt: Arc<AtomicBool>,
+}
+
+pub fn new_hebbian_network(
+    size: usize,
+    precision: Precision,
+) -> Result<Box<dyn Net<f64>>, NetworkError> {
+    Ok(match precision {
+        Precision::F64 => Box::new(classic_network::ClassicNetworkDiscrete::<f64>::new(
+            size, None,
+        )?),
+        Precision::F32 => Box::new(PrecisionAdapter::new(Box::new(
+            classic_network::ClassicNetworkDiscrete::<f32>::new(size, None)?,
+        ))),
+        Precision::I16 => Box::new(PrecisionAdapter::new(Box::new(
+            classic_network::ClassicNetworkDiscrete::<i16>::new(size, None)?,
+        ))),
+        Precision::I8 => Box::new(PrecisionAdapter::new(Box::new(
+            classic_network::ClassicNetworkDiscrete::<i8>::new(size, None)?,
+        ))),
+    })
+}
+
+// Every network is built here, both when the thread starts and when the type changes.
+// The networks with two layers get the sizes of the first two, or a single layer and an empty one,
+// the others get as many nodes as all the layers together
+pub fn build_network(
+    net_type: NetworkType,
+    layer_sizes: &[usize],
+    precision: Precision,
+) -> Result<Box<dyn Net<f64>>, NetworkError> {
+    let size = layer_sizes.iter().sum();
+    Ok(match net_type {
+        NetworkType::SquareDiscrete => new_hebbian_network(size, precision)?,
+        NetworkType::StorkeySquareDiscrete => {
+            Box::new(storkey_learning::StorkeyLearningNetwork::new(size, None)?)
+        }
+        NetworkType::SequenceSquareDiscrete => {
+            Box::new(sequence_network::SequenceNetwork::new(size, None, 0)?)
+        }
+        NetworkType::DelayedSequenceSquareDiscrete => Box::new(
+            sequence_network::SequenceNetwork::new(size, None, SEQUENCE_DELAY)?,
+        ),
+        NetworkType::BamSquareDiscrete => match layer_sizes {
+            [input_size, output_size] => Box::new(bam_network::BamNetwork::new(
+                *input_size,
+                *output_size,
+                None,
+            )?),
+            _ => Box::new(bam_network::BamNetwork::new(size, 0, None)?),
+        },
+        NetworkType::BoltzmannSquareDiscrete => {
+            Box::new(BoltzmannMachine::fully_visible(size, None)?)
+        }
+        NetworkType::RbmSquareDiscrete => match layer_sizes {
+            [visible_size, hidden_size] => Box::new(BoltzmannMachine::restricted(
+                *visible_size,
+                *hidden_size,
+                None,
+            )?),
+            _ => Box::new(BoltzmannMachine::restricted(size, 0, None)?),
+        },
+    })
+}
+
+// Every new network gets the learning rule, the networks with their own rule ignore it.
+// The progress of the iterative rules is forwarded to the main thread, at most as often as the gui is drawn,
+// and a stop sent during a training ends it after the current epoch
+pub fn configure_learning(
+    net: &mut dyn Net<f64>,
+    setup: &NetSetup,
+    net_send: &Sender<NetworkResponse>,
+) {
+    net.set_learning_rule(setup.learning_rule);
+    let net_send = net_send.clone();
+    let interrupt = setup.interrupt.clone();
+    let mut last_sent: Option<Instant> = None;
+    net.set_training_callback(Box::new(move |mut progress| {
+        let stopped = interrupt.load(Ordering::Relaxed);
+        progress.done |= stopped;
+        if progress.done || last_sent.map_or(true, |time| time.elapsed() >= SNAPSHOT_INTERVAL) {
+            last_sent = Some(Instant::now());
+            let _ = net_send.send(NetworkResponse::TrainingProgress(progress));
+        }
+        !stopped
+    }));
+}
 
 pub fn get_message(
     channel: &Receiver<NetworkCommand>,
@@ -32,19 +168,50 @@ pub fn get_message(
     }
 }
 
+// Returns true if the state of the network changed, an error if the command was refused
+#[allow(clippy::too_many_arguments)]
 pub fn handle_message(
     net: &mut Box<dyn Net<f64>>,
     command: NetworkCommand,
     is_stepping: &mut bool,
     old_step_num: &mut usize,
-    stepping_speed: &mut Duration,
-) -> bool {
+    pacer: &mut Pacer,
+    stored_patterns: &mut Vec<Vec<f64>>,
+    original_weights: &mut Option<Vec<Matrix<f64>>>,
+    setup: &mut NetSetup,
+    net_send: &Sender<NetworkResponse>,
+) -> Result<bool, NetworkError> {
+    // Whatever changes the weights, but isn't damage, makes the saved weights outdated
+    if matches!(
+        command,
+        NetworkCommand::Learn(_)
+            | NetworkCommand::LearnAll(_)
+            | NetworkCommand::ResetWeights
+            | NetworkCommand::ChangeNetType(_)
+            | NetworkCommand::SetLayerSizes(_)
+            | NetworkCommand::Solve(_)
+            | NetworkCommand::SetPrecision(_)
+            | NetworkCommand::SetLearningRule(_)
+            | NetworkCommand::Unlearn(_)
+    ) {
+        *original_weights = None;
+    }
+
     match command {
         NetworkCommand::None => {}
 
         NetworkCommand::Learn(vec) => {
             net.learn(&vec);
             println!("{}", hop_net::state_vec_to_string(&vec));
+            stored_patterns.push(vec);
+            // The new pattern may have pushed the older ones out of their minima
+            send_pattern_stability(net.as_ref(), stored_patterns, net_send);
+        }
+
+        NetworkCommand::LearnAll(patterns) => {
+            net.learn_all(&patterns);
+            *stored_patterns = patterns;
+            send_pattern_stability(net.as_ref(), stored_patterns, net_send);
         }
 
         NetworkCommand::Go => {
@@ -54,68 +221,418 @@ pub fn handle_message(
 
         NetworkCommand::Stop => {
             *is_stepping = false;
+            // The training the stop was meant for is over, the next ones have to run
+            setup.interrupt.store(false, Ordering::Relaxed);
         }
 
         NetworkCommand::SetState(vec) => {
-            net.set_state(&vec);
+            let size_changed = vec.len() != net.get_state_ref().len();
+            net.set_state(&vec)?;
+            // Networks forget everything when their size changes
+            if size_changed {
+                stored_patterns.clear();
+                *original_weights = None;
+            }
             *old_step_num = net.get_steps();
         }
 
         NetworkCommand::SetSpeed(speed) => {
-            *stepping_speed = Duration::from_millis(1000 / speed);
+            pacer.set_speed(speed);
+        }
+
+        NetworkCommand::StepNode => {
+            let state_changed = net.step();
+            *old_step_num = net.get_steps();
+            send_node_update(net.as_ref(), state_changed, net_send);
+            return Ok(true);
+        }
+
+        NetworkCommand::StepSweep => {
+            // The last step that flipped a node, so that the sweep can tell if it reached a fixed point
+            for _ in 0..experiments::steps_per_sweep(net.as_ref()) {
+                if net.step() {
+                    *old_step_num = net.get_steps();
+                }
+            }
+            return Ok(true);
+        }
+
+        NetworkCommand::Converge => {
+            *is_stepping = false;
+            let cycle = experiments::converge(net.as_mut(), MAX_CONVERGE_SWEEPS);
+            *old_step_num = net.get_steps();
+            let mut responses = vec![NetworkResponse::NewState(net.get_state())];
+            match cycle {
+                Some(cycle) if cycle.period > 1 => {
+                    println!("Converged to a cycle of period {}", cycle.period);
+                    responses.push(NetworkResponse::CycleDetected(cycle));
+                }
+                Some(_) => println!("Converged to a fixed point"),
+                None => println!("Still changing after {} sweeps", MAX_CONVERGE_SWEEPS),
+            }
+            responses.push(NetworkResponse::Stopped);
+            for response in responses {
+                if net_send.send(response).is_err() {
+                    println!("Error sending the converged state");
+                }
+            }
         }
 
         NetworkCommand::ResetWeights => {
             net.reset_weights();
+            stored_patterns.clear();
         }
 
+        // If the new network has the same layers as the old one, it learns the same patterns and starts
+        // from the same state, so that the two can be compared on the same memories.
+        // Otherwise it starts with no memories, and the sizes of its layers are sent right after this command
         NetworkCommand::ChangeNetType(new_type) => {
-            let size = net.get_state().len();
-            *net = match new_type {
-                NetworkType::SquareDiscrete => {
-                    Box::new(classic_network::ClassicNetworkDiscrete::new(size, None))
-                }
-                NetworkType::StorkeySquareDiscrete => {
-                    Box::new(storkey_learning::StorkeyLearningNetwork::new(size, None))
-                }
+            let layer_sizes = net.get_layer_sizes();
+            let state = net.get_state();
+            let update_mode = net.get_update_mode();
+            *net = build_network(new_type, &layer_sizes, setup.precision)?;
+            setup.net_type = Some(new_type);
+            // The sequence networks keep their own mode, the gui knows it
+            if !new_type.is_sequence() {
+                net.set_update_mode(update_mode);
+            }
+            configure_learning(net.as_mut(), setup, net_send);
+
+            if net.get_layer_sizes() == layer_sizes {
+                net.learn_all(stored_patterns);
+                net.set_state(&state)?;
+                *old_step_num = net.get_steps();
+                send_pattern_stability(net.as_ref(), stored_patterns, net_send);
+            } else {
+                stored_patterns.clear();
+            }
+            return Ok(true);
+        }
+
+        NetworkCommand::SetUpdateMode(mode) => {
+            net.set_update_mode(mode);
+        }
+
+        // The hebbian network is built again with the new precision, and it learns again what it knew
+        NetworkCommand::SetPrecision(precision) => {
+            if !setup.learning_rule.kind.works_with(precision) {
+                return Err(NetworkError::RuleNeedsFloats {
+                    rule: setup.learning_rule.kind,
+                    precision,
+                });
+            }
+            setup.precision = precision;
+            if setup.net_type == Some(NetworkType::SquareDiscrete) {
+                let state = net.get_state();
+                let update_mode = net.get_update_mode();
+                *net = new_hebbian_network(state.len(), precision)?;
+                net.set_update_mode(update_mode);
+                configure_learning(net.as_mut(), setup, net_send);
+                net.learn_all(stored_patterns);
+                net.set_state(&state)?;
+                *old_step_num = net.get_steps();
+                return Ok(true);
+            }
+        }
+
+        // The network builds its weights again from scratch, training once on all the patterns it knows.
+        // The state is left as it is
+        NetworkCommand::SetLearningRule(rule) => {
+            if !rule.kind.works_with(setup.precision) {
+                return Err(NetworkError::RuleNeedsFloats {
+                    rule: rule.kind,
+                    precision: setup.precision,
+                });
+            }
+            setup.learning_rule = rule;
+            net.set_learning_rule(rule);
+            if setup.net_type == Some(NetworkType::SquareDiscrete) {
+                send_pattern_stability(net.as_ref(), stored_patterns, net_send);
+            }
+        }
+
+        NetworkCommand::Solve(config) => {
+            stored_patterns.clear();
+            let update_mode = net.get_update_mode();
+            setup.net_type = None;
+            *net = Box::new(optimization_network::OptimizationNetwork::new(&config));
+            net.set_update_mode(update_mode);
+            *old_step_num = net.get_steps();
+            return Ok(true);
+        }
+
+        NetworkCommand::SetLayerSizes(sizes) => {
+            if sizes != net.get_layer_sizes() {
+                stored_patterns.clear();
+            }
+            net.set_layer_sizes(&sizes)?;
+            *old_step_num = net.get_steps();
+        }
+
+        NetworkCommand::Damage(damage) => {
+            // Only the weights before the first damage are worth going back to
+            if original_weights.is_none() {
+                *original_weights = Some(damage::snapshot_weights(net.as_mut()));
+            }
+            damage::apply_damage(net.as_mut(), damage);
+        }
+
+        NetworkCommand::RestoreWeights => match original_weights.take() {
+            Some(weights) => damage::restore_weights(net.as_mut(), &weights),
+            None => return Err(NetworkError::NotDamaged),
+        },
+
+        NetworkCommand::RunDamageExperiment(experiment) => {
+            *is_stepping = false;
+            let points = experiments::run_damage_experiment(
+                net.as_mut(),
+                stored_patterns,
+                experiment,
+                &mut |done, total| {
+                    let _ = net_send.send(NetworkResponse::ExperimentProgress(done, total));
+                },
+            )?;
+            if net_send
+                .send(NetworkResponse::ExperimentResult(points))
+                .is_err()
+            {
+                println!("Error sending the experiment result");
+            }
+            // The experiment restores the state, but the gui may be showing one of the probes
+            return Ok(true);
+        }
+
+        // The dreams use the network to relax, so it can't be stepping at the same time
+        NetworkCommand::Unlearn(unlearning) => {
+            *is_stepping = false;
+            let report = unlearning::unlearn(
+                net.as_mut(),
+                stored_patterns,
+                unlearning,
+                &mut |done, total| {
+                    let _ = net_send.send(NetworkResponse::UnlearningProgress(done, total));
+                },
+            )?;
+            if net_send
+                .send(NetworkResponse::UnlearningResult(report))
+                .is_err()
+            {
+                println!("Error sending the unlearning result");
+            }
+            send_pattern_stability(net.as_ref(), stored_patterns, net_send);
+            *old_step_num = net.get_steps();
+            return Ok(true);
+        }
+
+        NetworkCommand::RunDiagnostics => {
+            send_diagnostics(net.as_ref(), stored_patterns, net_send);
+        }
+
+        NetworkCommand::EnumerateStates => {
+            let result =
+                enumeration::enumerate_states(net.as_ref(), stored_patterns, &mut |done, total| {
+                    let _ = net_send.send(NetworkResponse::EnumerationProgress(done, total));
+                });
+            if net_send
+                .send(NetworkResponse::EnumerationResult(result))
+                .is_err()
+            {
+                println!("Error sending the enumeration result");
+            }
+        }
+
+        NetworkCommand::ComputeLandscape(plane) => {
+            let landscape = landscape::compute_landscape(net.as_ref(), plane);
+            if net_send
+                .send(NetworkResponse::Landscape(landscape))
+                .is_err()
+            {
+                println!("Error sending the energy landscape");
+            }
+        }
+
+        NetworkCommand::GetStatus => {
+            let state = net.get_state_ref();
+            let thresholds = net.get_local_fields(&vec![0.0; state.len()]);
+            let status = NetworkStatus {
+                steps: net.get_steps(),
+                energy: enumeration::energy(net.as_ref(), state, &thresholds),
             };
-            return true;
+            if net_send.send(NetworkResponse::Status(status)).is_err() {
+                println!("Error sending the status of the network");
+            }
+        }
+
+        // A state that doesn't fit anymore was asked for before the layout changed, nobody is waiting for it
+        NetworkCommand::ComputeLocalFields(state) => {
+            if state.len() == net.get_state_ref().len() {
+                let fields = net.get_local_fields(&state);
+                if net_send
+                    .send(NetworkResponse::LocalFields(state, fields))
+                    .is_err()
+                {
+                    println!("Error sending the local fields");
+                }
+            }
+        }
+
+        NetworkCommand::Symmetrize => {
+            diagnostics::symmetrize(net.as_mut());
+            send_diagnostics(net.as_ref(), stored_patterns, net_send);
+        }
+
+        NetworkCommand::ClearDiagonal => {
+            diagnostics::clear_diagonal(net.as_mut());
+            send_diagnostics(net.as_ref(), stored_patterns, net_send);
         }
 
         _ => println!("An unimplemented command was recieved"),
     }
-    false
+    Ok(false)
+}
+
+fn send_pattern_stability(
+    net: &dyn Net<f64>,
+    stored_patterns: &[Vec<f64>],
+    net_send: &Sender<NetworkResponse>,
+) {
+    let unstable_bits = stored_patterns
+        .iter()
+        .map(|pattern| diagnostics::unstable_bits(net, pattern))
+        .collect();
+    let response = NetworkResponse::PatternStability(unstable_bits);
+    if net_send.send(response).is_err() {
+        println!("Error sending the stability of the patterns");
+    }
+}
+
+fn send_node_update(net: &dyn Net<f64>, flipped: bool, net_send: &Sender<NetworkResponse>) {
+    if let Some((index, field)) = net.get_last_update() {
+        let update = NodeUpdate {
+            index,
+            field,
+            flipped,
+        };
+        if net_send.send(NetworkResponse::NodeUpdated(update)).is_err() {
+            println!("Error sending the updated node");
+        }
+    }
+}
+
+// Returns false if the gui is gone
+fn send_enumeration_availability(net: &dyn Net<f64>, net_send: &Sender<NetworkResponse>) -> bool {
+    let reason = enumeration::unavailable_reason(net);
+    net_send
+        .send(NetworkResponse::EnumerationAvailability(reason))
+        .is_ok()
+}
+
+// Called at the end of every sweep, returns the cycle the network is in, if it closed one.
+// The asynchronous update visits the nodes in a random order, so a state can come back at the end of a sweep
+// without the network going around in circles: there only a sweep without flips stops it, as a fixed point
+fn record_sweep(
+    cycle_detector: &mut CycleDetector,
+    net: &dyn Net<f64>,
+    old_step_num: usize,
+) -> Option<Cycle> {
+    if net.get_update_mode() == UpdateMode::Asynchronous {
+        let sweep_changed =
+            net.get_steps().saturating_sub(old_step_num) < experiments::steps_per_sweep(net);
+        return (!sweep_changed).then(|| Cycle {
+            period: 1,
+            states: vec![net.get_state()],
+        });
+    }
+    let state_len = net.get_state_ref().len();
+    cycle_detector
+        .push(&net.get_dynamic_state())
+        .map(|mut cycle| {
+            // The user is only interested in the part of the state that can be seen
+            for state in cycle.states.iter_mut() {
+                state.truncate(state_len);
+            }
+            cycle
+        })
+}
+
+// A fixed point is only a stop, a longer cycle is shown to the user too. Returns false if the gui is gone
+fn send_cycle(cycle: Cycle, net_send: &Sender<NetworkResponse>) -> bool {
+    println!("Cycle of period {} detected", cycle.period);
+    if cycle.period > 1
+        && net_send
+            .send(NetworkResponse::CycleDetected(cycle))
+            .is_err()
+    {
+        return false;
+    }
+    net_send.send(NetworkResponse::Stopped).is_ok()
+}
+
+fn send_diagnostics(
+    net: &dyn Net<f64>,
+    stored_patterns: &[Vec<f64>],
+    net_send: &Sender<NetworkResponse>,
+) {
+    let report = diagnostics::diagnose(net, stored_patterns);
+    if net_send.send(NetworkResponse::Diagnostics(report)).is_err() {
+        println!("Error sending the diagnostics report");
+    }
 }
 
 pub fn start_net_thread(
     net_type: NetworkType,
     start_state: Vec<f64>,
-    step_speed: usize,
+    layer_sizes: Vec<usize>,
+    step_speed: StepSpeed,
     net_send: Sender<NetworkResponse>,
     net_recieve: Receiver<NetworkCommand>,
+    interrupt: Arc<AtomicBool>,
 ) -> std::thread::JoinHandle<()> {
     std::thread::spawn(move || {
         println!("Net thread up");
 
-        let std_err_fn = || {
-            panic!("Net thread closed unexpectedly");
-        };
-
         // -----------------------------Setup-----------------------------
-        let mut net: Box<dyn Net<f64>> = match net_type {
-            NetworkType::SquareDiscrete => Box::new(classic_network::ClassicNetworkDiscrete::new(
-                start_state.len(),
-                Some(&start_state),
-            )),
-            _ => {
-                panic!("Not implemented");
+        let mut net = match build_network(net_type, &layer_sizes, Precision::F64) {
+            Ok(net) => net,
+            Err(error) => {
+                // Nothing can be done without a network, the gui is told why before it notices the thread is gone
+                println!("The network couldn't be built: {}", error);
+                let _ = net_send.send(NetworkResponse::Error(error));
+                return;
+            }
+        };
+        if let Err(error) = net.set_state(&start_state) {
+            if net_send.send(NetworkResponse::Error(error)).is_err() {
+                return;
             }
+        }
+        let mut setup = NetSetup {
+            net_type: Some(net_type),
+            precision: Precision::F64,
+            learning_rule: LearningRule::default(),
+            interrupt,
         };
+        configure_learning(net.as_mut(), &setup, &net_send);
+        if !send_enumeration_availability(net.as_ref(), &net_send) {
+            return;
+        }
 
-        let mut sleep_time = Duration::from_millis((1000.0 / step_speed as f64) as u64);
+        let mut pacer = Pacer::new(step_speed);
         let mut is_stepping = false;
         let mut old_step_num = 0;
-        let max_steps_without_change = net.get_state().len() + 1;
+        // States are recorded once per sweep, so a fixed point shows up as a cycle of period 1
+        let mut cycle_detector = CycleDetector::default();
+        // The steps taken since the last sweep was completed, the step count of the network isn't
+        // reset by every command that changes its size or its mode
+        let mut steps_in_sweep = 0;
+        // The patterns learned by the network, in the order they were learned
+        let mut stored_patterns: Vec<Vec<f64>> = Vec::new();
+        // The weights before they were damaged
+        let mut original_weights = None;
+        // Where the network goes back to if it crashes
+        let mut checkpoint = Checkpoint::new(net_type, layer_sizes, net.as_ref());
+        // What the gui knows of the state, the changes are sent in batches against it
+        let mut sync = StateSync::new(net.as_ref());
 
         // -----------------------------Main loop-----------------------------
         loop {
@@ -128,50 +645,558 @@ pub fn start_net_thread(
             }
 
             let mess = mess.unwrap();
-            if mess != NetworkCommand::None {
-                let net_state_changed = handle_message(
-                    &mut net,
-                    mess,
-                    &mut is_stepping,
-                    &mut old_step_num,
-                    &mut sleep_time,
-                );
+            // The command is gone after it's handled, if the network crashes its name is all that's left
+            let mut during = String::from("a step");
+
+            // The commands the network can't carry out are refused with an error, this only catches the bugs.
+            // A panic anywhere in here doesn't kill the thread, the network goes back to the checkpoint instead,
+            // but only natively: the web build aborts on panics, and there the gui has to start a new thread.
+            // The closure returns false if the gui is gone
+            let iteration = panic::catch_unwind(AssertUnwindSafe(|| {
+                if mess != NetworkCommand::None {
+                    if let Err(error) = validate_command(net.as_ref(), setup.net_type, &mess) {
+                        return net_send.send(NetworkResponse::Error(error)).is_ok();
+                    }
+                    during = command_name(&mess);
+                    // The gui has to see every step taken before the command, a stop in particular
+                    if !sync.flush(net.as_ref(), &net_send) {
+                        return false;
+                    }
+                    let replayed = checkpoint
+                        .replays(net.as_ref(), &mess)
+                        .then(|| mess.clone());
+                    let is_set_state = matches!(mess, NetworkCommand::SetState(_));
+                    let is_sweep = mess == NetworkCommand::StepSweep;
+                    let weights_changed = changes_weights(&mess);
+                    // Whatever the command was, the states seen so far may not be part of the same trajectory,
+                    // unless it only looked at the network or took it a sweep further
+                    if !matches!(
+                        mess,
+                        NetworkCommand::ComputeLandscape(_)
+                            | NetworkCommand::GetStatus
+                            | NetworkCommand::ComputeLocalFields(_)
+                            | NetworkCommand::StepSweep
+                    ) {
+                        cycle_detector.clear();
+                        steps_in_sweep = 0;
+                    }
+                    let result = handle_message(
+                        &mut net,
+                        mess,
+                        &mut is_stepping,
+                        &mut old_step_num,
+                        &mut pacer,
+                        &mut stored_patterns,
+                        &mut original_weights,
+                        &mut setup,
+                        &net_send,
+                    );
+                    let net_state_changed = match result {
+                        Ok(net_state_changed) => {
+                            if let Some(command) = replayed {
+                                checkpoint.commands.push(command);
+                            }
+                            net_state_changed
+                        }
+                        Err(error) => {
+                            if net_send.send(NetworkResponse::Error(error)).is_err() {
+                                return false;
+                            }
+                            // The command may have stopped halfway, the gui gets the state the network ended up in
+                            true
+                        }
+                    };
+                    checkpoint.state = net.get_state();
+                    checkpoint.update_mode = net.get_update_mode();
+                    pacer.restart();
+
+                    // The user may have edited a state the gui was still applying changes to,
+                    // the whole state is sent back so that the two agree again
+                    if net_state_changed || (is_set_state && sync.deltas_sent) {
+                        if !sync.send_state(net.as_ref(), &net_send) {
+                            return false;
+                        }
+                    } else {
+                        // The gui built the new state on its own
+                        sync.reset(net.as_ref());
+                    }
+
+                    if weights_changed && !send_enumeration_availability(net.as_ref(), &net_send) {
+                        return false;
+                    }
 
-                if net_state_changed {
-                    let update = NetworkResponse::NewState(net.get_state().clone());
-                    if net_send.send(update).is_err() {
-                        std_err_fn();
+                    // Sweeps taken one at a time stop where the stepping would have stopped
+                    if is_sweep {
+                        steps_in_sweep = 0;
+                    }
+                    if is_sweep && !net.is_stochastic() && !net.plays_sequences() {
+                        if let Some(cycle) =
+                            record_sweep(&mut cycle_detector, net.as_ref(), old_step_num)
+                        {
+                            if !send_cycle(cycle, &net_send) {
+                                return false;
+                            }
+                        }
                     }
                 }
-            }
 
-            if is_stepping {
-                // The net computes the next state, and than returns a copy to be sent to the main thread, it also computes
-                // if the new state is equal to the old one.
-                let (state_changed, new_state) = net.step();
+                if is_stepping {
+                    // The net computes the next state, and tells us if it's different from the old one,
+                    // the state is copied only if it has to be sent to the main thread
+                    let state_changed = net.step();
+                    if state_changed {
+                        old_step_num = net.get_steps();
+                    }
 
-                if state_changed {
-                    old_step_num = net.get_steps();
-                    if net_send.send(NetworkResponse::NewState(new_state)).is_err() {
-                        std_err_fn();
+                    let steps_per_sweep = experiments::steps_per_sweep(net.as_ref());
+                    steps_in_sweep += 1;
+                    let sweep_completed = steps_in_sweep >= steps_per_sweep;
+                    if sweep_completed {
+                        steps_in_sweep = 0;
                     }
-                } else {
-                    // We assume that is possible for the state to not change after a single step.
-                    // But if after x steps it still has not changed, we assue that we have reached an equilibrium state.
-                    let diff = net.get_steps() - old_step_num;
-                    if diff >= max_steps_without_change {
-                        println!("Stoppped stepping");
-                        is_stepping = false;
-                        if net_send.send(NetworkResponse::Stopped).is_err() {
-                            std_err_fn();
+                    let cycle = if sweep_completed && !net.is_stochastic() && !net.plays_sequences()
+                    {
+                        record_sweep(&mut cycle_detector, net.as_ref(), old_step_num)
+                    } else {
+                        None
+                    };
+
+                    if state_changed {
+                        if sync.is_due() && !sync.flush(net.as_ref(), &net_send) {
+                            return false;
+                        }
+                    } else if !net.is_stochastic() {
+                        // A stochastic network keeps sampling until it's told to stop.
+                        // We assume that is possible for the state to not change after a single step.
+                        // But if after x steps it still has not changed, we assue that we have reached an equilibrium state.
+                        let diff = net.get_steps().saturating_sub(old_step_num);
+                        if diff > steps_per_sweep {
+                            is_stepping = false;
+                            if !sync.flush(net.as_ref(), &net_send)
+                                || net_send.send(NetworkResponse::Stopped).is_err()
+                            {
+                                return false;
+                            }
                         }
-                    } else if net_send.send(NetworkResponse::None).is_err() {
-                        std_err_fn();
                     }
+
+                    if pacer.is_slow(experiments::steps_per_sweep(net.as_ref())) {
+                        send_node_update(net.as_ref(), state_changed, &net_send);
+                    }
+
+                    // The state keeps changing, but it does so going around in circles
+                    if let Some(cycle) = cycle {
+                        if is_stepping {
+                            is_stepping = false;
+                            if !sync.flush(net.as_ref(), &net_send) || !send_cycle(cycle, &net_send)
+                            {
+                                return false;
+                            }
+                        }
+                    }
+
+                    // At max speed the network doesn't wait, and the gui gets only the snapshots
+                    pacer.wait(experiments::steps_per_sweep(net.as_ref()));
                 }
+                true
+            }));
 
-                std::thread::sleep(sleep_time);
+            match iteration {
+                Ok(true) => {}
+                Ok(false) => {
+                    println!("Net thread closed");
+                    return;
+                }
+                Err(payload) => {
+                    is_stepping = false;
+                    cycle_detector.clear();
+                    // If even the checkpoint can't be restored the thread gives up, the gui notices it's gone and starts a new one
+                    if !checkpoint.restore(
+                        &mut net,
+                        &mut stored_patterns,
+                        &mut original_weights,
+                        &mut setup,
+                        &net_send,
+                    ) {
+                        println!("The network couldn't recover from a crash");
+                        return;
+                    }
+                    old_step_num = net.get_steps();
+                    steps_in_sweep = 0;
+                    sync.reset(net.as_ref());
+
+                    let error = NetworkError::Crashed {
+                        during,
+                        message: network_error::panic_message(payload.as_ref()),
+                    };
+                    let responses = [
+                        NetworkResponse::Error(error),
+                        NetworkResponse::EnumerationAvailability(enumeration::unavailable_reason(
+                            net.as_ref(),
+                        )),
+                        NetworkResponse::NewState(net.get_state()),
+                        NetworkResponse::Stopped,
+                    ];
+                    for response in responses {
+                        if net_send.send(response).is_err() {
+                            return;
+                        }
+                    }
+                }
             }
         }
     })
 }
+
+// The gui side of a network thread, the app talks to its own network through one of these,
+// and the comparison starts one for each of the networks it runs
+pub struct NetworkHandle {
+    send_to_net: Sender<NetworkCommand>,
+    recieve_from_net: Receiver<NetworkResponse>,
+    // A stop has to reach a network that is busy training, before the network gets to read it
+    interrupt: Arc<AtomicBool>,
+}
+
+impl NetworkHandle {
+    pub fn spawn(
+        net_type: NetworkType,
+        start_state: Vec<f64>,
+        layer_sizes: Vec<usize>,
+        step_speed: StepSpeed,
+    ) -> NetworkHandle {
+        let (main_send, net_recieve) = mpsc::channel::<NetworkCommand>();
+        let (net_send, main_recieve) = mpsc::channel::<NetworkResponse>();
+        let interrupt = Arc::new(AtomicBool::new(false));
+        start_net_thread(
+            net_type,
+            start_state,
+            layer_sizes,
+            step_speed,
+            net_send,
+            net_recieve,
+            interrupt.clone(),
+        );
+        NetworkHandle {
+            send_to_net: main_send,
+            recieve_from_net: main_recieve,
+            interrupt,
+        }
+    }
+
+    // Returns false if the thread is gone
+    pub fn send(&self, command: NetworkCommand) -> bool {
+        if command == NetworkCommand::Stop {
+            self.interrupt.store(true, Ordering::Relaxed);
+        }
+        self.send_to_net.send(command).is_ok()
+    }
+
+    pub fn try_recv(&self) -> Result<NetworkResponse, mpsc::TryRecvError> {
+        self.recieve_from_net.try_recv()
+    }
+}
+
+// Spaces the steps so that the network keeps the speed on average, the pause after a single step can be
+// shorter than the sleeps of the system allow, so the network steps on a schedule and waits only when it's ahead
+pub struct Pacer {
+    speed: StepSpeed,
+    start: Instant,
+    steps: u64,
+}
+
+impl Pacer {
+    fn new(speed: StepSpeed) -> Pacer {
+        Pacer {
+            speed,
+            start: Instant::now(),
+            steps: 0,
+        }
+    }
+
+    fn set_speed(&mut self, speed: StepSpeed) {
+        self.speed = speed;
+        self.restart();
+    }
+
+    // The time the network spent on something else doesn't have to be made up for
+    fn restart(&mut self) {
+        self.start = Instant::now();
+        self.steps = 0;
+    }
+
+    fn is_slow(&self, steps_per_sweep: usize) -> bool {
+        self.speed
+            .steps_per_second(steps_per_sweep)
+            .map_or(false, |steps_per_second| {
+                steps_per_second <= MAX_HIGHLIGHT_SPEED
+            })
+    }
+
+    fn wait(&mut self, steps_per_sweep: usize) {
+        let steps_per_second = match self.speed.steps_per_second(steps_per_sweep) {
+            Some(steps_per_second) => steps_per_second,
+            None => return,
+        };
+        self.steps += 1;
+        let due = self.start + Duration::from_secs_f64(self.steps as f64 / steps_per_second);
+        let now = Instant::now();
+        if due > now {
+            std::thread::sleep(due - now);
+        }
+    }
+}
+
+// Keeps track of the state the gui is showing, so that only the nodes that changed since then are sent
+struct StateSync {
+    sent_state: Vec<f64>,
+    last_sent: Instant,
+    // True if the gui was sent changes since the last whole state, it applies them to the state it has,
+    // even if the user edited it in the meantime
+    deltas_sent: bool,
+}
+
+impl StateSync {
+    fn new(net: &dyn Net<f64>) -> StateSync {
+        StateSync {
+            sent_state: net.get_state(),
+            last_sent: Instant::now(),
+            deltas_sent: false,
+        }
+    }
+
+    fn is_due(&self) -> bool {
+        self.last_sent.elapsed() >= SNAPSHOT_INTERVAL
+    }
+
+    // Sends the nodes that changed since the last time, if there are any.
+    // Returns false if the gui is gone
+    fn flush(&mut self, net: &dyn Net<f64>, net_send: &Sender<NetworkResponse>) -> bool {
+        let state = net.get_state_ref();
+        if state.len() != self.sent_state.len() {
+            return self.send_state(net, net_send);
+        }
+        let changes: Vec<(usize, f64)> = state
+            .iter()
+            .zip(self.sent_state.iter())
+            .enumerate()
+            .filter(|(_, (new, old))| new != old)
+            .map(|(i, (new, _))| (i, *new))
+            .collect();
+        if changes.is_empty() {
+            return true;
+        }
+        // When most of the nodes changed, the whole state is smaller than the list of changes
+        if changes.len() > state.len() / 2 {
+            return self.send_state(net, net_send);
+        }
+
+        for (i, value) in changes.iter() {
+            self.sent_state[*i] = *value;
+        }
+        self.last_sent = Instant::now();
+        self.deltas_sent = true;
+        net_send.send(NetworkResponse::StateDelta(changes)).is_ok()
+    }
+
+    fn send_state(&mut self, net: &dyn Net<f64>, net_send: &Sender<NetworkResponse>) -> bool {
+        self.reset(net);
+        self.last_sent = Instant::now();
+        net_send
+            .send(NetworkResponse::NewState(net.get_state()))
+            .is_ok()
+    }
+
+    // The gui already has the state of the network
+    fn reset(&mut self, net: &dyn Net<f64>) {
+        self.sent_state.clear();
+        self.sent_state.extend_from_slice(net.get_state_ref());
+        self.deltas_sent = false;
+    }
+}
+
+// What the network goes back to if it crashes. Copying the weights after every command that changes them
+// would cost as much as a learn, so the checkpoint keeps the commands that built the weights instead,
+// and a new network goes through them again. The damage and the dreams are random, they are done again
+// with the same amounts but not on the same weights
+struct Checkpoint {
+    // The network the thread started with
+    net_type: NetworkType,
+    layer_sizes: Vec<usize>,
+    start_state: Vec<f64>,
+    // Every command since then that changed the weights, the layers or the way the network learns
+    commands: Vec<NetworkCommand>,
+    // Where the network was after the last command that didn't crash
+    state: Vec<f64>,
+    update_mode: UpdateMode,
+}
+
+impl Checkpoint {
+    fn new(net_type: NetworkType, layer_sizes: Vec<usize>, net: &dyn Net<f64>) -> Checkpoint {
+        Checkpoint {
+            net_type,
+            layer_sizes,
+            start_state: net.get_state(),
+            commands: Vec::new(),
+            state: net.get_state(),
+            update_mode: net.get_update_mode(),
+        }
+    }
+
+    // True if the command has to be gone through again to build the network back
+    fn replays(&self, net: &dyn Net<f64>, command: &NetworkCommand) -> bool {
+        match command {
+            NetworkCommand::SetState(state) => state.len() != net.get_state_ref().len(),
+            NetworkCommand::SetUpdateMode(_) => true,
+            command => changes_weights(command),
+        }
+    }
+
+    // Builds the network from the start and goes through the commands again, their responses don't reach the gui.
+    // Returns false if the network crashed again while going back
+    fn restore(
+        &self,
+        net: &mut Box<dyn Net<f64>>,
+        stored_patterns: &mut Vec<Vec<f64>>,
+        original_weights: &mut Option<Vec<Matrix<f64>>>,
+        setup: &mut NetSetup,
+        net_send: &Sender<NetworkResponse>,
+    ) -> bool {
+        let (replay_send, _replay_recieve) = mpsc::channel();
+        let rebuilt = panic::catch_unwind(AssertUnwindSafe(|| {
+            *net = build_network(self.net_type, &self.layer_sizes, Precision::F64)?;
+            net.set_state(&self.start_state)?;
+            *setup = NetSetup {
+                net_type: Some(self.net_type),
+                precision: Precision::F64,
+                learning_rule: LearningRule::default(),
+                interrupt: setup.interrupt.clone(),
+            };
+            configure_learning(net.as_mut(), setup, &replay_send);
+            stored_patterns.clear();
+            *original_weights = None;
+
+            let mut is_stepping = false;
+            let mut old_step_num = 0;
+            let mut pacer = Pacer::new(StepSpeed::Max);
+            for command in self.commands.iter() {
+                handle_message(
+                    net,
+                    command.clone(),
+                    &mut is_stepping,
+                    &mut old_step_num,
+                    &mut pacer,
+                    stored_patterns,
+                    original_weights,
+                    setup,
+                    &replay_send,
+                )?;
+            }
+            // The training progress goes to the gui again
+            configure_learning(net.as_mut(), setup, net_send);
+            net.set_update_mode(self.update_mode);
+            net.set_state(&self.state)
+        }));
+        matches!(rebuilt, Ok(Ok(())))
+    }
+}
+
+// The commands that would make the network crash are refused before they get to it,
+// the states that don't fit a network are refused by the network itself
+fn validate_command(
+    net: &dyn Net<f64>,
+    net_type: Option<NetworkType>,
+    command: &NetworkCommand,
+) -> Result<(), NetworkError> {
+    let size = net.get_state_ref().len();
+    match command {
+        NetworkCommand::SetState(state) if state.len() < hop_net::MIN_STATE_SIZE => {
+            Err(NetworkError::StateTooShort {
+                len: state.len(),
+                min: hop_net::MIN_STATE_SIZE,
+            })
+        }
+        NetworkCommand::SetLayerSizes(sizes)
+            if sizes.iter().sum::<usize>() < hop_net::MIN_STATE_SIZE =>
+        {
+            Err(NetworkError::StateTooShort {
+                len: sizes.iter().sum(),
+                min: hop_net::MIN_STATE_SIZE,
+            })
+        }
+        // Only the optimization network has no type
+        NetworkCommand::Learn(_) if net_type.is_none() => Err(NetworkError::NotLearnable),
+        NetworkCommand::LearnAll(patterns) if net_type.is_none() && !patterns.is_empty() => {
+            Err(NetworkError::NotLearnable)
+        }
+        NetworkCommand::Learn(pattern) if pattern.len() != size => {
+            Err(NetworkError::StateSizeMismatch {
+                expected: size,
+                found: pattern.len(),
+            })
+        }
+        NetworkCommand::LearnAll(patterns) => {
+            match patterns.iter().find(|pattern| pattern.len() != size) {
+                Some(pattern) => Err(NetworkError::StateSizeMismatch {
+                    expected: size,
+                    found: pattern.len(),
+                }),
+                None => Ok(()),
+            }
+        }
+        NetworkCommand::ComputeLandscape(plane) => {
+            match [&plane.origin, &plane.axes[0], &plane.axes[1]]
+                .into_iter()
+                .find(|vector| vector.len() != size)
+            {
+                Some(vector) => Err(NetworkError::StateSizeMismatch {
+                    expected: size,
+                    found: vector.len(),
+                }),
+                None => Ok(()),
+            }
+        }
+        NetworkCommand::EnumerateStates => match enumeration::unavailable_reason(net) {
+            Some(reason) => Err(NetworkError::NotEnumerable(reason)),
+            None => Ok(()),
+        },
+        NetworkCommand::Unlearn(_) => match unlearning::unavailable_reason(net_type) {
+            Some(reason) => Err(NetworkError::NotUnlearnable(reason)),
+            None => Ok(()),
+        },
+        NetworkCommand::Solve(config)
+            if config.problem.neuron_count() < hop_net::MIN_STATE_SIZE =>
+        {
+            Err(NetworkError::StateTooShort {
+                len: config.problem.neuron_count(),
+                min: hop_net::MIN_STATE_SIZE,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+// The commands that change the weights, the checkpoint goes through them again after a crash
+pub fn changes_weights(command: &NetworkCommand) -> bool {
+    matches!(
+        command,
+        NetworkCommand::Learn(_)
+            | NetworkCommand::LearnAll(_)
+            | NetworkCommand::ResetWeights
+            | NetworkCommand::ChangeNetType(_)
+            | NetworkCommand::SetLayerSizes(_)
+            | NetworkCommand::Solve(_)
+            | NetworkCommand::SetPrecision(_)
+            | NetworkCommand::SetLearningRule(_)
+            | NetworkCommand::Unlearn(_)
+            | NetworkCommand::Damage(_)
+            | NetworkCommand::RestoreWeights
+            | NetworkCommand::Symmetrize
+            | NetworkCommand::ClearDiagonal
+    )
+}
+
+// The debug text of a command, without its arguments (the states would take a whole screen)
+fn command_name(command: &NetworkCommand) -> String {
+    let text = format!("{:?}", command);
+    text.split('(').next().unwrap_or_default().to_string()
+}
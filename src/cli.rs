@@ -0,0 +1,119 @@
+// Runs the experiments from the command line, without the gui:
+//     hopfield_nets recall PATTERNS PROBES [--storkey] [--synchronous] [--max-sweeps N]
+// The files have a pattern per line, + or 1 for the nodes that are on and - or 0 for the ones that are off,
+// every other character is skipped so the nodes can be spaced out. Empty lines and lines starting with # are ignored.
+// The network learns the patterns, then the probes are recalled all at once, on every thread of the machine
+use crate::app::hop_net::batch_recall::RecallOptions;
+use crate::app::hop_net::scalar::Precision;
+use crate::app::hop_net::NetworkType;
+use crate::app::hop_net::UpdateMode;
+use crate::app::thread_utils;
+
+pub const USAGE: &str =
+    "usage: hopfield_nets recall PATTERNS PROBES [--storkey] [--synchronous] [--max-sweeps N]";
+
+// The arguments come without the name of the program, the results are printed on the standard output
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(|command| command.as_str()) {
+        Some("recall") => recall(&args[1..]),
+        Some(command) => Err(format!("Unknown command {}", command)),
+        None => Err("No command given".to_string()),
+    }
+}
+
+// Prints a line per probe, with the state it ended in written like the patterns, so it can be read back
+fn recall(args: &[String]) -> Result<(), String> {
+    let mut net_type = NetworkType::SquareDiscrete;
+    let mut options = RecallOptions::default();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--storkey" => net_type = NetworkType::StorkeySquareDiscrete,
+            "--synchronous" => options.update_mode = UpdateMode::Synchronous,
+            "--max-sweeps" => {
+                options.max_sweeps = args
+                    .next()
+                    .and_then(|sweeps| sweeps.parse().ok())
+                    .ok_or("--max-sweeps needs a number")?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => files.push(arg.as_str()),
+        }
+    }
+    let (patterns, probes) = match files[..] {
+        [patterns, probes] => (read_patterns(patterns)?, read_patterns(probes)?),
+        _ => return Err("recall needs a file of patterns and one of probes".to_string()),
+    };
+    let size = match patterns.first() {
+        Some(pattern) => pattern.len(),
+        None => return Err("There are no patterns to learn".to_string()),
+    };
+    if let Some(pattern) = patterns.iter().find(|pattern| pattern.len() != size) {
+        return Err(format!(
+            "The patterns have to be the same size, found one of {} nodes and one of {}",
+            size,
+            pattern.len()
+        ));
+    }
+
+    let mut net = thread_utils::build_network(net_type, &[size], Precision::F64)
+        .map_err(|error| error.to_string())?;
+    net.learn_all(&patterns);
+    let results = net
+        .batch_recall(&probes, &patterns, options)
+        .ok_or("The network can't recall in batches")?
+        .map_err(|error| error.to_string())?;
+
+    println!("probe\tsteps\tconverged\tpattern\tstate");
+    for (i, result) in results.iter().enumerate() {
+        let pattern = match result.matched_pattern {
+            Some(pattern) => (pattern + 1).to_string(),
+            None => "-".to_string(),
+        };
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            i + 1,
+            result.steps,
+            result.converged,
+            pattern,
+            pattern_to_string(&result.state)
+        );
+    }
+    let recalled = results
+        .iter()
+        .filter(|result| result.matched_pattern.is_some())
+        .count();
+    println!(
+        "{} of {} probes recalled a pattern",
+        recalled,
+        results.len()
+    );
+    Ok(())
+}
+
+fn read_patterns(path: &str) -> Result<Vec<Vec<f64>>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|error| format!("Can't read {}: {}", path, error))?;
+    Ok(text
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.chars()
+                .filter_map(|c| match c {
+                    '+' | '1' => Some(1.0),
+                    '-' | '0' => Some(-1.0),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn pattern_to_string(pattern: &[f64]) -> String {
+    pattern
+        .iter()
+        .map(|node| if *node > 0.0 { '+' } else { '-' })
+        .collect()
+}
@@ -5,3 +5,8 @@
 #![allow(clippy::collapsible_if)]
 mod app;
 pub use app::HopfiledNetsApp;
+// The networks are public so that the benchmarks can use them
+pub use app::hop_net;
+// The experiments that can be run without the gui, only natively
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
@@ -4,6 +4,16 @@
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
+    // With arguments the app runs an experiment from the command line instead of opening the gui
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(error) = hopfield_nets::cli::run(&args) {
+            eprintln!("{}\n{}", error, hopfield_nets::cli::USAGE);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Log to stdout (if you run with `RUST_LOG=debug`).
     tracing_subscriber::fmt::init();
 